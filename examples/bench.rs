@@ -1,6 +1,5 @@
-use example_lib::{Example, WgpuDevice};
 use glam::vec2;
-use mondrian::backend::wgpu::WgpuRenderer;
+use mondrian::runner::{App, Context, RunnerOptions};
 
 fn main() {
     const NUM_FRAMES: usize = 10000;
@@ -9,7 +8,12 @@ fn main() {
         current_frame: 0,
         start_time: std::time::Instant::now(),
     };
-    example_lib::run_example(wgpu::PresentMode::Immediate, app);
+    example_lib::run_example(
+        RunnerOptions::default()
+            .with_title("Mondrian 10k Circles Benchmark")
+            .with_present_mode(wgpu::PresentMode::Immediate),
+        app,
+    );
 }
 
 struct BenchApp {
@@ -18,18 +22,9 @@ struct BenchApp {
     start_time: std::time::Instant,
 }
 
-impl Example for BenchApp {
-    fn name(&self) -> &str {
-        "Mondrian 10k Circles Benchmark"
-    }
-
-    fn draw(
-        &mut self,
-        painter: &mut mondrian::Painter,
-        _dev: &WgpuDevice,
-        _renderer: &mut WgpuRenderer,
-        resolution: (u32, u32),
-    ) {
+impl App for BenchApp {
+    fn draw(&mut self, painter: &mut mondrian::Painter, context: &mut Context) {
+        let resolution = context.resolution();
         for _ in 0..10000 {
             let x = fastrand::f32() * resolution.0 as f32;
             let y = fastrand::f32() * resolution.1 as f32;
@@ -0,0 +1,79 @@
+//! Headless benchmark comparing bounding box binning against precise per-primitive tile coverage.
+
+use glam::{Vec2, vec2};
+use mondrian::{
+    Shape,
+    binner::{BinningMode, ShapeBinner},
+};
+
+const RESOLUTION: (u32, u32) = (1920, 1080);
+const NUM_SHAPES: usize = 2000;
+const NUM_ITERATIONS: usize = 100;
+
+fn main() {
+    let shapes = generate_shapes();
+
+    for mode in [BinningMode::BoundingBox, BinningMode::Precise] {
+        let mut binner = ShapeBinner::new(32, RESOLUTION);
+        binner.mode = mode;
+
+        let start_time = std::time::Instant::now();
+        for _ in 0..NUM_ITERATIONS {
+            binner.bin_shapes(&shapes);
+        }
+        let elapsed = start_time.elapsed().as_secs_f32() / NUM_ITERATIONS as f32;
+
+        println!(
+            "{:<12} shape_indices: {:>8} ({:>7.1} per tile), binning took {:.3} ms",
+            format!("{mode:?}"),
+            binner.shape_indices.len(),
+            binner.shape_indices.len() as f32 / binner.shapes_by_tile.len() as f32,
+            elapsed * 1000.0
+        );
+    }
+}
+
+/// Generates long diagonal lines, thin rotated quads and rings, the worst cases for bounding box binning
+fn generate_shapes() -> Vec<Shape> {
+    let mut painter = mondrian::Painter::new();
+    let mut shapes = Vec::with_capacity(NUM_SHAPES);
+    painter.start(RESOLUTION);
+
+    let screen = vec2(RESOLUTION.0 as f32, RESOLUTION.1 as f32);
+    let random_point = || vec2(fastrand::f32(), fastrand::f32()) * screen;
+    let color = [1.0, 1.0, 1.0, 1.0];
+    for i in 0..NUM_SHAPES {
+        match i % 3 {
+            0 => {
+                painter.add_filled_line(random_point(), random_point(), 2.0, color);
+            }
+            1 => {
+                let center = random_point();
+                let dir = Vec2::from_angle(fastrand::f32() * std::f32::consts::TAU);
+                let along = dir * 300.0;
+                let across = dir.perp() * 4.0;
+                painter.add_filled_polyquad(
+                    center - along - across,
+                    center + along - across,
+                    center + along + across,
+                    center - along + across,
+                    color,
+                );
+            }
+            _ => {
+                let radius = 100.0 + fastrand::f32() * 200.0;
+                painter.add_filled_circle_sector(
+                    random_point(),
+                    radius - 4.0,
+                    radius,
+                    0.0,
+                    std::f32::consts::TAU,
+                    color,
+                );
+            }
+        }
+    }
+
+    painter.finish(|painted, _| shapes.extend_from_slice(painted));
+    shapes
+}
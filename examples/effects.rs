@@ -1,6 +1,10 @@
-use example_lib::{Example, WgpuDevice, load_rgba_texture};
+use example_lib::load_rgba_texture;
 use glam::vec3;
-use mondrian::{TextureId, backend::wgpu::WgpuRenderer};
+use mondrian::{
+    TextureId,
+    backend::sampler::SamplerOptions,
+    runner::{App, Context, RunnerOptions},
+};
 use slotmap::Key;
 
 fn main() {
@@ -9,7 +13,10 @@ fn main() {
         texture_sdf: TextureId::null(),
     };
 
-    example_lib::run_example(wgpu::PresentMode::Fifo, app)
+    example_lib::run_example(
+        RunnerOptions::default().with_title("Mondrian Shape Effects Example"),
+        app,
+    )
 }
 
 struct ExampleApp {
@@ -17,26 +24,22 @@ struct ExampleApp {
     texture_sdf: TextureId,
 }
 
-impl Example for ExampleApp {
-    fn name(&self) -> &str {
-        "Mondrian Shape Effects Example"
+impl App for ExampleApp {
+    fn init(&mut self, context: &mut Context) {
+        self.texture_mtsdf = load_rgba_texture(
+            context,
+            include_bytes!("textures/mtsdf.rgba"),
+            SamplerOptions::default(),
+        );
+        self.texture_sdf = load_rgba_texture(
+            context,
+            include_bytes!("textures/sdf.rgba"),
+            SamplerOptions::default(),
+        );
     }
 
-    fn draw(
-        &mut self,
-        painter: &mut mondrian::Painter,
-        dev: &WgpuDevice,
-        renderer: &mut WgpuRenderer,
-        resolution: (u32, u32),
-    ) {
-        if self.texture_mtsdf.is_null() {
-            self.texture_mtsdf =
-                load_rgba_texture(dev, renderer, include_bytes!("textures/mtsdf.rgba"));
-        }
-        if self.texture_sdf.is_null() {
-            self.texture_sdf =
-                load_rgba_texture(dev, renderer, include_bytes!("textures/sdf.rgba"));
-        }
+    fn draw(&mut self, painter: &mut mondrian::Painter, context: &mut Context) {
+        let resolution = context.resolution();
 
         // White background
         painter.add_filled_rect(
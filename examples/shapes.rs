@@ -1,38 +1,36 @@
 use std::f32::consts::TAU;
 
-use example_lib::{Example, WgpuDevice};
 use glam::{Vec2, vec2, vec3};
-use mondrian::backend::wgpu::WgpuRenderer;
+use mondrian::{
+    GroupStyle,
+    runner::{App, Context, RunnerOptions},
+};
 
 fn main() {
     let app = ExampleApp {
         start_time: std::time::Instant::now(),
     };
 
-    example_lib::run_example(wgpu::PresentMode::Fifo, app)
+    example_lib::run_example(
+        RunnerOptions::default()
+            .with_title("Mondrian Shapes Example")
+            .with_logical_pixels(true),
+        app,
+    )
 }
 
 struct ExampleApp {
     start_time: std::time::Instant,
 }
 
-impl Example for ExampleApp {
-    fn name(&self) -> &str {
-        "Mondrian Shapes Example"
-    }
-
-    fn draw(
-        &mut self,
-        painter: &mut mondrian::Painter,
-        _dev: &WgpuDevice,
-        _renderer: &mut WgpuRenderer,
-        resolution: (u32, u32),
-    ) {
+impl App for ExampleApp {
+    fn draw(&mut self, painter: &mut mondrian::Painter, context: &mut Context) {
+        let size = context.logical_size();
         let time = self.start_time.elapsed().as_secs_f32();
 
         painter.add_filled_rect(
             [0.0, 0.0],
-            [resolution.0 as f32, resolution.1 as f32],
+            [size.0, size.1],
             0.0,
             vec3(0.1, 0.1, 0.15).extend(1.0),
         );
@@ -75,6 +73,17 @@ impl Example for ExampleApp {
         painter.add_circle([180.0, 530.0], 25.0, yellow, 2.0);
         painter.end_group();
 
+        // Nested groups with an explicit style. The outline is joined around the circles of the outer group, while the
+        // nested group is drawn as a separate glowing shape on top of them.
+        painter.begin_group_with_style(GroupStyle::new(yellow).with_line_width(3.0));
+        painter.add_filled_circle([260.0, 500.0], 25.0, blue);
+        painter.add_filled_circle([300.0, 500.0], 25.0, blue);
+        painter.begin_group_with_style(GroupStyle::new(blue).with_glow(vec3(0.0, 0.5, 1.0), 8.0));
+        painter.add_filled_circle([280.0, 530.0], 12.0, yellow);
+        painter.add_filled_circle([300.0, 530.0], 12.0, yellow);
+        painter.end_group();
+        painter.end_group();
+
         painter.add_rect_center_size(
             [450.0, 220.0],
             [50.0, 50.0],
@@ -118,7 +127,7 @@ impl Example for ExampleApp {
         );
 
         let top_left = Vec2::ZERO;
-        let bottom_right = vec2(resolution.0 as f32, resolution.1 as f32);
+        let bottom_right = vec2(size.0, size.1);
         let top_right = vec2(bottom_right.x, top_left.y);
         let bottom_left = vec2(top_left.x, bottom_right.y);
 
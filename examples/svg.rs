@@ -0,0 +1,49 @@
+use mondrian::{
+    runner::{App, Context, RunnerOptions},
+    svg::SvgImage,
+};
+
+/// Shown when no SVG file is passed on the command line
+const ICON: &str = r##"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 64 64" width="64" height="64">
+  <rect x="4" y="4" width="56" height="56" rx="12" fill="#2d3142" stroke="#bfc0c0" stroke-width="2"/>
+  <g transform="rotate(-15 32 32)">
+    <circle cx="32" cy="28" r="14" fill="#ef8354"/>
+    <ellipse cx="32" cy="46" rx="16" ry="5" fill="#4f5d75"/>
+  </g>
+  <path d="M20 28 a12 12 0 0 1 24 0" fill="none" stroke="white" stroke-width="3"/>
+  <path d="M14 54 C 24 40, 40 62, 50 48" fill="none" stroke="#bfc0c0" stroke-width="2"/>
+  <polygon points="26,24 38,24 32,34" fill="white" fill-opacity="0.8"/>
+</svg>"##;
+
+fn main() {
+    let svg = match std::env::args().nth(1) {
+        Some(path) => std::fs::read_to_string(path).expect("Failed to read SVG file"),
+        None => ICON.to_string(),
+    };
+    let image = SvgImage::parse(&svg).expect("Failed to parse SVG");
+    for feature in image.unsupported() {
+        println!("Not supported: {feature}");
+    }
+
+    example_lib::run_example(
+        RunnerOptions::default().with_title("Mondrian SVG Example"),
+        ExampleApp { image },
+    )
+}
+
+struct ExampleApp {
+    image: SvgImage,
+}
+
+impl App for ExampleApp {
+    fn draw(&mut self, painter: &mut mondrian::Painter, context: &mut Context) {
+        let resolution = context.resolution();
+        // The same image at increasing scales, fitted to the window height at the largest one
+        let largest = (resolution.1 as f32 - 80.0) / self.image.size.y.max(1.0);
+        let mut x = 40.0;
+        for scale in [0.25, 0.5, 1.0].map(|s| s * largest) {
+            self.image.paint(painter, [x, 40.0], scale);
+            x += self.image.size.x * scale + 40.0;
+        }
+    }
+}
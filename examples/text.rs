@@ -0,0 +1,132 @@
+use glam::vec3;
+use mondrian::{
+    TextAlign, TextStyle,
+    runner::{App, Context, RunnerOptions},
+    text::{GlyphCache, GlyphCacheOptions},
+};
+
+/// Fonts tried when no font file is passed on the command line
+const SYSTEM_FONTS: &[&str] = &[
+    "/usr/share/fonts/truetype/dejavu/DejaVuSans.ttf",
+    "/usr/share/fonts/TTF/DejaVuSans.ttf",
+    "/System/Library/Fonts/Supplemental/Arial.ttf",
+    "C:\\Windows\\Fonts\\arial.ttf",
+];
+
+const PARAGRAPH: &str = "Mondrian draws every glyph as a textured rect, using multi-channel signed distance \
+    fields generated from the font outlines at runtime. Text stays crisp at any size.";
+
+fn main() {
+    let path = std::env::args()
+        .nth(1)
+        .or_else(|| {
+            SYSTEM_FONTS
+                .iter()
+                .find(|path| std::path::Path::new(path).exists())
+                .map(|path| path.to_string())
+        })
+        .expect("No font found, pass the path to a .ttf or .otf file as the first argument");
+    let font_data = std::fs::read(&path).expect("Failed to read font file");
+
+    let app = ExampleApp {
+        font_data: Some(font_data),
+        glyph_cache: None,
+        start_time: std::time::Instant::now(),
+    };
+
+    example_lib::run_example(
+        RunnerOptions::default().with_title("Mondrian Text Example"),
+        app,
+    )
+}
+
+struct ExampleApp {
+    font_data: Option<Vec<u8>>,
+    glyph_cache: Option<GlyphCache>,
+    start_time: std::time::Instant,
+}
+
+impl App for ExampleApp {
+    fn init(&mut self, context: &mut Context) {
+        self.glyph_cache = Some(
+            GlyphCache::new(
+                context.device,
+                context.queue,
+                context.renderer,
+                self.font_data.take().unwrap(),
+                0,
+                GlyphCacheOptions::default(),
+            )
+            .expect("Failed to load font"),
+        );
+    }
+
+    fn draw(&mut self, painter: &mut mondrian::Painter, context: &mut Context) {
+        let resolution = context.resolution();
+        let cache = self.glyph_cache.as_mut().unwrap();
+        let time = self.start_time.elapsed().as_secs_f32();
+
+        let lines = [
+            (12.0, "The quick brown fox jumps over the lazy dog"),
+            (24.0, "The quick brown fox jumps over the lazy dog"),
+            (48.0, "AVATAR Wavy Type 0123456789"),
+        ];
+        let mut y = 40.0;
+        for (size, text) in lines {
+            cache
+                .prepare(context.device, context.queue, context.renderer, text)
+                .expect("Failed to prepare glyphs");
+            let layout = painter.add_text(
+                cache.font(),
+                [40.0, y],
+                text,
+                &TextStyle::new(size, [1.0, 1.0, 1.0, 1.0]),
+            );
+            y += layout.size.y + 16.0;
+        }
+
+        // Weight, outline and shadow, applied to the glyph distance fields
+        let styled = [
+            TextStyle::new(40.0, [1.0, 1.0, 1.0, 1.0]).with_distance_offset(-1.0),
+            TextStyle::new(40.0, [1.0, 1.0, 1.0, 1.0]).with_distance_offset(0.6),
+            TextStyle::new(40.0, [0.3, 0.8, 1.0, 1.0]).with_line_width(1.5),
+            TextStyle::new(40.0, [1.0, 1.0, 1.0, 1.0]).with_shadow(0.8, 6.0),
+            TextStyle::new(40.0, [1.0, 1.0, 1.0, 1.0]).with_glow(vec3(1.0, 0.4, 0.0), 6.0),
+        ];
+        let mut x = 40.0;
+        for (style, text) in styled
+            .iter()
+            .zip(["Bold", "Thin", "Outline", "Shadow", "Glow"])
+        {
+            cache
+                .prepare(context.device, context.queue, context.renderer, text)
+                .expect("Failed to prepare glyphs");
+            let layout = painter.add_text(cache.font(), [x, y], text, style);
+            x += layout.size.x + 32.0;
+        }
+        y += 64.0;
+
+        // Wrapped paragraphs with each alignment, in columns that change width over time
+        let column_width = (resolution.0 as f32 - 160.0) / 3.0 * (0.75 + 0.25 * time.sin());
+        cache
+            .prepare(context.device, context.queue, context.renderer, PARAGRAPH)
+            .expect("Failed to prepare glyphs");
+        for (i, align) in [TextAlign::Left, TextAlign::Center, TextAlign::Right]
+            .into_iter()
+            .enumerate()
+        {
+            let x = 40.0 + i as f32 * (column_width + 40.0);
+            let style = TextStyle::new(18.0, [0.9, 0.8, 0.3, 1.0])
+                .with_align(align)
+                .with_max_width(column_width);
+            let layout = painter.add_text(cache.font(), [x, y], PARAGRAPH, &style);
+            painter.add_rect(
+                [x, y],
+                [x + column_width, y + layout.size.y],
+                0.0,
+                [0.3, 0.3, 0.3, 1.0],
+                1.0,
+            );
+        }
+    }
+}
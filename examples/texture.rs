@@ -1,7 +1,13 @@
 use std::f32::consts::TAU;
 
-use example_lib::{Example, WgpuDevice, load_rgba_texture};
-use mondrian::{backend::wgpu::WgpuRenderer, shape::TextureId};
+use example_lib::{load_rgba_atlas_texture, load_rgba_texture};
+use glam::vec2;
+use mondrian::{
+    TextureFit,
+    backend::sampler::{SamplerOptions, TextureAddressMode},
+    runner::{App, Context, RunnerOptions},
+    shape::TextureId,
+};
 use slotmap::Key;
 
 fn main() {
@@ -9,37 +15,42 @@ fn main() {
         start_time: std::time::Instant::now(),
         texture_id1: TextureId::null(),
         texture_id2: TextureId::null(),
+        texture_id3: TextureId::null(),
     };
 
-    example_lib::run_example(wgpu::PresentMode::Fifo, app)
+    example_lib::run_example(
+        RunnerOptions::default().with_title("Mondrian Texture Example"),
+        app,
+    )
 }
 
 struct ExampleApp {
     start_time: std::time::Instant,
     texture_id1: TextureId,
     texture_id2: TextureId,
+    texture_id3: TextureId,
 }
 
-impl Example for ExampleApp {
-    fn name(&self) -> &str {
-        "Mondrian Texture Example"
+impl App for ExampleApp {
+    fn init(&mut self, context: &mut Context) {
+        self.texture_id1 = load_rgba_texture(
+            context,
+            include_bytes!("textures/painting.rgba"),
+            SamplerOptions::default(),
+        );
+        self.texture_id2 = load_rgba_atlas_texture(
+            context,
+            include_bytes!("textures/mondrian.rgba"),
+            SamplerOptions::default(),
+        );
+        self.texture_id3 = load_rgba_atlas_texture(
+            context,
+            include_bytes!("textures/mondrian.rgba"),
+            SamplerOptions::NEAREST.with_address_mode(TextureAddressMode::MirrorRepeat),
+        );
     }
 
-    fn draw(
-        &mut self,
-        painter: &mut mondrian::Painter,
-        dev: &WgpuDevice,
-        renderer: &mut WgpuRenderer,
-        _resolution: (u32, u32),
-    ) {
-        if self.texture_id1.is_null() {
-            self.texture_id1 =
-                load_rgba_texture(dev, renderer, include_bytes!("textures/painting.rgba"));
-        }
-        if self.texture_id2.is_null() {
-            self.texture_id2 =
-                load_rgba_texture(dev, renderer, include_bytes!("textures/mondrian.rgba"));
-        }
+    fn draw(&mut self, painter: &mut mondrian::Painter, _context: &mut Context) {
         let time = self.start_time.elapsed().as_secs_f32();
 
         painter
@@ -89,5 +100,34 @@ impl Example for ExampleApp {
         painter
             .add_filled_line([570.0, 320.0], [730.0, 480.0], 12.0, [0.0, 1.0, 1.0, 1.0])
             .with_texture_id(self.texture_id1);
+
+        // Top-left quarter of the texture, flipped horizontally
+        painter
+            .add_filled_rect([650.0, 550.0], [800.0, 700.0], 16.0, [1.0, 1.0, 1.0, 1.0])
+            .with_texture_id(self.texture_id1)
+            .with_texture_uv([0.0, 0.0], [0.5, 0.5])
+            .with_texture_flip(true, false);
+
+        // Aspect-preserving fits in a wide rect
+        painter
+            .add_filled_rect([850.0, 550.0], [1100.0, 650.0], 0.0, [1.0, 1.0, 1.0, 1.0])
+            .with_texture_id(self.texture_id1)
+            .with_texture_fit(TextureFit::Contain);
+        painter
+            .add_filled_rect([850.0, 670.0], [1100.0, 770.0], 0.0, [1.0, 1.0, 1.0, 1.0])
+            .with_texture_id(self.texture_id1)
+            .with_texture_fit(TextureFit::Cover);
+
+        // Repeating pattern
+        painter
+            .add_filled_circle([1000.0, 400.0], 100.0, [1.0, 1.0, 1.0, 1.0])
+            .with_texture_id(self.texture_id2)
+            .with_texture_fit(TextureFit::Tile(vec2(40.0, 40.0)));
+
+        // Nearest filtering, mirrored three times horizontally and twice vertically
+        painter
+            .add_filled_rect([850.0, 800.0], [1100.0, 900.0], 0.0, [1.0, 1.0, 1.0, 1.0])
+            .with_texture_id(self.texture_id3)
+            .with_texture_uv([0.0, 0.0], [3.0, 2.0]);
     }
 }
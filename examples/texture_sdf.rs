@@ -1,5 +1,9 @@
-use example_lib::{Example, WgpuDevice, load_rgba_texture};
-use mondrian::{backend::wgpu::WgpuRenderer, shape::TextureId};
+use example_lib::load_rgba_texture;
+use mondrian::{
+    backend::sampler::SamplerOptions,
+    runner::{App, Context, RunnerOptions},
+    shape::TextureId,
+};
 use slotmap::Key;
 
 fn main() {
@@ -8,7 +12,10 @@ fn main() {
         texture_sdf: TextureId::null(),
     };
 
-    example_lib::run_example(wgpu::PresentMode::Fifo, app)
+    example_lib::run_example(
+        RunnerOptions::default().with_title("Mondrian (MT)SDF Texture Example"),
+        app,
+    )
 }
 
 struct ExampleApp {
@@ -16,27 +23,21 @@ struct ExampleApp {
     texture_sdf: TextureId,
 }
 
-impl Example for ExampleApp {
-    fn name(&self) -> &str {
-        "Mondrian (MT)SDF Texture Example"
+impl App for ExampleApp {
+    fn init(&mut self, context: &mut Context) {
+        self.texture_mtsdf = load_rgba_texture(
+            context,
+            include_bytes!("textures/mtsdf.rgba"),
+            SamplerOptions::default(),
+        );
+        self.texture_sdf = load_rgba_texture(
+            context,
+            include_bytes!("textures/sdf.rgba"),
+            SamplerOptions::default(),
+        );
     }
 
-    fn draw(
-        &mut self,
-        painter: &mut mondrian::Painter,
-        dev: &WgpuDevice,
-        renderer: &mut WgpuRenderer,
-        _resolution: (u32, u32),
-    ) {
-        if self.texture_mtsdf.is_null() {
-            self.texture_mtsdf =
-                load_rgba_texture(dev, renderer, include_bytes!("textures/mtsdf.rgba"));
-        }
-        if self.texture_sdf.is_null() {
-            self.texture_sdf =
-                load_rgba_texture(dev, renderer, include_bytes!("textures/sdf.rgba"));
-        }
-
+    fn draw(&mut self, painter: &mut mondrian::Painter, _context: &mut Context) {
         // MTSDF
         painter
             .add_filled_rect_center_size([200.0, 200.0], [200.0, 200.0], 0.0, [1.0, 1.0, 1.0, 1.0])
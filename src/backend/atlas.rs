@@ -0,0 +1,347 @@
+use glam::{UVec2, Vec4, uvec2};
+
+use crate::error::Error;
+
+/// Configuration of the texture atlas used by [`super::wgpu::WgpuRenderer::register_atlas_texture`]
+#[derive(Clone, Copy, Debug)]
+pub struct AtlasConfig {
+    /// Width and height of each atlas page, in pixels
+    pub page_size: u32,
+    /// Maximum number of atlas pages. Each page occupies a single texture slot.
+    pub max_pages: usize,
+    /// Images larger than this (in either dimension) are not packed into the atlas
+    pub max_entry_size: u32,
+    /// When all pages are full, evict the least recently used images to make room for new ones
+    pub evict_when_full: bool,
+}
+
+impl Default for AtlasConfig {
+    fn default() -> Self {
+        Self {
+            page_size: 2048,
+            max_pages: 4,
+            max_entry_size: 256,
+            evict_when_full: true,
+        }
+    }
+}
+
+/// Location of an image inside the atlas
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AtlasAllocation {
+    pub page: usize,
+    pub position: UVec2,
+    pub size: UVec2,
+}
+
+impl AtlasAllocation {
+    /// UV rectangle of the allocation within its page, as `(min.x, min.y, max.x, max.y)`
+    pub fn uv_rect(&self, page_size: u32) -> Vec4 {
+        let min = self.position.as_vec2() / page_size as f32;
+        let max = (self.position + self.size).as_vec2() / page_size as f32;
+        Vec4::new(min.x, min.y, max.x, max.y)
+    }
+}
+
+pub(crate) struct AtlasPage {
+    pub texture: wgpu::Texture,
+    pub view: wgpu::TextureView,
+    allocator: ShelfAllocator,
+}
+
+/// A set of RGBA8 atlas pages that small images are packed into
+pub(crate) struct TextureAtlas {
+    pub config: AtlasConfig,
+    pub pages: Vec<AtlasPage>,
+}
+
+impl TextureAtlas {
+    /// Empty space kept around every image, so linear filtering doesn't pick up texels from neighbouring images
+    const PADDING: u32 = 1;
+
+    pub fn new(config: AtlasConfig) -> Self {
+        Self {
+            config,
+            pages: Vec::new(),
+        }
+    }
+
+    /// Returns true if an image of the given size should be packed into the atlas
+    pub fn accepts(&self, size: UVec2) -> bool {
+        size.x > 0
+            && size.y > 0
+            && size.max_element() <= self.config.max_entry_size
+            && size.max_element() + Self::PADDING * 2 <= self.config.page_size
+    }
+
+    /// Reserves space for an image of the given size, creating a new page if needed.
+    /// Returns `None` if all pages are full.
+    pub fn allocate(&mut self, device: &wgpu::Device, size: UVec2) -> Option<AtlasAllocation> {
+        if !self.accepts(size) {
+            return None;
+        }
+
+        for (page_index, page) in self.pages.iter_mut().enumerate() {
+            if let Ok(position) = page.allocator.allocate(size) {
+                return Some(AtlasAllocation {
+                    page: page_index,
+                    position,
+                    size,
+                });
+            }
+        }
+
+        if self.pages.len() >= self.config.max_pages {
+            return None;
+        }
+
+        let mut page = self.create_page(device);
+        let position = page.allocator.allocate(size).ok()?;
+        self.pages.push(page);
+        Some(AtlasAllocation {
+            page: self.pages.len() - 1,
+            position,
+            size,
+        })
+    }
+
+    pub fn deallocate(&mut self, allocation: &AtlasAllocation) {
+        if let Some(page) = self.pages.get_mut(allocation.page) {
+            page.allocator
+                .deallocate(allocation.position, allocation.size);
+        }
+    }
+
+    pub fn write(&self, queue: &wgpu::Queue, allocation: &AtlasAllocation, rgba: &[u8]) {
+        queue.write_texture(
+            wgpu::TexelCopyTextureInfo {
+                texture: &self.pages[allocation.page].texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d {
+                    x: allocation.position.x,
+                    y: allocation.position.y,
+                    z: 0,
+                },
+                aspect: wgpu::TextureAspect::All,
+            },
+            rgba,
+            wgpu::TexelCopyBufferLayout {
+                offset: 0,
+                bytes_per_row: Some(allocation.size.x * 4),
+                rows_per_image: Some(allocation.size.y),
+            },
+            wgpu::Extent3d {
+                width: allocation.size.x,
+                height: allocation.size.y,
+                depth_or_array_layers: 1,
+            },
+        );
+    }
+
+    fn create_page(&self, device: &wgpu::Device) -> AtlasPage {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Texture Atlas Page"),
+            size: wgpu::Extent3d {
+                width: self.config.page_size,
+                height: self.config.page_size,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba8Unorm,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        AtlasPage {
+            texture,
+            view,
+            allocator: ShelfAllocator::new(UVec2::splat(self.config.page_size), Self::PADDING),
+        }
+    }
+}
+
+/// Shelf packing allocator.
+///
+/// The page is split into horizontal shelves, each holding images of (roughly) the same height side by side.
+/// Freed space is tracked per shelf as a list of free spans, and shelves at the bottom of the page are
+/// removed again once they are completely empty.
+pub(crate) struct ShelfAllocator {
+    size: UVec2,
+    /// Empty space kept on every side of each image
+    padding: u32,
+    shelves: Vec<Shelf>,
+}
+
+struct Shelf {
+    y: u32,
+    height: u32,
+    /// Free horizontal spans as `(x, width)`, sorted by x
+    free_spans: Vec<(u32, u32)>,
+}
+
+impl ShelfAllocator {
+    /// Shelf heights are rounded up to this, so images of similar heights can share shelves
+    const SHELF_HEIGHT_ALIGNMENT: u32 = 8;
+
+    pub fn new(size: UVec2, padding: u32) -> Self {
+        Self {
+            size,
+            padding,
+            shelves: Vec::new(),
+        }
+    }
+
+    /// Reserves space for an image of the given size, returning the position of its top left corner.
+    /// Returns [`Error::AtlasFull`] if there is no space left, or the image is larger than the page.
+    pub fn allocate(&mut self, size: UVec2) -> Result<UVec2, Error> {
+        self.allocate_padded(size + self.padding * 2)
+            .map(|position| position + self.padding)
+            .ok_or(Error::AtlasFull)
+    }
+
+    /// Frees the space of an image allocated with the given position and size
+    pub fn deallocate(&mut self, position: UVec2, size: UVec2) {
+        self.deallocate_padded(position - self.padding, size + self.padding * 2);
+    }
+
+    fn allocate_padded(&mut self, size: UVec2) -> Option<UVec2> {
+        // Pick the shelf that wastes the least height
+        let best_shelf = self
+            .shelves
+            .iter()
+            .enumerate()
+            .filter(|(_, shelf)| shelf.height >= size.y)
+            .filter(|(_, shelf)| shelf.free_spans.iter().any(|&(_, w)| w >= size.x))
+            .min_by_key(|(_, shelf)| shelf.height - size.y)
+            .map(|(i, _)| i);
+
+        // Rather open a new shelf than put a small image in a much taller shelf, unless the page is out of space
+        let shelf_index = match best_shelf {
+            Some(i) if self.shelves[i].height <= size.y * 2 => i,
+            best_shelf => match self.push_shelf(size) {
+                Some(i) => i,
+                None => best_shelf?,
+            },
+        };
+
+        let shelf = &mut self.shelves[shelf_index];
+        let span_index = shelf.free_spans.iter().position(|&(_, w)| w >= size.x)?;
+        let (x, width) = shelf.free_spans[span_index];
+        if width == size.x {
+            shelf.free_spans.remove(span_index);
+        } else {
+            shelf.free_spans[span_index] = (x + size.x, width - size.x);
+        }
+
+        Some(uvec2(x, shelf.y))
+    }
+
+    fn push_shelf(&mut self, size: UVec2) -> Option<usize> {
+        let y = self.shelves.last().map(|s| s.y + s.height).unwrap_or(0);
+        let height = size
+            .y
+            .next_multiple_of(Self::SHELF_HEIGHT_ALIGNMENT)
+            .min(self.size.y - y.min(self.size.y));
+        if height < size.y || size.x > self.size.x {
+            return None;
+        }
+        self.shelves.push(Shelf {
+            y,
+            height,
+            free_spans: vec![(0, self.size.x)],
+        });
+        Some(self.shelves.len() - 1)
+    }
+
+    fn deallocate_padded(&mut self, position: UVec2, size: UVec2) {
+        let Some(shelf) = self.shelves.iter_mut().find(|s| s.y == position.y) else {
+            return;
+        };
+
+        let insert_at = shelf.free_spans.partition_point(|&(x, _)| x < position.x);
+        shelf.free_spans.insert(insert_at, (position.x, size.x));
+
+        // Merge with the neighbouring spans
+        if insert_at + 1 < shelf.free_spans.len() {
+            let (x, w) = shelf.free_spans[insert_at];
+            let (next_x, next_w) = shelf.free_spans[insert_at + 1];
+            if x + w == next_x {
+                shelf.free_spans[insert_at] = (x, w + next_w);
+                shelf.free_spans.remove(insert_at + 1);
+            }
+        }
+        if insert_at > 0 {
+            let (prev_x, prev_w) = shelf.free_spans[insert_at - 1];
+            let (x, w) = shelf.free_spans[insert_at];
+            if prev_x + prev_w == x {
+                shelf.free_spans[insert_at - 1] = (prev_x, prev_w + w);
+                shelf.free_spans.remove(insert_at);
+            }
+        }
+
+        // Drop empty shelves from the bottom, so their height can be reused for differently sized images
+        while let Some(last) = self.shelves.last()
+            && last.free_spans == [(0, self.size.x)]
+        {
+            self.shelves.pop();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn images_are_padded() {
+        let mut allocator = ShelfAllocator::new(UVec2::splat(64), 1);
+        assert_eq!(allocator.allocate(uvec2(10, 10)), Ok(uvec2(1, 1)));
+        assert_eq!(allocator.allocate(uvec2(10, 10)), Ok(uvec2(13, 1)));
+        // The padded height is rounded up to a 16 pixel shelf, which still fits a padded 14 pixel image
+        assert_eq!(allocator.allocate(uvec2(20, 14)), Ok(uvec2(25, 1)));
+        assert_eq!(allocator.allocate(uvec2(20, 20)), Ok(uvec2(1, 17)));
+    }
+
+    #[test]
+    fn freed_space_is_reused() {
+        let mut allocator = ShelfAllocator::new(UVec2::splat(64), 1);
+        let first = allocator.allocate(uvec2(10, 10)).unwrap();
+        let second = allocator.allocate(uvec2(10, 10)).unwrap();
+        allocator.deallocate(first, uvec2(10, 10));
+        assert_eq!(allocator.allocate(uvec2(8, 6)), Ok(first));
+        assert_eq!(allocator.shelves.len(), 1);
+
+        // Emptying the only shelf removes it, so a taller image can start at the top again
+        allocator.deallocate(first, uvec2(8, 6));
+        allocator.deallocate(second, uvec2(10, 10));
+        assert!(allocator.shelves.is_empty());
+        assert_eq!(allocator.allocate(uvec2(30, 30)), Ok(uvec2(1, 1)));
+    }
+
+    #[test]
+    fn adjacent_spans_merge() {
+        let mut allocator = ShelfAllocator::new(uvec2(36, 64), 1);
+        let positions: Vec<UVec2> = (0..3)
+            .map(|_| allocator.allocate(uvec2(10, 10)).unwrap())
+            .collect();
+        assert_eq!(allocator.allocate(uvec2(10, 10)).map(|p| p.y), Ok(17));
+
+        // Freeing the right and then the left image joins both spans with the one in between
+        allocator.deallocate(positions[2], uvec2(10, 10));
+        allocator.deallocate(positions[0], uvec2(10, 10));
+        allocator.deallocate(positions[1], uvec2(10, 10));
+        assert_eq!(allocator.shelves[0].free_spans, [(0, 36)]);
+        assert_eq!(allocator.allocate(uvec2(34, 10)), Ok(uvec2(1, 1)));
+    }
+
+    #[test]
+    fn oversized_images_are_rejected() {
+        let mut allocator = ShelfAllocator::new(UVec2::splat(64), 1);
+        assert_eq!(allocator.allocate(uvec2(63, 10)), Err(Error::AtlasFull));
+        assert_eq!(allocator.allocate(uvec2(10, 63)), Err(Error::AtlasFull));
+        assert_eq!(allocator.allocate(uvec2(62, 62)), Ok(uvec2(1, 1)));
+        assert_eq!(allocator.allocate(uvec2(1, 1)), Err(Error::AtlasFull));
+    }
+}
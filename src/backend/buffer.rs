@@ -0,0 +1,319 @@
+use std::num::NonZeroU64;
+
+/// Controls how the renderer's GPU buffers are resized when the amount of data changes
+#[derive(Clone, Copy, Debug)]
+pub struct BufferGrowthPolicy {
+    /// Minimum capacity of each buffer, in elements
+    pub min_capacity: usize,
+    /// When set, a buffer is shrunk after using less than a quarter of its capacity for this many consecutive frames.
+    /// When `None`, buffers never shrink.
+    pub shrink_after_frames: Option<u32>,
+}
+
+impl Default for BufferGrowthPolicy {
+    fn default() -> Self {
+        Self {
+            min_capacity: 64,
+            shrink_after_frames: Some(120),
+        }
+    }
+}
+
+impl BufferGrowthPolicy {
+    /// Capacity to allocate for `len` elements. Capacities are rounded up to a power of two, so that fluctuating
+    /// element counts don't cause a reallocation every time they grow slightly.
+    fn capacity_for(&self, len: usize) -> usize {
+        len.max(self.min_capacity).max(1).next_power_of_two()
+    }
+}
+
+/// How buffer data is transferred to the GPU
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum UploadMode {
+    /// Upload through [`wgpu::Queue::write_buffer`]
+    #[default]
+    Queue,
+    /// Upload through a [`wgpu::util::StagingBelt`], which recycles its staging buffers once the GPU is done with them.
+    /// This avoids allocating new staging memory every frame when multiple frames are in flight.
+    StagingBelt,
+}
+
+/// Statistics about the renderer's GPU buffer usage
+#[derive(Clone, Copy, Debug, Default)]
+pub struct BufferStats {
+    /// Number of times a buffer had to be reallocated because it was too small
+    pub grows: u64,
+    /// Number of times a buffer was reallocated to a smaller size
+    pub shrinks: u64,
+    /// Total number of bytes uploaded to the GPU
+    pub bytes_uploaded: u64,
+    /// Combined size of all buffers, in bytes
+    pub allocated_bytes: u64,
+}
+
+impl BufferStats {
+    /// Total number of buffer reallocations
+    pub fn reallocations(&self) -> u64 {
+        self.grows + self.shrinks
+    }
+}
+
+/// A GPU buffer that is only reallocated when its capacity, following a [`BufferGrowthPolicy`], no longer fits
+pub(crate) struct GrowableBuffer {
+    label: &'static str,
+    buffer: wgpu::Buffer,
+    element_size: usize,
+    capacity: Capacity,
+}
+
+impl GrowableBuffer {
+    pub fn new(
+        device: &wgpu::Device,
+        label: &'static str,
+        element_size: usize,
+        policy: &BufferGrowthPolicy,
+    ) -> Self {
+        let capacity = Capacity::new(policy);
+        Self {
+            label,
+            buffer: Self::create_buffer(device, label, capacity.elements * element_size),
+            element_size,
+            capacity,
+        }
+    }
+
+    fn create_buffer(device: &wgpu::Device, label: &'static str, size: usize) -> wgpu::Buffer {
+        device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some(label),
+            size: size as u64,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        })
+    }
+
+    pub fn buffer(&self) -> &wgpu::Buffer {
+        &self.buffer
+    }
+
+    pub fn size_bytes(&self) -> u64 {
+        (self.capacity.elements * self.element_size) as u64
+    }
+
+    /// Makes sure the buffer can hold `len` elements, shrinking it if it has been underused for long enough.
+    ///
+    /// Returns true if the buffer was reallocated, in which case its contents are lost and any bind groups referencing
+    /// it must be recreated.
+    pub fn reserve(
+        &mut self,
+        device: &wgpu::Device,
+        len: usize,
+        policy: &BufferGrowthPolicy,
+        stats: &mut BufferStats,
+    ) -> bool {
+        if !self.capacity.reserve(len, policy, stats) {
+            return false;
+        }
+        self.buffer = Self::create_buffer(
+            device,
+            self.label,
+            self.capacity.elements * self.element_size,
+        );
+        true
+    }
+}
+
+/// Capacity of a [`GrowableBuffer`], tracked apart from the buffer itself
+#[derive(Debug)]
+struct Capacity {
+    /// Capacity of the buffer in elements
+    elements: usize,
+    underused_frames: u32,
+}
+
+impl Capacity {
+    fn new(policy: &BufferGrowthPolicy) -> Self {
+        Self {
+            elements: policy.capacity_for(0),
+            underused_frames: 0,
+        }
+    }
+
+    /// Updates the capacity for a frame using `len` elements. Returns true if the capacity changed, and the buffer has
+    /// to be reallocated.
+    fn reserve(
+        &mut self,
+        len: usize,
+        policy: &BufferGrowthPolicy,
+        stats: &mut BufferStats,
+    ) -> bool {
+        let new_capacity = if len > self.elements {
+            stats.grows += 1;
+            policy.capacity_for(len)
+        } else if let Some(shrink_after_frames) = policy.shrink_after_frames
+            && len <= self.elements / 4
+            && policy.capacity_for(len) < self.elements
+        {
+            self.underused_frames += 1;
+            if self.underused_frames < shrink_after_frames {
+                return false;
+            }
+            stats.shrinks += 1;
+            policy.capacity_for(len)
+        } else {
+            self.underused_frames = 0;
+            return false;
+        };
+
+        self.elements = new_capacity;
+        self.underused_frames = 0;
+        true
+    }
+}
+
+/// Records buffer uploads according to the configured [`UploadMode`]
+pub(crate) struct Uploader {
+    pub mode: UploadMode,
+    belt: wgpu::util::StagingBelt,
+    encoder: Option<wgpu::CommandEncoder>,
+}
+
+impl Uploader {
+    const STAGING_CHUNK_SIZE: u64 = 1 << 20;
+
+    pub fn new(mode: UploadMode) -> Self {
+        Self {
+            mode,
+            belt: wgpu::util::StagingBelt::new(Self::STAGING_CHUNK_SIZE),
+            encoder: None,
+        }
+    }
+
+    pub fn write(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        buffer: &wgpu::Buffer,
+        offset: u64,
+        data: &[u8],
+        stats: &mut BufferStats,
+    ) {
+        let Some(size) = NonZeroU64::new(data.len() as u64) else {
+            return;
+        };
+        stats.bytes_uploaded += size.get();
+
+        match self.mode {
+            UploadMode::Queue => queue.write_buffer(buffer, offset, data),
+            UploadMode::StagingBelt => {
+                let encoder = self.encoder.get_or_insert_with(|| {
+                    device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                        label: Some("Mondrian Upload Encoder"),
+                    })
+                });
+                self.belt
+                    .write_buffer(encoder, buffer, offset, size, device)
+                    .copy_from_slice(data);
+            }
+        }
+    }
+
+    /// Submits any uploads recorded through the staging belt. Must be called before the frame's draw commands are submitted.
+    pub fn flush(&mut self, queue: &wgpu::Queue) {
+        let Some(encoder) = self.encoder.take() else {
+            return;
+        };
+        self.belt.finish();
+        queue.submit(Some(encoder.finish()));
+        self.belt.recall();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn grows_to_powers_of_two() {
+        let policy = BufferGrowthPolicy::default();
+        assert_eq!(policy.capacity_for(0), 64);
+        assert_eq!(policy.capacity_for(64), 64);
+        assert_eq!(policy.capacity_for(65), 128);
+        assert_eq!(policy.capacity_for(1000), 1024);
+
+        let mut stats = BufferStats::default();
+        let mut capacity = Capacity::new(&policy);
+        assert_eq!(capacity.elements, 64);
+        assert!(!capacity.reserve(64, &policy, &mut stats));
+        assert!(capacity.reserve(65, &policy, &mut stats));
+        assert_eq!(capacity.elements, 128);
+        assert!(capacity.reserve(3000, &policy, &mut stats));
+        assert_eq!(capacity.elements, 4096);
+        assert_eq!((stats.grows, stats.shrinks), (2, 0));
+
+        let unbounded = BufferGrowthPolicy {
+            min_capacity: 0,
+            shrink_after_frames: None,
+        };
+        assert_eq!(unbounded.capacity_for(0), 1);
+        assert_eq!(unbounded.capacity_for(3), 4);
+    }
+
+    #[test]
+    fn keeps_capacity_after_a_spike() {
+        let policy = BufferGrowthPolicy::default();
+        let mut stats = BufferStats::default();
+        let mut capacity = Capacity::new(&policy);
+        assert!(capacity.reserve(4000, &policy, &mut stats));
+        assert_eq!(capacity.elements, 4096);
+
+        // A few quiet frames between spikes don't shrink the buffer
+        for _ in 0..10 {
+            for _ in 0..100 {
+                assert!(!capacity.reserve(100, &policy, &mut stats));
+            }
+            assert!(!capacity.reserve(4000, &policy, &mut stats));
+        }
+        assert_eq!(capacity.elements, 4096);
+        assert_eq!((stats.grows, stats.shrinks), (1, 0));
+    }
+
+    #[test]
+    fn shrinks_after_underused_frames() {
+        let policy = BufferGrowthPolicy::default();
+        let mut stats = BufferStats::default();
+        let mut capacity = Capacity::new(&policy);
+        capacity.reserve(4000, &policy, &mut stats);
+
+        // Using more than a quarter of the capacity keeps it
+        for _ in 0..200 {
+            assert!(!capacity.reserve(1100, &policy, &mut stats));
+        }
+        assert_eq!(capacity.elements, 4096);
+
+        for _ in 0..119 {
+            assert!(!capacity.reserve(1000, &policy, &mut stats));
+        }
+        assert!(capacity.reserve(1000, &policy, &mut stats));
+        assert_eq!(capacity.elements, 1024);
+        assert_eq!((stats.grows, stats.shrinks), (1, 1));
+
+        // Never below the minimum capacity
+        for _ in 0..120 {
+            capacity.reserve(0, &policy, &mut stats);
+        }
+        assert_eq!(capacity.elements, 64);
+        for _ in 0..1000 {
+            assert!(!capacity.reserve(0, &policy, &mut stats));
+        }
+
+        let never_shrink = BufferGrowthPolicy {
+            shrink_after_frames: None,
+            ..policy
+        };
+        capacity.reserve(4000, &never_shrink, &mut stats);
+        for _ in 0..1000 {
+            assert!(!capacity.reserve(0, &never_shrink, &mut stats));
+        }
+        assert_eq!(capacity.elements, 4096);
+    }
+}
@@ -1,9 +1,10 @@
 use assert_offset::AssertOffsets;
-use glam::Vec4;
+use glam::{Vec2, Vec4};
 
 use crate::{
     Primitive, Shape,
-    shape::{BoundingBox, ShapeFlags},
+    backend::sampler::SamplerOptions,
+    shape::{BoundingBox, ShapeFlags, TextureFit},
 };
 
 #[repr(C)]
@@ -26,10 +27,35 @@ pub struct GpuShape {
 
     #[offset(0x40)]
     pub params: GpuShapeParams,
+
+    /// UV rectangle of the texture to sample, as `(min.x, min.y, max.x, max.y)`. Covers the whole texture unless it lives in an atlas.
+    #[offset(0x60)]
+    pub texture_rect: Vec4,
+
+    /// Region of the texture (or atlas entry) to sample, as `(min.x, min.y, max.x, max.y)`
+    #[offset(0x70)]
+    pub uv_rect: Vec4,
+
+    #[offset(0x80)]
+    pub texture_fit: u32,
+    /// Index of the sampler to use, see [`SamplerOptions::index`]
+    pub sampler_index: u32,
+    #[offset(0x88)]
+    pub tile_size: Vec2,
+
+    /// Distance range of SDF and MTSDF textures, in texels
+    #[offset(0x90)]
+    pub sdf_range: f32,
+    pub _padding: [u32; 3],
 }
 
 impl GpuShape {
-    pub fn from_shape(shape: &Shape, texture_id: Option<u32>) -> Self {
+    pub fn from_shape(
+        shape: &Shape,
+        texture_id: Option<u32>,
+        texture_rect: Vec4,
+        sampler: SamplerOptions,
+    ) -> Self {
         let shape_type = match shape.primitive {
             Primitive::Circle { .. } => 0,
             Primitive::Triangle { .. } => 1,
@@ -39,7 +65,21 @@ impl GpuShape {
             Primitive::PolyQuad { .. } => 5,
         };
 
-        let bounds = shape.bounds();
+        // Distance offset and line width of SDF textures are applied in texture space, so the texture is still mapped
+        // onto the primitive itself
+        let bounds = if shape.has_sdf_texture() {
+            shape.primitive.bounds()
+        } else {
+            shape.bounds()
+        };
+
+        let mapping = &shape.texture_mapping;
+        let (texture_fit, tile_size) = match mapping.fit {
+            TextureFit::Stretch => (0, Vec2::ZERO),
+            TextureFit::Contain => (1, Vec2::ZERO),
+            TextureFit::Cover => (2, Vec2::ZERO),
+            TextureFit::Tile(size) => (3, size),
+        };
 
         Self {
             header: ShapeHeader::new(shape_type, texture_id),
@@ -50,17 +90,38 @@ impl GpuShape {
             color: shape.color,
             glow: shape.glow,
             params: GpuShapeParams::from(&shape.primitive),
+            texture_rect,
+            uv_rect: Vec4::new(
+                mapping.uv_min.x,
+                mapping.uv_min.y,
+                mapping.uv_max.x,
+                mapping.uv_max.y,
+            ),
+            texture_fit,
+            sampler_index: sampler.index(),
+            tile_size,
+            sdf_range: mapping.sdf_range,
+            _padding: [0; 3],
         }
     }
 }
 
+/// Per-frame constants passed to the shader through push constants
+#[repr(C)]
+#[derive(Clone, Copy, AssertOffsets, Default, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct ScreenConstants {
+    #[offset(0x00)]
+    pub width_tiles: u32,
+    /// Number of live shapes in the shape buffer. The buffer may be larger, anything past this count is stale.
+    #[offset(0x04)]
+    pub shape_count: u32,
+}
+
 #[repr(C)]
 #[derive(Default, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
 pub struct ShapeHeader(u32);
 
 impl ShapeHeader {
-    pub const SENTINEL: Self = ShapeHeader(u32::MAX);
-
     pub fn new(shape_type: u32, texture_id: Option<u32>) -> Self {
         let texture_id_bits = texture_id.unwrap_or(u32::MAX) << 8;
         let shape_type_bits = shape_type & 0xFF;
@@ -9,16 +9,21 @@ const SHAPE_TYPE_RECT: u32 = 2;
 const SHAPE_TYPE_LINE: u32 = 3;
 const SHAPE_TYPE_CIRCLE_SECTOR: u32 = 4;
 const SHAPE_TYPE_POLYQUAD: u32 = 5;
-const SHAPE_TYPE_SENTINEL: u32 = 0xFF;
+
+const TEXTURE_FIT_STRETCH: u32 = 0;
+const TEXTURE_FIT_CONTAIN: u32 = 1;
+const TEXTURE_FIT_COVER: u32 = 2;
+const TEXTURE_FIT_TILE: u32 = 3;
+
+const SAMPLER_ADDRESS_CLAMP: u32 = 0;
+const SAMPLER_ADDRESS_REPEAT: u32 = 1;
+const SAMPLER_ADDRESS_MIRROR: u32 = 2;
 
 const FLAG_TEXTURE_IS_SDF: u32 = 0x1u;
 const FLAG_TEXTURE_IS_MTSDF: u32 = 0x2u;
 
 const TILE_SIZE: f32 = 32.0;
 
-const SDF_TEXTURE_RANGE: f32 = 4.0;
-const SDF_TEXTURE_SIZE: f32 = 32.0;
-
 const ANTI_ALIASING: bool = true;
 
 struct Shape {
@@ -35,6 +40,15 @@ struct Shape {
     glow: vec4<f32>,
 
     params: array<f32, 8>,
+
+    texture_rect: vec4<f32>,
+    uv_rect: vec4<f32>,
+
+    texture_fit: u32,
+    sampler_index: u32,
+    tile_size: vec2<f32>,
+
+    sdf_range: f32,
 }
 
 fn shape_type(shape: Shape) -> u32 {
@@ -49,9 +63,98 @@ fn shape_has_texture(shape: Shape) -> bool {
     return (shape.header & SHAPE_TEXTURE_ID_MASK) != SHAPE_TEXTURE_ID_MASK;
 }
 
-fn screen_px_range(bounds_min: vec2<f32>, bounds_max: vec2<f32>) -> f32 {
-    let shape_width = bounds_max.x - bounds_min.x;
-    return (shape_width / SDF_TEXTURE_SIZE) * SDF_TEXTURE_RANGE;
+// Distance range of an SDF texture in screen pixels, averaged over both axes so non-uniformly scaled textures keep
+// their edge sharpness. Kept at one pixel or more, so minified textures stay anti-aliased instead of aliasing.
+fn screen_px_range(sdf_range: f32, texels_per_px: vec2<f32>) -> f32 {
+    let px_per_texel = 1.0 / max(abs(texels_per_px), vec2<f32>(1e-6));
+    return max(0.5 * sdf_range * (px_per_texel.x + px_per_texel.y), 1.0);
+}
+
+// Size in pixels of the area the shape's UV rect is stretched over, according to the shape's fit mode
+fn shape_fit_size(shape: Shape, bounds_size: vec2<f32>, texture_size: vec2<f32>) -> vec2<f32> {
+    switch(shape.texture_fit) {
+        case TEXTURE_FIT_CONTAIN, TEXTURE_FIT_COVER: {
+            let region_size = abs(shape.uv_rect.zw - shape.uv_rect.xy)
+                * (shape.texture_rect.zw - shape.texture_rect.xy)
+                * texture_size;
+            let scales = bounds_size / region_size;
+            var scale = min(scales.x, scales.y);
+            if shape.texture_fit == TEXTURE_FIT_COVER {
+                scale = max(scales.x, scales.y);
+            }
+            return region_size * scale;
+        }
+        case TEXTURE_FIT_TILE: {
+            return shape.tile_size;
+        }
+        default: {
+            return bounds_size;
+        }
+    }
+}
+
+// Maps the fragment position to a [0, 1] coordinate within the shape's UV rect, according to the shape's fit mode.
+// Coordinates outside of [0, 1] are not covered by the texture.
+fn shape_fit_uv(shape: Shape, frag_pos: vec2<f32>, bounds_min: vec2<f32>, bounds_max: vec2<f32>, fit_size: vec2<f32>) -> vec2<f32> {
+    let bounds_size = bounds_max - bounds_min;
+    let offset = frag_pos - bounds_min;
+    switch(shape.texture_fit) {
+        case TEXTURE_FIT_CONTAIN, TEXTURE_FIT_COVER: {
+            return (offset - (bounds_size - fit_size) * 0.5) / fit_size;
+        }
+        case TEXTURE_FIT_TILE: {
+            return fract(offset / fit_size);
+        }
+        default: {
+            return offset / fit_size;
+        }
+    }
+}
+
+// Sampler address mode, the sampler index is `filter * 3 + address_mode`
+fn sampler_address_mode(sampler_index: u32) -> u32 {
+    return sampler_index % 3u;
+}
+
+// Wraps a UV coordinate into [0, 1] according to the address mode
+fn wrap_uv(uv: vec2<f32>, address_mode: u32) -> vec2<f32> {
+    switch(address_mode) {
+        case SAMPLER_ADDRESS_REPEAT: {
+            return fract(uv);
+        }
+        case SAMPLER_ADDRESS_MIRROR: {
+            return 1.0 - abs(fract(uv * 0.5) * 2.0 - 1.0);
+        }
+        default: {
+            return clamp(uv, vec2<f32>(0.0), vec2<f32>(1.0));
+        }
+    }
+}
+
+// Maps a UV coordinate to the shape's texture rect, which is a sub-region of the texture for atlas entries.
+// Whole textures are wrapped by the sampler, atlas entries are wrapped here so they don't repeat the whole atlas page.
+// The result is kept half a texel inside of the rect, so filtering doesn't bleed in neighbouring atlas entries.
+fn texture_rect_uv(uv: vec2<f32>, rect: vec4<f32>, texture_size: vec2<f32>, sampler_index: u32) -> vec2<f32> {
+    let address_mode = sampler_address_mode(sampler_index);
+    let whole_texture = all(rect == vec4<f32>(0.0, 0.0, 1.0, 1.0));
+    if whole_texture && address_mode != SAMPLER_ADDRESS_CLAMP {
+        return uv;
+    }
+    let half_texel = 0.5 / texture_size;
+    return clamp(mix(rect.xy, rect.zw, wrap_uv(uv, address_mode)), rect.xy + half_texel, rect.zw - half_texel);
+}
+
+// Samples with explicit gradients, as implicit derivatives break down where tiled or wrapped coordinates jump.
+// Explicit gradients also don't require uniform control flow, which the per-shape texture lookup can't guarantee.
+fn sample_texture(texture: texture_2d<f32>, sampler_index: u32, uv: vec2<f32>, ddx: vec2<f32>, ddy: vec2<f32>) -> vec4<f32> {
+    switch(sampler_index) {
+        case 0u: { return textureSampleGrad(texture, sampler_nearest_clamp, uv, ddx, ddy); }
+        case 1u: { return textureSampleGrad(texture, sampler_nearest_repeat, uv, ddx, ddy); }
+        case 2u: { return textureSampleGrad(texture, sampler_nearest_mirror, uv, ddx, ddy); }
+        case 4u: { return textureSampleGrad(texture, sampler_linear_repeat, uv, ddx, ddy); }
+        case 5u: { return textureSampleGrad(texture, sampler_linear_mirror, uv, ddx, ddy); }
+        default: { return textureSampleGrad(texture, sampler_linear_clamp, uv, ddx, ddy); }
+    }
 }
 
 fn shape_group_id(shape: Shape) -> u32 {
@@ -83,18 +186,29 @@ var<storage, read> shape_ranges: array<u32>;
 @group(0) @binding(2)
 var<storage, read> shape_indices: array<u32>;
 
-@group(1) @binding(0)
-var shape_textures: binding_array<texture_2d<f32>>;
-
-@group(1) @binding(1)
-var texture_sampler: sampler;
+// The shape textures and the `screen` constants are declared by the variant specific code prepended to this file,
+// which also provides `shape_texture_size(texture_id)` and `sample_shape_texture(texture_id, ...)`.
+// The `sd_*` distance functions are prepended from `sdf.wgsl`.
+
+// One sampler per filter and address mode combination, in the order of `SamplerOptions::index`
+@group(1) @binding(8)
+var sampler_nearest_clamp: sampler;
+@group(1) @binding(9)
+var sampler_nearest_repeat: sampler;
+@group(1) @binding(10)
+var sampler_nearest_mirror: sampler;
+@group(1) @binding(11)
+var sampler_linear_clamp: sampler;
+@group(1) @binding(12)
+var sampler_linear_repeat: sampler;
+@group(1) @binding(13)
+var sampler_linear_mirror: sampler;
 
 struct ScreenConstants {
     width_tiles: u32,
+    shape_count: u32,
 };
 
-var<push_constant> screen: ScreenConstants;
-
 @vertex
 fn main_vs(@builtin(vertex_index) vertex_index: u32) -> @builtin(position) vec4<f32> {
     // Generate a fullscreen triangle
@@ -122,12 +236,13 @@ fn main_fs(@builtin(position) frag_coord: vec4<f32>) -> @location(0) vec4<f32> {
     var last_group_id: u32 = 0xFFFFFFFF;
     for(var i: u32 = shape_start; i < shape_end; i = i + 1u) {
         let shape_index = shape_indices[i];
-        let shape = shapes[shape_index];
-        if(shape_type(shape) == SHAPE_TYPE_SENTINEL) {
+        // Shapes past the live shape count are left over from previous frames
+        if(shape_index >= screen.shape_count) {
             break;
         }
+        let shape = shapes[shape_index];
         var next_group_id: u32 = 0xFFFFFFFF;
-        if(i + 1u < shape_end) {
+        if(i + 1u < shape_end && shape_indices[i + 1u] < screen.shape_count) {
             next_group_id = shape_group_id(shapes[shape_indices[i + 1u]]);
         }
 
@@ -153,19 +268,39 @@ fn main_fs(@builtin(position) frag_coord: vec4<f32>) -> @location(0) vec4<f32> {
             var shape_color = shape.color;
             let texture_id = shape_texture_id(shape);
             var dist_soft = dist_hard;
+            // Glows fade out before the distances stored in SDF textures saturate at half their range
+            var max_soft_dist = 1e6;
             if shape_has_texture(shape) {
-                var uv = (frag_pos - group_bounds_min) / (group_bounds_max - group_bounds_min);
-                let texture = shape_textures[texture_id];
-                let tex_color = textureSample(texture, texture_sampler, uv);
+                let texture_size = shape_texture_size(texture_id);
+                let fit_size = shape_fit_size(shape, group_bounds_max - group_bounds_min, texture_size);
+                let local_uv = shape_fit_uv(shape, frag_pos, group_bounds_min, group_bounds_max, fit_size);
+                var uv = mix(shape.uv_rect.xy, shape.uv_rect.zw, local_uv);
+                uv = texture_rect_uv(uv, shape.texture_rect, texture_size, shape.sampler_index);
+                // Texture coordinate change per pixel, used to select the mip level
+                let uv_per_px = (shape.uv_rect.zw - shape.uv_rect.xy)
+                    * (shape.texture_rect.zw - shape.texture_rect.xy)
+                    / fit_size;
+                var tex_color = sample_shape_texture(
+                    texture_id,
+                    shape.sampler_index,
+                    uv,
+                    vec2<f32>(uv_per_px.x, 0.0),
+                    vec2<f32>(0.0, uv_per_px.y),
+                );
+                // Letterboxed area of TEXTURE_FIT_CONTAIN
+                if any(local_uv < vec2<f32>(0.0)) || any(local_uv > vec2<f32>(1.0)) {
+                    tex_color = vec4<f32>(0.0);
+                }
+                let px_range = screen_px_range(shape.sdf_range, uv_per_px * texture_size);
                 if shape_texture_is_mtsdf(shape) {
-                    var msdf = median(tex_color.r, tex_color.g, tex_color.b);
-                    let sdf = tex_color.a;
-
-                    dist_hard = -(screen_px_range(group_bounds_min, group_bounds_max) * (msdf - 0.5)) + 0.5;
-                    dist_soft = -(SDF_TEXTURE_SIZE * (tex_color.a - 0.5)) + 0.5;
+                    let msdf = median(tex_color.r, tex_color.g, tex_color.b);
+                    dist_hard = sdf_texture_distance(msdf, px_range, shape);
+                    dist_soft = sdf_texture_distance(tex_color.a, px_range, shape);
+                    max_soft_dist = styled_distance(px_range * 0.5, shape) + 0.5;
                 } else if shape_texture_is_sdf(shape) {
-                    dist_hard = -(screen_px_range(group_bounds_min, group_bounds_max) * (tex_color.x - 0.5)) + 0.5;
-                    dist_soft = -(SDF_TEXTURE_SIZE * (tex_color.x - 0.5));
+                    dist_hard = sdf_texture_distance(tex_color.x, px_range, shape);
+                    dist_soft = dist_hard;
+                    max_soft_dist = styled_distance(px_range * 0.5, shape) + 0.5;
                 } else {
                     shape_color = shape_color * tex_color;
                 }
@@ -173,7 +308,7 @@ fn main_fs(@builtin(position) frag_coord: vec4<f32>) -> @location(0) vec4<f32> {
 
 
             if shape.glow.a != 0.0 {
-                let glow_dist = abs(shape.glow.a);
+                let glow_dist = max(min(abs(shape.glow.a), max_soft_dist), 1e-3);
                 let glow_color = shape.glow.rgb;
                 let glow_strength = clamp(1.0 - (dist_soft / glow_dist), 0.0, 1.0);
                 if shape.glow.a < 0.0 {
@@ -250,136 +385,23 @@ fn sd_shape(p: vec2<f32>, shape: Shape) -> f32 {
     }
 }
 
-fn sd_union(d1: f32, d2: f32) -> f32 {
-    return min(d1, d2);
-}
-
-fn sd_outline(d: f32, thickness: f32) -> f32 {
-    return abs(d) - thickness;
-}
-
-// SDF functions (https://iquilezles.org/articles/distfunctions2d/)
-fn sd_circle(p: vec2<f32>, radius: f32) -> f32 {
-    return length(p) - radius;
-}
-
-fn sd_rounded_rect(p: vec2<f32>, b: vec2<f32>, r: vec4<f32>) -> f32 {
-    var radii = vec2<f32>(0.0, 0.0);
-    if p.x > 0.0 {
-        radii.x = r.x;
-        radii.y = r.y;
-    } else {
-        radii.x = r.z;
-        radii.y = r.w;
-    }
-    if p.y > 0.0 {
-        radii.x = radii.x;
-    } else {
-        radii.x = radii.y;
-    }
-
-    let q = abs(p)-b+radii.x;
-    return min(max(q.x,q.y),0.0) + length(max(q, vec2(0.0, 0.0))) - radii.x;
-}
-
-fn sd_line(p: vec2<f32>, a: vec2<f32>, b: vec2<f32>) -> f32 {
-    let pa = p - a;
-    let ba = b - a;
-    let h = clamp(dot(pa, ba) / dot(ba, ba), 0.0, 1.0);
-    return length(pa - ba * h);
-}
-
-fn sd_triangle(p: vec2<f32>, p0: vec2<f32>, p1: vec2<f32>, p2: vec2<f32>) -> f32
-{
-    let e0 = p1-p0; let e1 = p2-p1; let e2 = p0-p2;
-    let v0 = p -p0; let v1 = p -p1; let v2 = p -p2;
-    let pq0 = v0 - e0*clamp( dot(v0,e0)/dot(e0,e0), 0.0, 1.0 );
-    let pq1 = v1 - e1*clamp( dot(v1,e1)/dot(e1,e1), 0.0, 1.0 );
-    let pq2 = v2 - e2*clamp( dot(v2,e2)/dot(e2,e2), 0.0, 1.0 );
-    let s = sign( e0.x*e2.y - e0.y*e2.x );
-    let d = min(min(vec2(dot(pq0,pq0), s*(v0.x*e0.y-v0.y*e0.x)),
-                     vec2(dot(pq1,pq1), s*(v1.x*e1.y-v1.y*e1.x))),
-                     vec2(dot(pq2,pq2), s*(v2.x*e2.y-v2.y*e2.x)));
-    return -sqrt(d.x)*sign(d.y);
-}
-
-fn sd_sector(p: vec2<f32>, ir: f32, or: f32, a1: f32, a2: f32) -> f32 {
-    let TAU: f32 = 6.283185307179586;
-    var delta: f32 = a2 - a1;
-    delta = delta - floor(delta / TAU) * TAU;
-
-    if (delta <= 1e-6) {
-        return length(p);
+// Applies the shape's distance offset and outline to a distance, like they are applied to primitives
+fn styled_distance(d: f32, shape: Shape) -> f32 {
+    var dist = d + shape.distance_offset;
+    if shape.line_width > 0.0 {
+        dist = sd_outline(dist, shape.line_width / 2.0);
     }
-    if (delta >= TAU - 1e-6) {
-        let r: f32 = length(p);
-        return max(r - or, ir - r);
-    }
-
-    let mid: f32 = a1 + 0.5 * delta;
-    let cm: f32 = cos(mid);
-    let sm: f32 = sin(mid);
-    let q: vec2<f32> = vec2<f32>(cm * p.x + sm * p.y, -sm * p.x + cm * p.y);
-
-    let rlen: f32 = length(q);
-    let theta: f32 = atan2(q.y, q.x);
-    let half: f32 = 0.5 * delta;
-
-    if (abs(theta) <= half) {
-        if (rlen < ir) {
-            return ir - rlen;
-        } else if (rlen > or) {
-            return rlen - or;
-        } else {
-            let d_in: f32 = rlen - ir;
-            let d_out: f32 = or - rlen;
-            return -min(d_in, d_out);
-        }
-    }
-
-    var sign: f32 = -1.0;
-    if (theta > 0.0) { sign = 1.0; }
-
-    let ch: f32 = cos(half);
-    let sh: f32 = sin(half);
-    let u: vec2<f32> = vec2<f32>(ch, sign * sh);
-
-    let t: f32 = dot(q, u);
-    let tclamped: f32 = clamp(t, ir, or);
-
-    let closest: vec2<f32> = u * tclamped;
-    return length(q - closest); // positive (outside)
+    return dist;
 }
 
-fn sd_quad(p: vec2<f32>, v: array<vec2<f32>, 4>) -> f32 {
-    let N: u32 = 4u;
-
-    var d: f32 = dot(p - v[0], p - v[0]);
-    var s: f32 = 1.0;
-
-    var j: u32 = N - 1u;
-
-    for (var i: u32 = 0u; i < N; i = i + 1u) {
-        let vi = v[i];
-        let vj = v[j];
-
-        let e = vj - vi;
-        let w = p - vi;
-
-        let t = clamp(dot(w, e) / dot(e, e), 0.0, 1.0);
-        let b = w - e * t;
-
-        d = min(d, dot(b, b));
-
-        let cross = e.x * w.y - e.y * w.x;
-
-        if ((p.y >= vi.y && p.y < vj.y && cross > 0.0) ||
-            (p.y <  vi.y && p.y >= vj.y && cross < 0.0)) {
-            s = -s;
-        }
-
-        j = i;
+// Converts a value sampled from an SDF texture to a styled distance in screen pixels.
+// Values at either end of the range may be arbitrarily far from the edge, so they are treated as infinitely far.
+fn sdf_texture_distance(value: f32, px_range: f32, shape: Shape) -> f32 {
+    var dist = -(px_range * (value - 0.5));
+    if value <= 0.0 {
+        dist = 1e6;
+    } else if value >= 1.0 {
+        dist = -1e6;
     }
-
-    return s * sqrt(d);
+    return styled_distance(dist, shape) + 0.5;
 }
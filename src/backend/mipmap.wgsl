@@ -0,0 +1,27 @@
+// Downsamples one mip level into the next, by drawing a full-screen triangle that samples the previous level
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+};
+
+@group(0) @binding(0)
+var source_texture: texture_2d<f32>;
+
+@group(0) @binding(1)
+var source_sampler: sampler;
+
+@vertex
+fn main_vs(@builtin(vertex_index) vertex_index: u32) -> VertexOutput {
+    let uv = vec2<f32>(f32((vertex_index << 1u) & 2u), f32(vertex_index & 2u));
+    var out: VertexOutput;
+    out.position = vec4<f32>(uv * vec2<f32>(2.0, -2.0) + vec2<f32>(-1.0, 1.0), 0.0, 1.0);
+    out.uv = uv;
+    return out;
+}
+
+@fragment
+fn main_fs(in: VertexOutput) -> @location(0) vec4<f32> {
+    // Sampling between the four source texels with linear filtering averages them
+    return textureSample(source_texture, source_sampler, in.uv);
+}
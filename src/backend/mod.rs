@@ -1,2 +1,6 @@
+pub mod atlas;
+pub mod buffer;
 pub mod common;
+pub mod sampler;
+pub mod texture;
 pub mod wgpu;
@@ -0,0 +1,98 @@
+/// How texels are filtered when a texture is sampled
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum TextureFilter {
+    /// Use the nearest texel, for crisp pixel art
+    Nearest,
+    #[default]
+    Linear,
+}
+
+/// How texture coordinates outside of `[0, 1]` are handled, eg. with a UV rect extending past the texture
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum TextureAddressMode {
+    #[default]
+    ClampToEdge,
+    Repeat,
+    MirrorRepeat,
+}
+
+/// Sampler settings of a registered texture
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct SamplerOptions {
+    pub filter: TextureFilter,
+    pub address_mode: TextureAddressMode,
+}
+
+impl SamplerOptions {
+    pub const NEAREST: Self = Self {
+        filter: TextureFilter::Nearest,
+        address_mode: TextureAddressMode::ClampToEdge,
+    };
+    pub const LINEAR: Self = Self {
+        filter: TextureFilter::Linear,
+        address_mode: TextureAddressMode::ClampToEdge,
+    };
+
+    /// Number of distinct sampler configurations, each bound as its own sampler
+    pub(crate) const COUNT: usize = 6;
+
+    pub fn with_filter(mut self, filter: TextureFilter) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    pub fn with_address_mode(mut self, address_mode: TextureAddressMode) -> Self {
+        self.address_mode = address_mode;
+        self
+    }
+
+    /// Index of the sampler in the texture bind group. Must match `sample_shape_texture` in the shader.
+    pub(crate) fn index(&self) -> u32 {
+        let filter = match self.filter {
+            TextureFilter::Nearest => 0,
+            TextureFilter::Linear => 1,
+        };
+        let address_mode = match self.address_mode {
+            TextureAddressMode::ClampToEdge => 0,
+            TextureAddressMode::Repeat => 1,
+            TextureAddressMode::MirrorRepeat => 2,
+        };
+        filter * 3 + address_mode
+    }
+
+    /// All sampler configurations, ordered by [`SamplerOptions::index`]
+    pub(crate) fn all() -> [Self; Self::COUNT] {
+        let filters = [TextureFilter::Nearest, TextureFilter::Linear];
+        let address_modes = [
+            TextureAddressMode::ClampToEdge,
+            TextureAddressMode::Repeat,
+            TextureAddressMode::MirrorRepeat,
+        ];
+        std::array::from_fn(|i| Self {
+            filter: filters[i / 3],
+            address_mode: address_modes[i % 3],
+        })
+    }
+
+    pub(crate) fn create_sampler(&self, device: &wgpu::Device) -> wgpu::Sampler {
+        let address_mode = match self.address_mode {
+            TextureAddressMode::ClampToEdge => wgpu::AddressMode::ClampToEdge,
+            TextureAddressMode::Repeat => wgpu::AddressMode::Repeat,
+            TextureAddressMode::MirrorRepeat => wgpu::AddressMode::MirrorRepeat,
+        };
+        let filter = match self.filter {
+            TextureFilter::Nearest => wgpu::FilterMode::Nearest,
+            TextureFilter::Linear => wgpu::FilterMode::Linear,
+        };
+        device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Texture Sampler"),
+            address_mode_u: address_mode,
+            address_mode_v: address_mode,
+            address_mode_w: address_mode,
+            mag_filter: filter,
+            min_filter: filter,
+            mipmap_filter: filter,
+            ..Default::default()
+        })
+    }
+}
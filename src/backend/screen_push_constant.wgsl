@@ -0,0 +1 @@
+var<push_constant> screen: ScreenConstants;
@@ -0,0 +1,3 @@
+// Compatibility path for adapters without push constants
+@group(2) @binding(0)
+var<uniform> screen: ScreenConstants;
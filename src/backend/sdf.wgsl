@@ -0,0 +1,135 @@
+// SDF functions (https://iquilezles.org/articles/distfunctions2d/)
+// Distances are negative inside of a shape. Mirrored by the `sdf` module on the CPU, keep both in sync.
+
+fn sd_union(d1: f32, d2: f32) -> f32 {
+    return min(d1, d2);
+}
+
+fn sd_outline(d: f32, thickness: f32) -> f32 {
+    return abs(d) - thickness;
+}
+
+fn sd_circle(p: vec2<f32>, radius: f32) -> f32 {
+    return length(p) - radius;
+}
+
+fn sd_rounded_rect(p: vec2<f32>, b: vec2<f32>, r: vec4<f32>) -> f32 {
+    var radii = vec2<f32>(0.0, 0.0);
+    if p.x > 0.0 {
+        radii.x = r.x;
+        radii.y = r.y;
+    } else {
+        radii.x = r.z;
+        radii.y = r.w;
+    }
+    if p.y > 0.0 {
+        radii.x = radii.x;
+    } else {
+        radii.x = radii.y;
+    }
+
+    let q = abs(p)-b+radii.x;
+    return min(max(q.x,q.y),0.0) + length(max(q, vec2(0.0, 0.0))) - radii.x;
+}
+
+fn sd_line(p: vec2<f32>, a: vec2<f32>, b: vec2<f32>) -> f32 {
+    let pa = p - a;
+    let ba = b - a;
+    let h = clamp(dot(pa, ba) / dot(ba, ba), 0.0, 1.0);
+    return length(pa - ba * h);
+}
+
+fn sd_triangle(p: vec2<f32>, p0: vec2<f32>, p1: vec2<f32>, p2: vec2<f32>) -> f32
+{
+    let e0 = p1-p0; let e1 = p2-p1; let e2 = p0-p2;
+    let v0 = p -p0; let v1 = p -p1; let v2 = p -p2;
+    let pq0 = v0 - e0*clamp( dot(v0,e0)/dot(e0,e0), 0.0, 1.0 );
+    let pq1 = v1 - e1*clamp( dot(v1,e1)/dot(e1,e1), 0.0, 1.0 );
+    let pq2 = v2 - e2*clamp( dot(v2,e2)/dot(e2,e2), 0.0, 1.0 );
+    let s = sign( e0.x*e2.y - e0.y*e2.x );
+    let d = min(min(vec2(dot(pq0,pq0), s*(v0.x*e0.y-v0.y*e0.x)),
+                     vec2(dot(pq1,pq1), s*(v1.x*e1.y-v1.y*e1.x))),
+                     vec2(dot(pq2,pq2), s*(v2.x*e2.y-v2.y*e2.x)));
+    return -sqrt(d.x)*sign(d.y);
+}
+
+fn sd_sector(p: vec2<f32>, ir: f32, or: f32, a1: f32, a2: f32) -> f32 {
+    let TAU: f32 = 6.283185307179586;
+    var delta: f32 = a2 - a1;
+    delta = delta - floor(delta / TAU) * TAU;
+
+    if (delta <= 1e-6) {
+        return length(p);
+    }
+    if (delta >= TAU - 1e-6) {
+        let r: f32 = length(p);
+        return max(r - or, ir - r);
+    }
+
+    let mid: f32 = a1 + 0.5 * delta;
+    let cm: f32 = cos(mid);
+    let sm: f32 = sin(mid);
+    let q: vec2<f32> = vec2<f32>(cm * p.x + sm * p.y, -sm * p.x + cm * p.y);
+
+    let rlen: f32 = length(q);
+    let theta: f32 = atan2(q.y, q.x);
+    let half: f32 = 0.5 * delta;
+
+    if (abs(theta) <= half) {
+        if (rlen < ir) {
+            return ir - rlen;
+        } else if (rlen > or) {
+            return rlen - or;
+        } else {
+            let d_in: f32 = rlen - ir;
+            let d_out: f32 = or - rlen;
+            return -min(d_in, d_out);
+        }
+    }
+
+    var sign: f32 = -1.0;
+    if (theta > 0.0) { sign = 1.0; }
+
+    let ch: f32 = cos(half);
+    let sh: f32 = sin(half);
+    let u: vec2<f32> = vec2<f32>(ch, sign * sh);
+
+    let t: f32 = dot(q, u);
+    let tclamped: f32 = clamp(t, ir, or);
+
+    let closest: vec2<f32> = u * tclamped;
+    return length(q - closest); // positive (outside)
+}
+
+fn sd_quad(p: vec2<f32>, v: array<vec2<f32>, 4>) -> f32 {
+    let N: u32 = 4u;
+
+    var d: f32 = dot(p - v[0], p - v[0]);
+    var s: f32 = 1.0;
+
+    var j: u32 = N - 1u;
+
+    for (var i: u32 = 0u; i < N; i = i + 1u) {
+        let vi = v[i];
+        let vj = v[j];
+
+        let e = vj - vi;
+        let w = p - vi;
+
+        let t = clamp(dot(w, e) / dot(e, e), 0.0, 1.0);
+        let b = w - e * t;
+
+        d = min(d, dot(b, b));
+
+        let cross = e.x * w.y - e.y * w.x;
+
+        if ((p.y >= vi.y && p.y < vj.y && cross > 0.0) ||
+            (p.y <  vi.y && p.y >= vj.y && cross < 0.0)) {
+            s = -s;
+        }
+
+        j = i;
+    }
+
+    return s * sqrt(d);
+}
@@ -0,0 +1,266 @@
+use std::collections::HashMap;
+
+use glam::UVec2;
+
+use crate::{backend::sampler::SamplerOptions, error::Error};
+
+/// Pixel format of the data passed to [`super::wgpu::WgpuRenderer::create_texture`]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TextureDataFormat {
+    /// 8-bit RGBA, 4 bytes per pixel
+    #[default]
+    Rgba8,
+    /// Single 8-bit channel, eg. for SDF textures. Sampled as `(r, 0, 0, 1)`.
+    R8,
+    /// 16-bit float RGBA, 8 bytes per pixel
+    Rgba16Float,
+}
+
+impl TextureDataFormat {
+    pub fn bytes_per_pixel(&self) -> u32 {
+        match self {
+            TextureDataFormat::Rgba8 => 4,
+            TextureDataFormat::R8 => 1,
+            TextureDataFormat::Rgba16Float => 8,
+        }
+    }
+
+    pub fn wgpu_format(&self) -> wgpu::TextureFormat {
+        match self {
+            TextureDataFormat::Rgba8 => wgpu::TextureFormat::Rgba8Unorm,
+            TextureDataFormat::R8 => wgpu::TextureFormat::R8Unorm,
+            TextureDataFormat::Rgba16Float => wgpu::TextureFormat::Rgba16Float,
+        }
+    }
+}
+
+/// Options for textures created by [`super::wgpu::WgpuRenderer::create_texture`]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct TextureOptions {
+    pub format: TextureDataFormat,
+    pub sampler: SamplerOptions,
+    /// Allocate a full mip chain and generate it on the GPU, so textures drawn smaller than their size don't alias.
+    /// The mips are regenerated whenever the texture is updated.
+    pub generate_mipmaps: bool,
+}
+
+/// Number of mip levels in a full mip chain for the given size
+pub(crate) fn full_mip_level_count(size: UVec2) -> u32 {
+    32 - size.max_element().max(1).leading_zeros()
+}
+
+/// Checks that `data` holds exactly `size` tightly packed pixels
+pub(crate) fn check_texture_data(
+    size: UVec2,
+    bytes_per_pixel: u32,
+    data: &[u8],
+) -> Result<(), Error> {
+    let expected_len = size.x as usize * size.y as usize * bytes_per_pixel as usize;
+    if data.len() != expected_len {
+        return Err(Error::InvalidTextureData {
+            expected_len,
+            len: data.len(),
+        });
+    }
+    Ok(())
+}
+
+/// Writes tightly packed pixel data to a region of the first mip level of a texture
+pub(crate) fn write_texture_region(
+    queue: &wgpu::Queue,
+    texture: &wgpu::Texture,
+    origin: UVec2,
+    size: UVec2,
+    bytes_per_pixel: u32,
+    data: &[u8],
+) {
+    debug_assert!(check_texture_data(size, bytes_per_pixel, data).is_ok());
+    queue.write_texture(
+        wgpu::TexelCopyTextureInfo {
+            texture,
+            mip_level: 0,
+            origin: wgpu::Origin3d {
+                x: origin.x,
+                y: origin.y,
+                z: 0,
+            },
+            aspect: wgpu::TextureAspect::All,
+        },
+        data,
+        wgpu::TexelCopyBufferLayout {
+            offset: 0,
+            bytes_per_row: Some(size.x * bytes_per_pixel),
+            rows_per_image: Some(size.y),
+        },
+        wgpu::Extent3d {
+            width: size.x,
+            height: size.y,
+            depth_or_array_layers: 1,
+        },
+    );
+}
+
+/// Generates mip chains by repeatedly downsampling each level into the next with a render pass
+pub(crate) struct MipmapGenerator {
+    shader: wgpu::ShaderModule,
+    bind_group_layout: wgpu::BindGroupLayout,
+    pipeline_layout: wgpu::PipelineLayout,
+    sampler: wgpu::Sampler,
+    /// One pipeline per texture format, created on first use
+    pipelines: HashMap<wgpu::TextureFormat, wgpu::RenderPipeline>,
+}
+
+impl MipmapGenerator {
+    pub fn new(device: &wgpu::Device) -> Self {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Mondrian mipmap shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("mipmap.wgsl").into()),
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Mipmap Bind Group Layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    count: None,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    count: None,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                },
+            ],
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Mipmap Pipeline Layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Mipmap Sampler"),
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        Self {
+            shader,
+            bind_group_layout,
+            pipeline_layout,
+            sampler,
+            pipelines: HashMap::new(),
+        }
+    }
+
+    /// Fills mip levels `1..` of the texture from level 0.
+    ///
+    /// The texture must have been created with [`wgpu::TextureUsages::RENDER_ATTACHMENT`] and a renderable format.
+    pub fn generate(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        texture: &wgpu::Texture,
+    ) {
+        if texture.mip_level_count() <= 1 {
+            return;
+        }
+
+        let format = texture.format();
+        let pipeline = self.pipelines.entry(format).or_insert_with(|| {
+            Self::create_pipeline(device, &self.pipeline_layout, &self.shader, format)
+        });
+
+        let mip_view = |level: u32| {
+            texture.create_view(&wgpu::TextureViewDescriptor {
+                label: Some("Mipmap Level View"),
+                base_mip_level: level,
+                mip_level_count: Some(1),
+                ..Default::default()
+            })
+        };
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Mipmap Encoder"),
+        });
+        for level in 1..texture.mip_level_count() {
+            let source = mip_view(level - 1);
+            let target = mip_view(level);
+            let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("Mipmap Bind Group"),
+                layout: &self.bind_group_layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::TextureView(&source),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::Sampler(&self.sampler),
+                    },
+                ],
+            });
+
+            let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Mipmap Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &target,
+                    depth_slice: None,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+            pass.set_pipeline(pipeline);
+            pass.set_bind_group(0, &bind_group, &[]);
+            pass.draw(0..3, 0..1);
+        }
+        queue.submit(Some(encoder.finish()));
+    }
+
+    fn create_pipeline(
+        device: &wgpu::Device,
+        layout: &wgpu::PipelineLayout,
+        shader: &wgpu::ShaderModule,
+        format: wgpu::TextureFormat,
+    ) -> wgpu::RenderPipeline {
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Mipmap Pipeline"),
+            layout: Some(layout),
+            vertex: wgpu::VertexState {
+                module: shader,
+                entry_point: None,
+                buffers: &[],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: shader,
+                entry_point: None,
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: None,
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+            cache: None,
+        })
+    }
+}
@@ -0,0 +1,10 @@
+@group(1) @binding(0)
+var shape_textures: binding_array<texture_2d<f32>>;
+
+fn shape_texture_size(texture_id: u32) -> vec2<f32> {
+    return vec2<f32>(textureDimensions(shape_textures[texture_id]));
+}
+
+fn sample_shape_texture(texture_id: u32, sampler_index: u32, uv: vec2<f32>, ddx: vec2<f32>, ddy: vec2<f32>) -> vec4<f32> {
+    return sample_texture(shape_textures[texture_id], sampler_index, uv, ddx, ddy);
+}
@@ -0,0 +1,45 @@
+// Compatibility path for adapters without binding arrays, with a fixed number of individually bound textures.
+// Must match `WgpuRenderer::COMPAT_MAX_TEXTURES`.
+
+@group(1) @binding(0)
+var shape_texture_0: texture_2d<f32>;
+@group(1) @binding(1)
+var shape_texture_1: texture_2d<f32>;
+@group(1) @binding(2)
+var shape_texture_2: texture_2d<f32>;
+@group(1) @binding(3)
+var shape_texture_3: texture_2d<f32>;
+@group(1) @binding(4)
+var shape_texture_4: texture_2d<f32>;
+@group(1) @binding(5)
+var shape_texture_5: texture_2d<f32>;
+@group(1) @binding(6)
+var shape_texture_6: texture_2d<f32>;
+@group(1) @binding(7)
+var shape_texture_7: texture_2d<f32>;
+
+fn shape_texture_size(texture_id: u32) -> vec2<f32> {
+    switch(texture_id) {
+        case 1u: { return vec2<f32>(textureDimensions(shape_texture_1)); }
+        case 2u: { return vec2<f32>(textureDimensions(shape_texture_2)); }
+        case 3u: { return vec2<f32>(textureDimensions(shape_texture_3)); }
+        case 4u: { return vec2<f32>(textureDimensions(shape_texture_4)); }
+        case 5u: { return vec2<f32>(textureDimensions(shape_texture_5)); }
+        case 6u: { return vec2<f32>(textureDimensions(shape_texture_6)); }
+        case 7u: { return vec2<f32>(textureDimensions(shape_texture_7)); }
+        default: { return vec2<f32>(textureDimensions(shape_texture_0)); }
+    }
+}
+
+fn sample_shape_texture(texture_id: u32, sampler_index: u32, uv: vec2<f32>, ddx: vec2<f32>, ddy: vec2<f32>) -> vec4<f32> {
+    switch(texture_id) {
+        case 1u: { return sample_texture(shape_texture_1, sampler_index, uv, ddx, ddy); }
+        case 2u: { return sample_texture(shape_texture_2, sampler_index, uv, ddx, ddy); }
+        case 3u: { return sample_texture(shape_texture_3, sampler_index, uv, ddx, ddy); }
+        case 4u: { return sample_texture(shape_texture_4, sampler_index, uv, ddx, ddy); }
+        case 5u: { return sample_texture(shape_texture_5, sampler_index, uv, ddx, ddy); }
+        case 6u: { return sample_texture(shape_texture_6, sampler_index, uv, ddx, ddy); }
+        case 7u: { return sample_texture(shape_texture_7, sampler_index, uv, ddx, ddy); }
+        default: { return sample_texture(shape_texture_0, sampler_index, uv, ddx, ddy); }
+    }
+}
@@ -1,51 +1,185 @@
-use std::{collections::HashMap, num::NonZeroU32};
+use std::{
+    collections::{HashMap, HashSet},
+    num::NonZeroU32,
+};
 
+use glam::{UVec2, Vec4, uvec2};
 use slotmap::SlotMap;
-use wgpu::{ShaderStages, util::DeviceExt};
+use wgpu::ShaderStages;
 
 use crate::{
     Shape,
-    backend::common::{GpuShape, ShapeHeader},
+    backend::{
+        atlas::{AtlasAllocation, AtlasConfig, TextureAtlas},
+        buffer::{BufferGrowthPolicy, BufferStats, GrowableBuffer, UploadMode, Uploader},
+        common::{GpuShape, ScreenConstants},
+        sampler::SamplerOptions,
+        texture::{
+            MipmapGenerator, TextureOptions, check_texture_data, full_mip_level_count,
+            write_texture_region,
+        },
+    },
     binner::ShapeBinner,
+    error::{Diagnostics, Error},
+    scene::SceneDamage,
     shape::TextureId,
 };
 
+/// Optional device features used by the renderer. Without them, the renderer falls back to a compatibility path that
+/// works on WebGPU and other restricted adapters.
+///
+/// Storage buffers in fragment shaders are still required, so downlevel backends without them (eg. WebGL2) are unsupported.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RendererFeatures {
+    /// Pass per-frame constants through push constants instead of a uniform buffer
+    pub push_constants: bool,
+    /// Bind up to [`WgpuRenderer::MAX_TEXTURES`] textures through a binding array. Otherwise only
+    /// [`WgpuRenderer::COMPAT_MAX_TEXTURES`] distinct textures can be drawn per frame, so small images should be packed
+    /// into the atlas, where a whole atlas page only takes a single texture slot.
+    pub binding_array: bool,
+}
+
+impl RendererFeatures {
+    /// Features and limits the renderer makes use of when available
+    pub const OPTIONAL_FEATURES: wgpu::Features = wgpu::Features::PUSH_CONSTANTS
+        .union(wgpu::Features::TEXTURE_BINDING_ARRAY)
+        .union(wgpu::Features::SAMPLED_TEXTURE_AND_STORAGE_BUFFER_ARRAY_NON_UNIFORM_INDEXING);
+
+    /// Detects which features are enabled on the device
+    pub fn from_device(device: &wgpu::Device) -> Self {
+        Self::from_features(device.features(), &device.limits())
+    }
+
+    pub fn from_features(features: wgpu::Features, limits: &wgpu::Limits) -> Self {
+        Self {
+            push_constants: features.contains(wgpu::Features::PUSH_CONSTANTS)
+                && limits.max_push_constant_size >= size_of::<ScreenConstants>() as u32,
+            binding_array: features.contains(
+                wgpu::Features::TEXTURE_BINDING_ARRAY
+                    | wgpu::Features::SAMPLED_TEXTURE_AND_STORAGE_BUFFER_ARRAY_NON_UNIFORM_INDEXING,
+            ) && limits.max_binding_array_elements_per_shader_stage
+                >= WgpuRenderer::MAX_TEXTURES,
+        }
+    }
+
+    /// Limits to request from the adapter to enable the given features, on top of `base`
+    pub fn required_limits(&self, base: wgpu::Limits) -> wgpu::Limits {
+        let mut limits = base;
+        if self.push_constants {
+            limits.max_push_constant_size = limits
+                .max_push_constant_size
+                .max(size_of::<ScreenConstants>() as u32);
+        }
+        if self.binding_array {
+            limits.max_binding_array_elements_per_shader_stage = limits
+                .max_binding_array_elements_per_shader_stage
+                .max(WgpuRenderer::MAX_TEXTURES);
+        }
+        limits
+    }
+}
+
 pub struct WgpuRenderer {
+    features: RendererFeatures,
     pipeline: wgpu::RenderPipeline,
 
     texture_bind_group_layout: wgpu::BindGroupLayout,
     texture_bind_group: wgpu::BindGroup,
-    texture_sampler: wgpu::Sampler,
+    /// One sampler per [`SamplerOptions`] configuration, bound after the texture array
+    texture_samplers: [wgpu::Sampler; SamplerOptions::COUNT],
 
     shape_buffer_bind_group_layout: wgpu::BindGroupLayout,
     shape_buffer_bind_group: wgpu::BindGroup,
 
-    shape_buffer: wgpu::Buffer,
-    /// Size of the shape buffer in elements
-    shape_buffer_size: usize,
+    shape_buffer: GrowableBuffer,
+    shape_ranges_buffer: GrowableBuffer,
+    shape_indices_buffer: GrowableBuffer,
 
-    shape_ranges_buffer: wgpu::Buffer,
-    /// Size of the shape ranges buffer in elements
-    shape_ranges_buffer_size: usize,
+    buffer_growth_policy: BufferGrowthPolicy,
+    buffer_stats: BufferStats,
+    uploader: Uploader,
 
-    shape_indices_buffer: wgpu::Buffer,
-    /// Size of the shape indices buffer in elements
-    shape_indices_buffer_size: usize,
+    screen_constants: ScreenConstants,
+    /// Holds the screen constants when push constants aren't available
+    screen_uniform: Option<(wgpu::Buffer, wgpu::BindGroup)>,
 
-    screen_width_tiles: u32,
-
-    textures: SlotMap<TextureId, wgpu::TextureView>,
+    textures: SlotMap<TextureId, RegisteredTexture>,
     texture_id_map: HashMap<TextureId, u32>,
     placeholder_texture: wgpu::TextureView,
+    atlas: TextureAtlas,
+    mipmap_generator: MipmapGenerator,
+    diagnostics: Diagnostics,
+    /// Unknown texture IDs that were already warned about, until they stop being used
+    warned_unknown_textures: HashSet<TextureId>,
+    /// Whether running out of texture slots was already warned about, until a frame fits again
+    warned_too_many_textures: bool,
+    /// Incremented on every prepare, used to track when atlas textures were last used
+    frame_index: u64,
+}
+
+enum RegisteredTexture {
+    View {
+        view: wgpu::TextureView,
+        sampler: SamplerOptions,
+    },
+    Atlas {
+        allocation: AtlasAllocation,
+        last_used_frame: u64,
+        sampler: SamplerOptions,
+    },
+}
+
+impl RegisteredTexture {
+    fn sampler(&self) -> SamplerOptions {
+        match self {
+            RegisteredTexture::View { sampler, .. } | RegisteredTexture::Atlas { sampler, .. } => {
+                *sampler
+            }
+        }
+    }
 }
 
 impl WgpuRenderer {
-    const MAX_TEXTURES: u32 = 1024;
+    /// Maximum number of distinct textures per frame when binding arrays are available
+    pub const MAX_TEXTURES: u32 = 1024;
+    /// Maximum number of distinct textures per frame on the compatibility path, see [`RendererFeatures::binding_array`]
+    pub const COMPAT_MAX_TEXTURES: u32 = 8;
+    /// First binding of the samplers in the texture bind group, after the texture bindings
+    const SAMPLER_BINDING: u32 = 8;
 
+    /// Creates a renderer using the optional features enabled on the device
     pub fn new(device: &wgpu::Device, format: wgpu::TextureFormat) -> Self {
+        Self::with_features(device, format, RendererFeatures::from_device(device))
+    }
+
+    /// Creates a renderer using only the given features, which must be enabled on the device
+    pub fn with_features(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        features: RendererFeatures,
+    ) -> Self {
+        let screen_source = if features.push_constants {
+            include_str!("screen_push_constant.wgsl")
+        } else {
+            include_str!("screen_uniform.wgsl")
+        };
+        let textures_source = if features.binding_array {
+            include_str!("textures_binding_array.wgsl")
+        } else {
+            include_str!("textures_fixed.wgsl")
+        };
         let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
             label: Some("Mondrian main drawing shader"),
-            source: wgpu::ShaderSource::Wgsl(include_str!("main.wgsl").into()),
+            source: wgpu::ShaderSource::Wgsl(
+                [
+                    screen_source,
+                    textures_source,
+                    include_str!("sdf.wgsl"),
+                    include_str!("main.wgsl"),
+                ]
+                .join("\n")
+                .into(),
+            ),
         });
 
         let shape_buffer_bind_group_layout =
@@ -85,36 +219,89 @@ impl WgpuRenderer {
                 ],
             });
 
+        let texture_layout_entry = |binding, count| wgpu::BindGroupLayoutEntry {
+            binding,
+            count,
+            ty: wgpu::BindingType::Texture {
+                sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                view_dimension: wgpu::TextureViewDimension::D2,
+                multisampled: false,
+            },
+            visibility: wgpu::ShaderStages::FRAGMENT,
+        };
+        let mut texture_layout_entries = if features.binding_array {
+            vec![texture_layout_entry(0, NonZeroU32::new(Self::MAX_TEXTURES))]
+        } else {
+            (0..Self::COMPAT_MAX_TEXTURES)
+                .map(|binding| texture_layout_entry(binding, None))
+                .collect()
+        };
+        texture_layout_entries.extend((0..SamplerOptions::COUNT).map(|i| {
+            wgpu::BindGroupLayoutEntry {
+                binding: Self::SAMPLER_BINDING + i as u32,
+                count: None,
+                ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                visibility: wgpu::ShaderStages::FRAGMENT,
+            }
+        }));
         let texture_bind_group_layout =
             device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
                 label: Some("Texture Bind Group Layout"),
-                entries: &[
-                    wgpu::BindGroupLayoutEntry {
-                        binding: 0,
-                        count: NonZeroU32::new(Self::MAX_TEXTURES),
-                        ty: wgpu::BindingType::Texture {
-                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
-                            view_dimension: wgpu::TextureViewDimension::D2,
-                            multisampled: false,
-                        },
-                        visibility: wgpu::ShaderStages::FRAGMENT,
-                    },
-                    wgpu::BindGroupLayoutEntry {
-                        binding: 1,
-                        count: None,
-                        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
-                        visibility: wgpu::ShaderStages::FRAGMENT,
+                entries: &texture_layout_entries,
+            });
+
+        let screen_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("Screen Constants Bind Group Layout"),
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    count: None,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
                     },
-                ],
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                }],
             });
 
-        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-            label: Some("Shape Pipeline Layout"),
-            bind_group_layouts: &[&shape_buffer_bind_group_layout, &texture_bind_group_layout],
-            push_constant_ranges: &[wgpu::PushConstantRange {
-                stages: wgpu::ShaderStages::FRAGMENT,
-                range: 0..4,
-            }],
+        let pipeline_layout = if features.push_constants {
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Shape Pipeline Layout"),
+                bind_group_layouts: &[&shape_buffer_bind_group_layout, &texture_bind_group_layout],
+                push_constant_ranges: &[wgpu::PushConstantRange {
+                    stages: wgpu::ShaderStages::FRAGMENT,
+                    range: 0..size_of::<ScreenConstants>() as u32,
+                }],
+            })
+        } else {
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Shape Pipeline Layout"),
+                bind_group_layouts: &[
+                    &shape_buffer_bind_group_layout,
+                    &texture_bind_group_layout,
+                    &screen_bind_group_layout,
+                ],
+                push_constant_ranges: &[],
+            })
+        };
+
+        let screen_uniform = (!features.push_constants).then(|| {
+            let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("Screen Constants Buffer"),
+                size: size_of::<ScreenConstants>() as u64,
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            });
+            let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("Screen Constants Bind Group"),
+                layout: &screen_bind_group_layout,
+                entries: &[wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: buffer.as_entire_binding(),
+                }],
+            });
+            (buffer, bind_group)
         });
 
         let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
@@ -143,68 +330,33 @@ impl WgpuRenderer {
             cache: None,
         });
 
-        let shape_buffer = device.create_buffer(&wgpu::BufferDescriptor {
-            label: Some("Shape Buffer"),
-            size: size_of::<GpuShape>() as u64,
-            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
-            mapped_at_creation: false,
-        });
-
-        let shape_ranges_buffer = device.create_buffer(&wgpu::BufferDescriptor {
-            label: Some("Shape Ranges Buffer"),
-            size: 4,
-            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
-            mapped_at_creation: false,
-        });
-
-        let shape_indices_buffer = device.create_buffer(&wgpu::BufferDescriptor {
-            label: Some("Shape Indices Buffer"),
-            size: 4,
-            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
-            mapped_at_creation: false,
-        });
+        let buffer_growth_policy = BufferGrowthPolicy::default();
+        let shape_buffer = GrowableBuffer::new(
+            device,
+            "Shape Buffer",
+            size_of::<GpuShape>(),
+            &buffer_growth_policy,
+        );
+        let shape_ranges_buffer = GrowableBuffer::new(
+            device,
+            "Shape Ranges Buffer",
+            size_of::<u32>(),
+            &buffer_growth_policy,
+        );
+        let shape_indices_buffer = GrowableBuffer::new(
+            device,
+            "Shape Indices Buffer",
+            size_of::<u32>(),
+            &buffer_growth_policy,
+        );
 
-        let shape_buffer_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            label: Some("Shape Buffer Bind Group"),
-            layout: &shape_buffer_bind_group_layout,
-            entries: &[
-                wgpu::BindGroupEntry {
-                    binding: 0,
-                    resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
-                        buffer: &shape_buffer,
-                        offset: 0,
-                        size: None,
-                    }),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 1,
-                    resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
-                        buffer: &shape_ranges_buffer,
-                        offset: 0,
-                        size: None,
-                    }),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 2,
-                    resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
-                        buffer: &shape_indices_buffer,
-                        offset: 0,
-                        size: None,
-                    }),
-                },
-            ],
-        });
+        let shape_buffer_bind_group = Self::create_shapes_bind_group(
+            device,
+            &shape_buffer_bind_group_layout,
+            [&shape_buffer, &shape_ranges_buffer, &shape_indices_buffer],
+        );
 
-        let texture_sampler = device.create_sampler(&wgpu::SamplerDescriptor {
-            label: Some("Texture Sampler"),
-            address_mode_u: wgpu::AddressMode::ClampToEdge,
-            address_mode_v: wgpu::AddressMode::ClampToEdge,
-            address_mode_w: wgpu::AddressMode::ClampToEdge,
-            mag_filter: wgpu::FilterMode::Linear,
-            min_filter: wgpu::FilterMode::Linear,
-            mipmap_filter: wgpu::FilterMode::Linear,
-            ..Default::default()
-        });
+        let texture_samplers = SamplerOptions::all().map(|options| options.create_sampler(device));
 
         let placeholder_texture = device
             .create_texture(&wgpu::TextureDescriptor {
@@ -223,75 +375,174 @@ impl WgpuRenderer {
             })
             .create_view(&wgpu::TextureViewDescriptor::default());
 
-        let texture_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            label: Some("Texture Bind Group"),
-            layout: &texture_bind_group_layout,
-            entries: &[
-                wgpu::BindGroupEntry {
-                    binding: 0,
-                    resource: wgpu::BindingResource::TextureViewArray(
-                        &[&placeholder_texture; Self::MAX_TEXTURES as usize],
-                    ),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 1,
-                    resource: wgpu::BindingResource::Sampler(&texture_sampler),
-                },
-            ],
-        });
+        let texture_bind_group = Self::create_texture_bind_group(
+            device,
+            &texture_bind_group_layout,
+            features,
+            &vec![&placeholder_texture; Self::texture_slots(features)],
+            &texture_samplers,
+        );
 
         Self {
+            features,
             pipeline,
             texture_bind_group,
             texture_bind_group_layout,
-            texture_sampler,
+            texture_samplers,
             shape_buffer_bind_group,
             shape_buffer,
             shape_ranges_buffer,
-            shape_ranges_buffer_size: 1,
             shape_indices_buffer,
-            shape_indices_buffer_size: 1,
             shape_buffer_bind_group_layout,
-            shape_buffer_size: 1,
-            screen_width_tiles: 0,
+            buffer_growth_policy,
+            buffer_stats: BufferStats::default(),
+            uploader: Uploader::new(UploadMode::default()),
+            screen_constants: ScreenConstants::default(),
+            screen_uniform,
 
             textures: SlotMap::with_key(),
             texture_id_map: HashMap::new(),
             placeholder_texture,
+            atlas: TextureAtlas::new(AtlasConfig::default()),
+            mipmap_generator: MipmapGenerator::new(device),
+            diagnostics: Diagnostics::default(),
+            warned_unknown_textures: HashSet::new(),
+            warned_too_many_textures: false,
+            frame_index: 0,
         }
     }
 
-    fn recreate_shapes_bind_group(&mut self, device: &wgpu::Device) {
-        self.shape_buffer_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+    fn create_shapes_bind_group(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        buffers: [&GrowableBuffer; 3],
+    ) -> wgpu::BindGroup {
+        let entries = buffers.map(|buffer| buffer.buffer().as_entire_buffer_binding());
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
             label: Some("Shape Buffer Bind Group"),
-            layout: &self.shape_buffer_bind_group_layout,
+            layout,
             entries: &[
                 wgpu::BindGroupEntry {
                     binding: 0,
-                    resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
-                        buffer: &self.shape_buffer,
-                        offset: 0,
-                        size: None,
-                    }),
+                    resource: wgpu::BindingResource::Buffer(entries[0].clone()),
                 },
                 wgpu::BindGroupEntry {
                     binding: 1,
-                    resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
-                        buffer: &self.shape_ranges_buffer,
-                        offset: 0,
-                        size: None,
-                    }),
+                    resource: wgpu::BindingResource::Buffer(entries[1].clone()),
                 },
                 wgpu::BindGroupEntry {
                     binding: 2,
-                    resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
-                        buffer: &self.shape_indices_buffer,
-                        offset: 0,
-                        size: None,
-                    }),
+                    resource: wgpu::BindingResource::Buffer(entries[2].clone()),
                 },
             ],
-        });
+        })
+    }
+
+    /// Number of texture slots in the texture bind group
+    fn texture_slots(features: RendererFeatures) -> usize {
+        if features.binding_array {
+            Self::MAX_TEXTURES as usize
+        } else {
+            Self::COMPAT_MAX_TEXTURES as usize
+        }
+    }
+
+    /// The features the renderer was created with
+    pub fn features(&self) -> RendererFeatures {
+        self.features
+    }
+
+    fn create_texture_bind_group(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        features: RendererFeatures,
+        texture_views: &[&wgpu::TextureView],
+        samplers: &[wgpu::Sampler; SamplerOptions::COUNT],
+    ) -> wgpu::BindGroup {
+        let mut entries = if features.binding_array {
+            vec![wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureViewArray(texture_views),
+            }]
+        } else {
+            texture_views
+                .iter()
+                .enumerate()
+                .map(|(binding, view)| wgpu::BindGroupEntry {
+                    binding: binding as u32,
+                    resource: wgpu::BindingResource::TextureView(view),
+                })
+                .collect()
+        };
+        entries.extend(
+            samplers
+                .iter()
+                .enumerate()
+                .map(|(i, sampler)| wgpu::BindGroupEntry {
+                    binding: Self::SAMPLER_BINDING + i as u32,
+                    resource: wgpu::BindingResource::Sampler(sampler),
+                }),
+        );
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Texture Bind Group"),
+            layout,
+            entries: &entries,
+        })
+    }
+
+    /// Makes sure the shape buffers can hold the given data, recreating the bind group if any of them was reallocated.
+    ///
+    /// Returns true if a buffer was reallocated, in which case all buffer contents must be re-uploaded.
+    fn reserve_shape_buffers(
+        &mut self,
+        device: &wgpu::Device,
+        num_shapes: usize,
+        binner: &ShapeBinner,
+    ) -> bool {
+        let policy = self.buffer_growth_policy;
+        let stats = &mut self.buffer_stats;
+        // Not short-circuiting, every buffer gets a chance to grow or shrink each frame
+        let reallocated = self
+            .shape_buffer
+            .reserve(device, num_shapes, &policy, stats)
+            | self
+                .shape_ranges_buffer
+                .reserve(device, binner.tile_ranges.len(), &policy, stats)
+            | self
+                .shape_indices_buffer
+                .reserve(device, binner.shape_indices.len(), &policy, stats);
+
+        if reallocated {
+            self.shape_buffer_bind_group = Self::create_shapes_bind_group(
+                device,
+                &self.shape_buffer_bind_group_layout,
+                [
+                    &self.shape_buffer,
+                    &self.shape_ranges_buffer,
+                    &self.shape_indices_buffer,
+                ],
+            );
+        }
+        self.buffer_stats.allocated_bytes = self.shape_buffer.size_bytes()
+            + self.shape_ranges_buffer.size_bytes()
+            + self.shape_indices_buffer.size_bytes();
+
+        reallocated
+    }
+
+    fn gpu_shape(&self, shape: &Shape) -> GpuShape {
+        let texture_id = shape
+            .texture_id
+            .and_then(|tex_id| self.texture_id_map.get(&tex_id).cloned());
+        let texture = shape.texture_id.and_then(|id| self.textures.get(id));
+        let texture_rect = match texture {
+            Some(RegisteredTexture::Atlas { allocation, .. }) => {
+                allocation.uv_rect(self.atlas.config.page_size)
+            }
+            _ => Vec4::new(0.0, 0.0, 1.0, 1.0),
+        };
+        let sampler = texture.map(|t| t.sampler()).unwrap_or_default();
+        GpuShape::from_shape(shape, texture_id, texture_rect, sampler)
     }
 
     fn prepare_shape_buffers(
@@ -301,72 +552,59 @@ impl WgpuRenderer {
         shapes: &[Shape],
         binner: &ShapeBinner,
     ) {
-        let mut gpu_shapes: Vec<GpuShape> = shapes
-            .iter()
-            .map(|s| {
-                let texture_id = s
-                    .texture_id
-                    .and_then(|tex_id| self.texture_id_map.get(&tex_id).cloned());
-                GpuShape::from_shape(s, texture_id)
-            })
-            .collect();
-        if gpu_shapes.len() < self.shape_buffer_size {
-            gpu_shapes.resize(
-                self.shape_buffer_size,
-                GpuShape {
-                    header: ShapeHeader::SENTINEL,
-                    ..Default::default()
-                },
-            );
-        }
+        self.reserve_shape_buffers(device, shapes.len(), binner);
 
-        let shape_data = bytemuck::cast_slice(&gpu_shapes);
-        if self.shape_buffer_size < shapes.len() {
-            self.shape_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                label: Some("Shape Buffer"),
-                contents: shape_data,
-                usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
-            });
-            self.recreate_shapes_bind_group(device);
-            self.shape_buffer_size = gpu_shapes.len();
-        } else {
-            queue.write_buffer(&self.shape_buffer, 0, shape_data);
-        }
+        let gpu_shapes: Vec<GpuShape> = shapes.iter().map(|s| self.gpu_shape(s)).collect();
 
-        let shape_ranges_data = bytemuck::cast_slice(&binner.tile_ranges);
-        if self.shape_ranges_buffer_size < binner.tile_ranges.len() {
-            self.shape_ranges_buffer =
-                device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                    label: Some("Shape Ranges Buffer"),
-                    contents: shape_ranges_data,
-                    usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
-                });
-            self.recreate_shapes_bind_group(device);
-            self.shape_ranges_buffer_size = binner.tile_ranges.len();
-        } else {
-            queue.write_buffer(&self.shape_ranges_buffer, 0, shape_ranges_data);
-        }
-
-        let shape_indices_data = bytemuck::cast_slice(&binner.shape_indices);
-        if self.shape_indices_buffer_size < binner.shape_indices.len() {
-            self.shape_indices_buffer =
-                device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                    label: Some("Shape Indices Buffer"),
-                    contents: shape_indices_data,
-                    usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
-                });
-            self.recreate_shapes_bind_group(device);
-            self.shape_indices_buffer_size = binner.shape_indices.len();
-        } else {
-            queue.write_buffer(&self.shape_indices_buffer, 0, shape_indices_data);
-        }
+        let stats = &mut self.buffer_stats;
+        self.uploader.write(
+            device,
+            queue,
+            self.shape_buffer.buffer(),
+            0,
+            bytemuck::cast_slice(&gpu_shapes),
+            stats,
+        );
+        self.uploader.write(
+            device,
+            queue,
+            self.shape_ranges_buffer.buffer(),
+            0,
+            bytemuck::cast_slice(&binner.tile_ranges),
+            stats,
+        );
+        self.uploader.write(
+            device,
+            queue,
+            self.shape_indices_buffer.buffer(),
+            0,
+            bytemuck::cast_slice(&binner.shape_indices),
+            stats,
+        );
+        self.uploader.flush(queue);
     }
 
     /// Collects texture views from the shapes and prepares the texture bind group
-    fn prepare_textures_bind_group(&mut self, device: &wgpu::Device, shapes: &[Shape]) {
-        self.texture_id_map.clear();
-        let mut texture_views: Vec<&wgpu::TextureView> =
-            Vec::with_capacity(Self::MAX_TEXTURES as usize);
+    ///
+    /// Returns true if the mapping from texture IDs to bind group slots changed.
+    fn prepare_textures_bind_group(&mut self, device: &wgpu::Device, shapes: &[Shape]) -> bool {
+        let previous_texture_id_map = std::mem::take(&mut self.texture_id_map);
+
+        for shape in shapes {
+            if let Some(RegisteredTexture::Atlas {
+                last_used_frame, ..
+            }) = shape.texture_id.and_then(|id| self.textures.get_mut(id))
+            {
+                *last_used_frame = self.frame_index;
+            }
+        }
+
+        let max_textures = Self::texture_slots(self.features);
+        let mut texture_views: Vec<&wgpu::TextureView> = Vec::with_capacity(max_textures);
+        // All textures on an atlas page share the page's slot
+        let mut atlas_page_slots: Vec<Option<u32>> = vec![None; self.atlas.pages.len()];
+        let mut unknown_textures = HashSet::new();
+        let mut too_many_textures = false;
         for shape in shapes {
             let Some(tex_id) = shape.texture_id else {
                 continue;
@@ -375,49 +613,333 @@ impl WgpuRenderer {
                 continue;
             };
 
-            if let Some(texture_view) = self.textures.get(tex_id) {
-                let new_id = texture_views.len() as u32;
-                self.texture_id_map.insert(tex_id, new_id);
-                texture_views.push(texture_view);
-                if texture_views.len() >= Self::MAX_TEXTURES as usize {
-                    println!(
-                        "Warning: Reached maximum number of textures ({}). Some textures will not be available.",
-                        Self::MAX_TEXTURES
-                    );
-                    break;
+            let view = match self.textures.get(tex_id) {
+                Some(RegisteredTexture::View { view, .. }) => view,
+                Some(RegisteredTexture::Atlas { allocation, .. }) => {
+                    if let Some(slot) = atlas_page_slots[allocation.page] {
+                        self.texture_id_map.insert(tex_id, slot);
+                        continue;
+                    }
+                    &self.atlas.pages[allocation.page].view
                 }
+                None => {
+                    if unknown_textures.insert(tex_id)
+                        && !self.warned_unknown_textures.contains(&tex_id)
+                    {
+                        self.diagnostics.warn(Error::UnknownTexture(tex_id));
+                    }
+                    continue;
+                }
+            };
+
+            if texture_views.len() >= max_textures {
+                if !self.warned_too_many_textures {
+                    self.diagnostics.warn(Error::TooManyTextures {
+                        max: max_textures as u32,
+                    });
+                }
+                too_many_textures = true;
+                break;
             }
+            texture_views.push(view);
+            let slot = texture_views.len() as u32 - 1;
+            if let Some(RegisteredTexture::Atlas { allocation, .. }) = self.textures.get(tex_id) {
+                atlas_page_slots[allocation.page] = Some(slot);
+            }
+            self.texture_id_map.insert(tex_id, slot);
         }
 
+        // Warn again only after the problem went away for a frame
+        self.warned_unknown_textures = unknown_textures;
+        self.warned_too_many_textures = too_many_textures;
+
+        let changed = self.texture_id_map != previous_texture_id_map;
+
         // Skip recreating the bind group if there are no textures in this frame
         if texture_views.is_empty() {
-            return;
+            return changed;
         }
 
-        texture_views.resize(Self::MAX_TEXTURES as usize, &self.placeholder_texture);
+        texture_views.resize(max_textures, &self.placeholder_texture);
 
-        self.texture_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            label: Some("Texture Bind Group"),
-            layout: &self.texture_bind_group_layout,
-            entries: &[
-                wgpu::BindGroupEntry {
-                    binding: 0,
-                    resource: wgpu::BindingResource::TextureViewArray(&texture_views),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 1,
-                    resource: wgpu::BindingResource::Sampler(&self.texture_sampler),
-                },
-            ],
+        self.texture_bind_group = Self::create_texture_bind_group(
+            device,
+            &self.texture_bind_group_layout,
+            self.features,
+            &texture_views,
+            &self.texture_samplers,
+        );
+
+        changed
+    }
+
+    /// Registers a texture, sampled with the given filtering and address mode.
+    pub fn register_texture(
+        &mut self,
+        texture_view: wgpu::TextureView,
+        sampler: SamplerOptions,
+    ) -> TextureId {
+        self.textures.insert(RegisteredTexture::View {
+            view: texture_view,
+            sampler,
+        })
+    }
+
+    /// Creates a texture from tightly packed pixel data in the given format, and registers it.
+    pub fn create_texture(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        size: (u32, u32),
+        data: &[u8],
+        options: TextureOptions,
+    ) -> Result<TextureId, Error> {
+        let size = uvec2(size.0, size.1);
+        check_texture_data(size, options.format.bytes_per_pixel(), data)?;
+        let (mip_level_count, usage) = if options.generate_mipmaps {
+            (
+                full_mip_level_count(size),
+                wgpu::TextureUsages::TEXTURE_BINDING
+                    | wgpu::TextureUsages::COPY_DST
+                    | wgpu::TextureUsages::RENDER_ATTACHMENT,
+            )
+        } else {
+            (
+                1,
+                wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            )
+        };
+
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Mondrian Texture"),
+            size: wgpu::Extent3d {
+                width: size.x,
+                height: size.y,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: options.format.wgpu_format(),
+            usage,
+            view_formats: &[],
         });
+        write_texture_region(
+            queue,
+            &texture,
+            UVec2::ZERO,
+            size,
+            options.format.bytes_per_pixel(),
+            data,
+        );
+        self.mipmap_generator.generate(device, queue, &texture);
+
+        Ok(self.register_texture(
+            texture.create_view(&wgpu::TextureViewDescriptor::default()),
+            options.sampler,
+        ))
+    }
+
+    /// Overwrites a region of a registered texture with tightly packed pixel data in the texture's format.
+    ///
+    /// Atlas textures are RGBA8, and `origin` is relative to the image's position in the atlas. Mipmaps are regenerated
+    /// if the texture has any and can be rendered to, eg. when created through [`WgpuRenderer::create_texture`].
+    pub fn update_texture(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        texture_id: TextureId,
+        origin: (u32, u32),
+        size: (u32, u32),
+        data: &[u8],
+    ) -> Result<(), Error> {
+        let (origin, size) = (uvec2(origin.0, origin.1), uvec2(size.0, size.1));
+        match self.textures.get(texture_id) {
+            Some(RegisteredTexture::View { view, .. }) => {
+                let texture = view.texture();
+                let texture_size = uvec2(texture.width(), texture.height());
+                if (origin + size).cmpgt(texture_size).any() {
+                    return Err(Error::TextureRegionOutOfBounds);
+                }
+                let bytes_per_pixel = texture
+                    .format()
+                    .block_copy_size(Some(wgpu::TextureAspect::All))
+                    .ok_or(Error::UnsupportedTextureFormat(texture.format()))?;
+                check_texture_data(size, bytes_per_pixel, data)?;
+                write_texture_region(queue, texture, origin, size, bytes_per_pixel, data);
+
+                if texture
+                    .usage()
+                    .contains(wgpu::TextureUsages::RENDER_ATTACHMENT)
+                {
+                    self.mipmap_generator.generate(device, queue, texture);
+                }
+            }
+            Some(RegisteredTexture::Atlas { allocation, .. }) => {
+                if (origin + size).cmpgt(allocation.size).any() {
+                    return Err(Error::TextureRegionOutOfBounds);
+                }
+                check_texture_data(size, 4, data)?;
+                let region = AtlasAllocation {
+                    page: allocation.page,
+                    position: allocation.position + origin,
+                    size,
+                };
+                self.atlas.write(queue, &region, data);
+            }
+            None => return Err(Error::UnknownTexture(texture_id)),
+        }
+        Ok(())
+    }
+
+    /// Regenerates the mip chain of a registered texture from its first level.
+    ///
+    /// Use this for textures registered through [`WgpuRenderer::register_texture`] whose contents were changed outside of
+    /// the renderer. The texture must be renderable and have [`wgpu::TextureUsages::RENDER_ATTACHMENT`]. Atlas
+    /// textures don't have mipmaps and are skipped.
+    pub fn generate_mipmaps(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        texture_id: TextureId,
+    ) {
+        if let Some(RegisteredTexture::View { view, .. }) = self.textures.get(texture_id) {
+            self.mipmap_generator
+                .generate(device, queue, view.texture());
+        }
+    }
+
+    /// Packs a small RGBA8 image into the shared texture atlas, instead of giving it its own texture slot.
+    ///
+    /// `rgba` must contain `size.0 * size.1` tightly packed RGBA8 pixels. Returns [`Error::AtlasFull`] if the image is too
+    /// large for the atlas (see [`AtlasConfig::max_entry_size`]), or if the atlas is full and no unused images could be evicted.
+    ///
+    /// Repeat and mirror address modes are applied to the atlas entry itself, not to the whole atlas page.
+    pub fn register_atlas_texture(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        size: (u32, u32),
+        rgba: &[u8],
+        sampler: SamplerOptions,
+    ) -> Result<TextureId, Error> {
+        let size = uvec2(size.0, size.1);
+        check_texture_data(size, 4, rgba)?;
+
+        let allocation = loop {
+            if let Some(allocation) = self.atlas.allocate(device, size) {
+                break allocation;
+            }
+            if !self.atlas.config.evict_when_full
+                || !self.atlas.accepts(size)
+                || !self.evict_least_recently_used()
+            {
+                return Err(Error::AtlasFull);
+            }
+        };
+
+        self.atlas.write(queue, &allocation, rgba);
+        Ok(self.textures.insert(RegisteredTexture::Atlas {
+            allocation,
+            last_used_frame: self.frame_index,
+            sampler,
+        }))
+    }
+
+    /// Evicts the atlas texture that has gone unused the longest. Textures used in the most recent frame are never evicted.
+    ///
+    /// Returns false if there was nothing to evict.
+    fn evict_least_recently_used(&mut self) -> bool {
+        let least_recently_used = self
+            .textures
+            .iter()
+            .filter_map(|(id, texture)| match texture {
+                RegisteredTexture::Atlas {
+                    last_used_frame, ..
+                } if *last_used_frame < self.frame_index => Some((id, *last_used_frame)),
+                _ => None,
+            })
+            .min_by_key(|(_, last_used_frame)| *last_used_frame);
+
+        match least_recently_used {
+            Some((id, _)) => {
+                self.unregister_texture(id);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Evicts all atlas textures that haven't been used for more than `max_unused_frames` frames.
+    ///
+    /// Returns the number of evicted textures. Their IDs become invalid, shapes still referencing them are drawn untextured.
+    pub fn evict_unused_atlas_textures(&mut self, max_unused_frames: u64) -> usize {
+        let expired: Vec<TextureId> = self
+            .textures
+            .iter()
+            .filter_map(|(id, texture)| match texture {
+                RegisteredTexture::Atlas {
+                    last_used_frame, ..
+                } if self.frame_index - last_used_frame > max_unused_frames => Some(id),
+                _ => None,
+            })
+            .collect();
+
+        for &id in &expired {
+            self.unregister_texture(id);
+        }
+        expired.len()
+    }
+
+    /// Sets the atlas configuration. Must be called before any atlas textures are registered, returns
+    /// [`Error::AtlasInUse`] otherwise.
+    pub fn set_atlas_config(&mut self, config: AtlasConfig) -> Result<(), Error> {
+        if !self.atlas.pages.is_empty() {
+            return Err(Error::AtlasInUse);
+        }
+        self.atlas.config = config;
+        Ok(())
+    }
+
+    /// Sets the handler that receives warnings, eg. about unknown textures or exceeding the texture limit.
+    /// Without a handler, warnings are logged through the `log` crate.
+    pub fn set_warning_handler(&mut self, handler: impl FnMut(&Error) + Send + 'static) {
+        self.diagnostics.set_handler(handler);
     }
 
-    pub fn register_texture(&mut self, texture_view: wgpu::TextureView) -> TextureId {
-        self.textures.insert(texture_view)
+    /// Returns true if the texture is registered, and hasn't been unregistered or evicted from the atlas.
+    pub fn contains_texture(&self, texture_id: TextureId) -> bool {
+        self.textures.contains_key(texture_id)
     }
 
     pub fn unregister_texture(&mut self, texture_id: TextureId) {
-        self.textures.remove(texture_id);
+        if let Some(RegisteredTexture::Atlas { allocation, .. }) = self.textures.remove(texture_id)
+        {
+            self.atlas.deallocate(&allocation);
+        }
+    }
+
+    fn update_screen_constants(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        shapes: &[Shape],
+        binner: &ShapeBinner,
+    ) {
+        self.screen_constants = ScreenConstants {
+            width_tiles: binner.resolution.0.div_ceil(binner.tile_size),
+            shape_count: shapes.len() as u32,
+        };
+        if let Some((buffer, _)) = &self.screen_uniform {
+            self.uploader.write(
+                device,
+                queue,
+                buffer,
+                0,
+                bytemuck::bytes_of(&self.screen_constants),
+                &mut self.buffer_stats,
+            );
+        }
     }
 
     pub fn prepare(
@@ -427,20 +949,194 @@ impl WgpuRenderer {
         shapes: &[Shape],
         binner: &ShapeBinner,
     ) {
-        self.screen_width_tiles = binner.resolution.0.div_ceil(binner.tile_size);
-        self.prepare_shape_buffers(device, queue, shapes, binner);
+        self.update_screen_constants(device, queue, shapes, binner);
+        self.frame_index += 1;
+        // Textures have to be mapped first, as the shape buffer contains the mapped texture slots
         self.prepare_textures_bind_group(device, shapes);
+        self.prepare_shape_buffers(device, queue, shapes, binner);
+    }
+
+    /// Like [`WgpuRenderer::prepare`], but only uploads the parts of the buffers that changed according to `damage`.
+    ///
+    /// Falls back to a full upload when the scene was rebuilt, the buffers need to grow, or the texture slots changed.
+    pub fn prepare_damaged(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        shapes: &[Shape],
+        binner: &ShapeBinner,
+        damage: &SceneDamage,
+    ) {
+        self.update_screen_constants(device, queue, shapes, binner);
+        self.frame_index += 1;
+        let textures_changed = self.prepare_textures_bind_group(device, shapes);
+
+        let reallocated = self.reserve_shape_buffers(device, shapes.len(), binner);
+        if damage.full || textures_changed || reallocated {
+            self.prepare_shape_buffers(device, queue, shapes, binner);
+            return;
+        }
+
+        let gpu_shapes: Vec<GpuShape> = shapes[damage.shapes.clone()]
+            .iter()
+            .map(|s| self.gpu_shape(s))
+            .collect();
+        let stats = &mut self.buffer_stats;
+        self.uploader.write(
+            device,
+            queue,
+            self.shape_buffer.buffer(),
+            (damage.shapes.start * size_of::<GpuShape>()) as u64,
+            bytemuck::cast_slice(&gpu_shapes),
+            stats,
+        );
+        self.uploader.write(
+            device,
+            queue,
+            self.shape_ranges_buffer.buffer(),
+            (damage.tile_ranges.start * size_of::<u32>()) as u64,
+            bytemuck::cast_slice(&binner.tile_ranges[damage.tile_ranges.clone()]),
+            stats,
+        );
+        self.uploader.write(
+            device,
+            queue,
+            self.shape_indices_buffer.buffer(),
+            (damage.shape_indices.start * size_of::<u32>()) as u64,
+            bytemuck::cast_slice(&binner.shape_indices[damage.shape_indices.clone()]),
+            stats,
+        );
+        self.uploader.flush(queue);
+    }
+
+    /// Sets the policy used to grow and shrink the shape buffers. Takes effect on the next call to `prepare`.
+    pub fn set_buffer_growth_policy(&mut self, policy: BufferGrowthPolicy) {
+        self.buffer_growth_policy = policy;
+    }
+
+    /// Sets how shape data is uploaded to the GPU
+    pub fn set_upload_mode(&mut self, mode: UploadMode) {
+        self.uploader.mode = mode;
+    }
+
+    pub fn buffer_stats(&self) -> BufferStats {
+        self.buffer_stats
     }
 
     pub fn render(&self, pass: &mut wgpu::RenderPass<'_>) {
+        self.bind(pass);
+        pass.draw(0..3, 0..1); // Draw a full-screen triangle
+    }
+
+    /// Renders only the regions of the screen marked dirty in `damage`, by scissoring the full-screen triangle to each dirty tile rect.
+    ///
+    /// The pass must target a texture that retains the previous frame's contents (eg. a [`PersistentTarget`] loaded with
+    /// [`wgpu::LoadOp::Load`]), as everything outside the dirty regions is left untouched.
+    pub fn render_damaged(
+        &self,
+        pass: &mut wgpu::RenderPass<'_>,
+        binner: &ShapeBinner,
+        damage: &SceneDamage,
+    ) {
+        if damage.is_empty() {
+            return;
+        }
+
+        self.bind(pass);
+        for rect in &damage.dirty_rects {
+            let (x, y, width, height) = rect.to_pixels(binner.tile_size, binner.resolution);
+            if width == 0 || height == 0 {
+                continue;
+            }
+            pass.set_scissor_rect(x, y, width, height);
+            pass.draw(0..3, 0..1);
+        }
+
+        // Restore the scissor rect for anything drawn after us
+        pass.set_scissor_rect(0, 0, binner.resolution.0, binner.resolution.1);
+    }
+
+    fn bind(&self, pass: &mut wgpu::RenderPass<'_>) {
         pass.set_pipeline(&self.pipeline);
         pass.set_bind_group(0, &self.shape_buffer_bind_group, &[]);
         pass.set_bind_group(1, &self.texture_bind_group, &[]);
-        pass.set_push_constants(
-            ShaderStages::FRAGMENT,
-            0,
-            &u32::to_ne_bytes(self.screen_width_tiles),
+        match &self.screen_uniform {
+            Some((_, bind_group)) => pass.set_bind_group(2, bind_group, &[]),
+            None => pass.set_push_constants(
+                ShaderStages::FRAGMENT,
+                0,
+                bytemuck::bytes_of(&self.screen_constants),
+            ),
+        }
+    }
+}
+
+/// Offscreen render target that keeps its contents between frames, for use with [`WgpuRenderer::render_damaged`].
+///
+/// Swapchain images don't retain their contents after being presented, so partial rendering goes into this target
+/// instead, which is then copied to the swapchain image.
+pub struct PersistentTarget {
+    texture: wgpu::Texture,
+    view: wgpu::TextureView,
+}
+
+impl PersistentTarget {
+    pub fn new(device: &wgpu::Device, resolution: (u32, u32), format: wgpu::TextureFormat) -> Self {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Persistent Render Target"),
+            size: wgpu::Extent3d {
+                width: resolution.0.max(1),
+                height: resolution.1.max(1),
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                | wgpu::TextureUsages::COPY_SRC
+                | wgpu::TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        Self { texture, view }
+    }
+
+    /// Recreates the target if the resolution or format changed.
+    ///
+    /// Returns true if the target was recreated, in which case its contents are lost and the next frame must be fully
+    /// redrawn (see [`crate::RetainedScene::invalidate`]).
+    pub fn ensure(
+        &mut self,
+        device: &wgpu::Device,
+        resolution: (u32, u32),
+        format: wgpu::TextureFormat,
+    ) -> bool {
+        let size = self.texture.size();
+        if (size.width, size.height) == (resolution.0.max(1), resolution.1.max(1))
+            && self.texture.format() == format
+        {
+            return false;
+        }
+
+        *self = Self::new(device, resolution, format);
+        true
+    }
+
+    pub fn texture(&self) -> &wgpu::Texture {
+        &self.texture
+    }
+
+    pub fn view(&self) -> &wgpu::TextureView {
+        &self.view
+    }
+
+    /// Copies the target to `destination`, which must have the same size and format, and `COPY_DST` usage.
+    pub fn copy_to(&self, encoder: &mut wgpu::CommandEncoder, destination: &wgpu::Texture) {
+        encoder.copy_texture_to_texture(
+            self.texture.as_image_copy(),
+            destination.as_image_copy(),
+            self.texture.size(),
         );
-        pass.draw(0..3, 0..1); // Draw a full-screen triangle
     }
 }
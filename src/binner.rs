@@ -1,11 +1,42 @@
-use glam::uvec2;
+use glam::{UVec2, uvec2};
 
 use crate::{Shape, shape::BoundingBox};
 
+/// Determines how the binner decides which tiles a shape group is added to
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BinningMode {
+    /// Add the group to every tile overlapping its culling bounds
+    BoundingBox,
+    /// Only add the group to tiles that are actually touched by one of its primitives
+    #[default]
+    Precise,
+}
+
+/// A rectangular region of tiles
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TileRect {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl TileRect {
+    /// Converts the tile region to a pixel rectangle `(x, y, width, height)`, clipped to the given resolution.
+    pub fn to_pixels(&self, tile_size: u32, resolution: (u32, u32)) -> (u32, u32, u32, u32) {
+        let x = (self.x * tile_size).min(resolution.0);
+        let y = (self.y * tile_size).min(resolution.1);
+        let max_x = ((self.x + self.width) * tile_size).min(resolution.0);
+        let max_y = ((self.y + self.height) * tile_size).min(resolution.1);
+        (x, y, max_x - x, max_y - y)
+    }
+}
+
 /// Structure used for shape tile-binning on the CPU side
 pub struct ShapeBinner {
     pub tile_size: u32,
     pub resolution: (u32, u32),
+    pub mode: BinningMode,
 
     pub shapes_by_tile: Vec<Vec<u32>>,
 
@@ -15,77 +46,87 @@ pub struct ShapeBinner {
 
 impl ShapeBinner {
     pub fn new(tile_size: u32, resolution: (u32, u32)) -> Self {
-        Self {
+        let mut binner = Self {
             tile_size,
             resolution,
+            mode: BinningMode::default(),
             shapes_by_tile: Vec::new(),
             tile_ranges: Vec::new(),
             shape_indices: Vec::new(),
-        }
+        };
+        binner.update_resolution(resolution);
+        binner
     }
 
     pub(crate) fn update_resolution(&mut self, resolution: (u32, u32)) {
         self.resolution = resolution;
-        let tiles_x = resolution.0.div_ceil(self.tile_size);
-        let tiles_y = resolution.1.div_ceil(self.tile_size);
+        let (tiles_x, tiles_y) = self.tile_count();
         // Resize the outer vector, adding/removing tile vectors where neded
         self.shapes_by_tile
             .resize((tiles_x * tiles_y) as usize, Vec::new());
     }
 
+    /// Number of tiles horizontally and vertically
+    pub fn tile_count(&self) -> (u32, u32) {
+        (
+            self.resolution.0.div_ceil(self.tile_size),
+            self.resolution.1.div_ceil(self.tile_size),
+        )
+    }
+
     pub fn bin_shapes(&mut self, shapes: &[Shape]) {
         self.shapes_by_tile.iter_mut().for_each(|v| v.clear());
 
-        let mut current_group_id = 0;
-        let mut group_start_index = 0;
-        for (i, _shape) in shapes.iter().enumerate() {
-            let next_group_id = shapes.get(i + 1).map(|s| s.group_id);
-            if next_group_id != Some(current_group_id) {
-                let shape_range = group_start_index as u32..(i as u32 + 1);
-                let bounds = shapes[group_start_index..=i]
-                    .iter()
-                    .fold(None, |acc: Option<BoundingBox>, s| {
-                        let shape_bounds = s.culling_bounds();
-                        Some(if let Some(acc_bounds) = acc {
-                            BoundingBox {
-                                min: acc_bounds.min.min(shape_bounds.min),
-                                max: acc_bounds.max.max(shape_bounds.max),
-                            }
-                        } else {
-                            shape_bounds
-                        })
-                    })
-                    .unwrap();
-                self.bin_shape_group(&bounds, shape_range);
-
-                group_start_index = i + 1;
-                current_group_id = next_group_id.unwrap_or(0);
-            }
+        let grid = self.grid();
+        for group in shape_groups(shapes) {
+            let shape_range = group.start as u32..group.end as u32;
+            grid.for_each_covered_tile(&shapes[group], |tile_index| {
+                self.shapes_by_tile[tile_index].extend(shape_range.clone());
+            });
         }
 
         self.calculate_shape_ranges();
     }
 
-    fn bin_shape_group(&mut self, bounds: &BoundingBox, shape_indices: std::ops::Range<u32>) {
-        let start_tile = uvec2(
-            (bounds.min.x / self.tile_size as f32).floor() as u32,
-            (bounds.min.y / self.tile_size as f32).floor() as u32,
-        );
-        let end_tile = uvec2(
-            (bounds.max.x / self.tile_size as f32).floor() as u32,
-            (bounds.max.y / self.tile_size as f32).floor() as u32,
-        );
-        let num_tiles_x = end_tile.x - start_tile.x + 1;
-        let num_tiles_y = end_tile.y - start_tile.y + 1;
-        let screen_tiles_x = self.resolution.0.div_ceil(self.tile_size);
-
-        for tile_y in start_tile.y..start_tile.y + num_tiles_y {
-            for tile_x in start_tile.x..start_tile.x + num_tiles_x {
-                let tile_index = (tile_y * screen_tiles_x + tile_x) as usize;
-                if let Some(tile_shapes) = self.shapes_by_tile.get_mut(tile_index) {
-                    tile_shapes.extend(shape_indices.clone());
+    /// Re-bins only the tiles marked in `dirty_tiles`, leaving the shape lists of all other tiles untouched.
+    ///
+    /// This is only valid if the shapes binned into the clean tiles haven't changed since the last call to
+    /// [`ShapeBinner::bin_shapes`] or [`ShapeBinner::rebin_tiles`].
+    pub fn rebin_tiles(&mut self, shapes: &[Shape], dirty_tiles: &[bool]) {
+        for (tile_shapes, _) in self
+            .shapes_by_tile
+            .iter_mut()
+            .zip(dirty_tiles)
+            .filter(|(_, dirty)| **dirty)
+        {
+            tile_shapes.clear();
+        }
+
+        let grid = self.grid();
+        for group in shape_groups(shapes) {
+            let shape_range = group.start as u32..group.end as u32;
+            grid.for_each_covered_tile(&shapes[group], |tile_index| {
+                if dirty_tiles.get(tile_index).copied().unwrap_or(false) {
+                    self.shapes_by_tile[tile_index].extend(shape_range.clone());
                 }
-            }
+            });
+        }
+
+        self.calculate_shape_ranges();
+    }
+
+    /// Calls `f` with the index of every tile a shape group would be binned into.
+    pub fn for_each_covered_tile(&self, group: &[Shape], f: impl FnMut(usize)) {
+        self.grid().for_each_covered_tile(group, f);
+    }
+
+    fn grid(&self) -> TileGrid {
+        let (tiles_x, tiles_y) = self.tile_count();
+        TileGrid {
+            tile_size: self.tile_size,
+            tiles_x,
+            tiles_y,
+            mode: self.mode,
         }
     }
 
@@ -94,8 +135,7 @@ impl ShapeBinner {
         self.tile_ranges.clear();
         self.shape_indices.clear();
 
-        let screen_tiles_x = self.resolution.0.div_ceil(self.tile_size);
-        let screen_tiles_y = self.resolution.1.div_ceil(self.tile_size);
+        let (screen_tiles_x, screen_tiles_y) = self.tile_count();
         let total_tiles = screen_tiles_x * screen_tiles_y;
 
         self.tile_ranges.reserve(total_tiles as usize + 1);
@@ -106,3 +146,284 @@ impl ShapeBinner {
         }
     }
 }
+
+/// Returns the index ranges of the shape groups in `shapes`. Shapes in a group are always stored contiguously.
+pub fn shape_groups(shapes: &[Shape]) -> impl Iterator<Item = std::ops::Range<usize>> + '_ {
+    shapes
+        .chunk_by(|a, b| a.group_id == b.group_id)
+        .scan(0, |start, group| {
+            let range = *start..*start + group.len();
+            *start = range.end;
+            Some(range)
+        })
+}
+
+/// Screen tile layout used while binning
+#[derive(Clone, Copy)]
+struct TileGrid {
+    tile_size: u32,
+    tiles_x: u32,
+    tiles_y: u32,
+    mode: BinningMode,
+}
+
+impl TileGrid {
+    fn for_each_covered_tile(&self, group: &[Shape], mut f: impl FnMut(usize)) {
+        let bounds = group
+            .iter()
+            .fold(BoundingBox::EMPTY, |acc, s| acc.union(&s.culling_bounds()));
+        if self.tiles_x == 0 || self.tiles_y == 0 || bounds.max.x < 0.0 || bounds.max.y < 0.0 {
+            return;
+        }
+
+        let start_tile = uvec2(
+            (bounds.min.x / self.tile_size as f32).floor() as u32,
+            (bounds.min.y / self.tile_size as f32).floor() as u32,
+        );
+        // Clamp to the screen, so tiles past the right edge don't wrap around into the next row
+        let end_tile = uvec2(
+            ((bounds.max.x / self.tile_size as f32).floor() as u32).min(self.tiles_x - 1),
+            ((bounds.max.y / self.tile_size as f32).floor() as u32).min(self.tiles_y - 1),
+        );
+        if start_tile.x > end_tile.x || start_tile.y > end_tile.y {
+            return;
+        }
+
+        match self.mode {
+            BinningMode::BoundingBox => {
+                for tile_y in start_tile.y..=end_tile.y {
+                    for tile_x in start_tile.x..=end_tile.x {
+                        f((tile_y * self.tiles_x + tile_x) as usize);
+                    }
+                }
+            }
+            BinningMode::Precise => {
+                self.visit_tile_region(start_tile, end_tile, group, &mut f);
+            }
+        }
+    }
+
+    /// Recursively subdivides the (inclusive) tile region, skipping any sub-region that isn't touched by the group.
+    ///
+    /// Long, thin shapes only touch a small fraction of the tiles in their bounding box, so rejecting large empty regions
+    /// early keeps the number of intersection tests close to the number of tiles actually covered.
+    fn visit_tile_region(
+        &self,
+        start_tile: UVec2,
+        end_tile: UVec2,
+        group: &[Shape],
+        f: &mut impl FnMut(usize),
+    ) {
+        let region_bounds = BoundingBox {
+            min: start_tile.as_vec2() * self.tile_size as f32,
+            max: (end_tile + 1).as_vec2() * self.tile_size as f32,
+        };
+        if !group.iter().any(|s| s.intersects(&region_bounds)) {
+            return;
+        }
+
+        let size = end_tile - start_tile + 1;
+        if size.x <= 1 && size.y <= 1 {
+            f((start_tile.y * self.tiles_x + start_tile.x) as usize);
+        } else if size.x >= size.y {
+            let split = start_tile.x + size.x / 2;
+            self.visit_tile_region(start_tile, uvec2(split - 1, end_tile.y), group, f);
+            self.visit_tile_region(uvec2(split, start_tile.y), end_tile, group, f);
+        } else {
+            let split = start_tile.y + size.y / 2;
+            self.visit_tile_region(start_tile, uvec2(end_tile.x, split - 1), group, f);
+            self.visit_tile_region(uvec2(start_tile.x, split), end_tile, group, f);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::{Vec2, Vec4, vec2};
+
+    use super::*;
+    use crate::{hit_test::group_distance, painter::Painter, shape::Primitive};
+
+    const RESOLUTION: (u32, u32) = (256, 192);
+
+    fn bounds(min: (f32, f32), max: (f32, f32)) -> BoundingBox {
+        BoundingBox {
+            min: min.into(),
+            max: max.into(),
+        }
+    }
+
+    /// Bins the painted shapes and checks that every group is listed in each tile containing a pixel it covers
+    fn assert_covered_tiles_listed(mode: BinningMode, paint: impl FnOnce(&mut Painter)) {
+        let mut painter = Painter::new();
+        painter.set_binning_mode(mode);
+        painter.start(RESOLUTION);
+        paint(&mut painter);
+        painter.finish(|shapes, binner| {
+            let (tiles_x, _) = binner.tile_count();
+            for group in shape_groups(shapes) {
+                for y in 0..RESOLUTION.1 {
+                    for x in 0..RESOLUTION.0 {
+                        let point = vec2(x as f32 + 0.5, y as f32 + 0.5);
+                        if group_distance(&shapes[group.clone()], point).0 > 0.0 {
+                            continue;
+                        }
+                        let tile = (y / binner.tile_size * tiles_x + x / binner.tile_size) as usize;
+                        let indices = &binner.shape_indices
+                            [binner.tile_ranges[tile] as usize..binner.tile_ranges[tile + 1] as usize];
+                        assert!(
+                            indices.contains(&(group.start as u32)),
+                            "{mode:?}: group {group:?} covers {point} but isn't binned into tile {tile}"
+                        );
+                    }
+                }
+            }
+        });
+    }
+
+    fn paint_test_shapes(painter: &mut Painter) {
+        // Rotated quad
+        painter.add_polyquad(
+            (40.0, 10.0),
+            (90.0, 50.0),
+            (60.0, 90.0),
+            (10.0, 50.0),
+            Vec4::ONE,
+            0.0,
+        );
+        // Thin diagonal line across many tiles
+        painter.add_filled_line((5.0, 180.0), (250.0, 3.0), 0.5, Vec4::ONE);
+        // Annulus sector, and one wrapping around angle zero
+        painter.add_filled_circle_sector((160.0, 60.0), 30.0, 50.0, 0.5, 2.5, Vec4::ONE);
+        painter.add_filled_circle_sector((100.0, 140.0), 20.0, 40.0, 5.5, 0.8, Vec4::ONE);
+        // Outlines only cover a ring around the edge
+        painter.add_circle((200.0, 150.0), 30.0, Vec4::ONE, 6.0);
+        painter.add_rect((120.0, 20.0), (140.0, 100.0), 4.0, Vec4::ONE, 3.0);
+        // Negative distance offsets grow shapes beyond their primitive
+        painter
+            .add_filled_triangle((30.0, 120.0), (60.0, 120.0), (45.0, 150.0), Vec4::ONE)
+            .with_distance_offset(-12.0);
+        // Reaching just one pixel into the neighbouring tile
+        painter.add_filled_circle((40.5, 176.5), 10.0, Vec4::ONE);
+        painter.add_filled_rect((150.0, 95.5), (158.0, 125.0), 0.0, Vec4::ONE);
+        // Partially off-screen
+        painter.add_filled_circle((250.0, 190.0), 20.0, Vec4::ONE);
+        painter.add_filled_circle((-10.0, -10.0), 20.0, Vec4::ONE);
+    }
+
+    #[test]
+    fn bins_shapes_into_every_covered_tile() {
+        assert_covered_tiles_listed(BinningMode::Precise, paint_test_shapes);
+        assert_covered_tiles_listed(BinningMode::BoundingBox, paint_test_shapes);
+    }
+
+    #[test]
+    fn bins_groups_into_every_covered_tile() {
+        let paint = |painter: &mut Painter| {
+            painter.begin_group();
+            painter.add_filled_line((20.0, 20.0), (230.0, 40.0), 1.0, Vec4::ONE);
+            painter.add_filled_circle((128.0, 150.0), 25.0, Vec4::ONE);
+            painter.end_group();
+            // Outlined group of a ring sector and a quad
+            painter.begin_group();
+            painter.add_filled_circle_sector((60.0, 120.0), 10.0, 40.0, 2.0, 1.0, Vec4::ONE);
+            painter.add_polyquad(
+                (150.0, 90.0),
+                (240.0, 100.0),
+                (230.0, 120.0),
+                (160.0, 130.0),
+                Vec4::ONE,
+                4.0,
+            );
+            painter.end_group();
+        };
+        assert_covered_tiles_listed(BinningMode::Precise, paint);
+        assert_covered_tiles_listed(BinningMode::BoundingBox, paint);
+    }
+
+    #[test]
+    fn skips_shapes_off_screen() {
+        let mut painter = Painter::new();
+        painter.start(RESOLUTION);
+        painter.add_filled_circle((400.0, 50.0), 10.0, Vec4::ONE);
+        painter.add_filled_circle((50.0, 400.0), 10.0, Vec4::ONE);
+        painter.add_filled_circle((-50.0, 50.0), 10.0, Vec4::ONE);
+        painter.add_filled_circle((50.0, -50.0), 10.0, Vec4::ONE);
+        painter.finish(|_, binner| assert!(binner.shape_indices.is_empty()));
+    }
+
+    #[test]
+    fn line_skips_corner_tiles() {
+        let line = Primitive::Line {
+            p1: Vec2::ZERO,
+            p2: vec2(128.0, 128.0),
+        };
+        assert!(line.intersects(&bounds((0.0, 0.0), (32.0, 32.0)), 1.0));
+        assert!(line.intersects(&bounds((64.0, 64.0), (96.0, 96.0)), 1.0));
+        assert!(!line.intersects(&bounds((96.0, 0.0), (128.0, 32.0)), 1.0));
+        assert!(!line.intersects(&bounds((0.0, 96.0), (32.0, 128.0)), 1.0));
+        // Tiles diagonally adjacent to the line only touch it at their corner
+        assert!(line.intersects(&bounds((32.0, 0.0), (64.0, 32.0)), 1.0));
+        assert!(!line.intersects(&bounds((34.0, 0.0), (66.0, 32.0)), 1.0));
+        assert!(line.intersects(&bounds((34.0, 0.0), (66.0, 32.0)), 2.0));
+    }
+
+    #[test]
+    fn rotated_quad_skips_corner_tiles() {
+        let quad = Primitive::PolyQuad {
+            points: [
+                vec2(64.0, 0.0),
+                vec2(128.0, 64.0),
+                vec2(64.0, 128.0),
+                vec2(0.0, 64.0),
+            ],
+        };
+        assert!(quad.intersects(&bounds((48.0, 48.0), (80.0, 80.0)), 0.0));
+        assert!(quad.intersects(&bounds((16.0, 16.0), (48.0, 48.0)), 0.0));
+        assert!(!quad.intersects(&bounds((0.0, 0.0), (31.0, 31.0)), 0.0));
+        assert!(!quad.intersects(&bounds((97.0, 97.0), (128.0, 128.0)), 0.0));
+    }
+
+    #[test]
+    fn annulus_skips_its_hole() {
+        let sector = Primitive::CircleSector {
+            center: vec2(64.0, 64.0),
+            radius_inner: 50.0,
+            radius_outer: 60.0,
+            angle_start: 0.0,
+            angle_end: std::f32::consts::TAU,
+        };
+        assert!(!sector.intersects(&bounds((48.0, 48.0), (80.0, 80.0)), 1.0));
+        assert!(sector.intersects(&bounds((110.0, 60.0), (120.0, 70.0)), 1.0));
+        assert!(!sector.intersects(&bounds((126.0, 60.0), (136.0, 70.0)), 1.0));
+        assert!(sector.intersects(&bounds((126.0, 60.0), (136.0, 70.0)), 3.0));
+    }
+
+    #[test]
+    fn shape_margins_include_outlines_and_offsets() {
+        let mut painter = Painter::new();
+        painter.start(RESOLUTION);
+        let circle = painter
+            .add_filled_circle((64.0, 64.0), 10.0, Vec4::ONE)
+            .clone();
+        let outlined = painter
+            .add_circle((64.0, 64.0), 10.0, Vec4::ONE, 8.0)
+            .clone();
+        let grown = painter
+            .add_filled_circle((64.0, 64.0), 10.0, Vec4::ONE)
+            .with_distance_offset(-6.0)
+            .clone();
+        let shrunk = painter
+            .add_filled_circle((64.0, 64.0), 10.0, Vec4::ONE)
+            .with_distance_offset(6.0)
+            .clone();
+
+        // 3px right of the circle edge, within antialiasing range only once grown
+        let tile = bounds((77.0, 60.0), (90.0, 70.0));
+        assert!(!circle.intersects(&tile));
+        assert!(outlined.intersects(&tile));
+        assert!(grown.intersects(&tile));
+        assert!(!shrunk.intersects(&tile));
+        assert!(shrunk.intersects(&bounds((68.0, 60.0), (90.0, 70.0))));
+    }
+}
@@ -0,0 +1,282 @@
+#[cfg(feature = "serde")]
+use crate::error::{Error, SceneError};
+use crate::{Painter, Shape, binner::shape_groups};
+
+/// The shapes painted in a frame, eg. for bug reports and regression tests.
+///
+/// Capture a scene from the shapes passed to [`Painter::finish`], and replay it into a painter later. With the `serde`
+/// feature, scenes can be written to a compact binary format and a readable RON text format.
+///
+/// Texture IDs are stored as they are, so textured shapes only replay correctly into a renderer that registered the
+/// same textures in the same order.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Scene {
+    pub resolution: (u32, u32),
+    /// Shapes in draw order, with their group styles applied
+    pub shapes: Vec<Shape>,
+}
+
+impl Scene {
+    /// Identifies the binary format, followed by its version
+    #[cfg(feature = "serde")]
+    const MAGIC: &[u8; 4] = b"MDRS";
+    #[cfg(feature = "serde")]
+    const VERSION: u8 = 1;
+
+    /// Captures a finished frame, eg. from the shapes and binner passed to [`Painter::finish`]
+    pub fn capture(shapes: &[Shape], resolution: (u32, u32)) -> Self {
+        Self {
+            resolution,
+            shapes: shapes.to_vec(),
+        }
+    }
+
+    /// Adds the scene's shapes to the painter's current frame, keeping their groups
+    pub fn replay(&self, painter: &mut Painter) {
+        for group in shape_groups(&self.shapes) {
+            if group.len() == 1 {
+                painter.add_shape(self.shapes[group.start].clone());
+                continue;
+            }
+            painter.begin_group();
+            for shape in &self.shapes[group] {
+                painter.add_shape(shape.clone());
+            }
+            painter.end_group();
+        }
+    }
+
+    /// Writes the scene as an SVG document, see [`crate::svg::export`]
+    #[cfg(feature = "svg")]
+    pub fn to_svg(&self) -> String {
+        crate::svg::export(&self.shapes, self.resolution)
+    }
+
+    /// Encodes the scene in the binary format
+    #[cfg(feature = "serde")]
+    pub fn to_bytes(&self) -> Result<Vec<u8>, Error> {
+        let mut bytes = Self::MAGIC.to_vec();
+        bytes.push(Self::VERSION);
+        bincode::serde::encode_into_std_write(self, &mut bytes, bincode::config::standard())
+            .map_err(|e| Error::SceneSerialization(SceneError::Encode(e.to_string())))?;
+        Ok(bytes)
+    }
+
+    /// Decodes a scene written by [`Scene::to_bytes`]
+    #[cfg(feature = "serde")]
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        let data = bytes
+            .strip_prefix(Self::MAGIC)
+            .ok_or(Error::SceneSerialization(SceneError::NotAScene))?;
+        match data.split_first() {
+            Some((&Self::VERSION, data)) => {
+                bincode::serde::decode_from_slice(data, bincode::config::standard())
+                    .map(|(scene, _)| scene)
+                    .map_err(|e| Error::SceneSerialization(SceneError::Decode(e.to_string())))
+            }
+            Some((&version, _)) => Err(Error::SceneSerialization(SceneError::UnsupportedVersion(
+                version,
+            ))),
+            None => Err(Error::SceneSerialization(SceneError::MissingVersion)),
+        }
+    }
+
+    /// Encodes the scene as human-readable RON
+    #[cfg(feature = "serde")]
+    pub fn to_ron(&self) -> Result<String, Error> {
+        ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+            .map_err(|e| Error::SceneSerialization(SceneError::Encode(e.to_string())))
+    }
+
+    /// Decodes a scene written by [`Scene::to_ron`]
+    #[cfg(feature = "serde")]
+    pub fn from_ron(text: &str) -> Result<Self, Error> {
+        ron::from_str(text)
+            .map_err(|e| Error::SceneSerialization(SceneError::Decode(e.to_string())))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::{Vec2, Vec3, Vec4};
+
+    use super::*;
+    use crate::shape::{CornerRadius, Primitive, TextureId};
+
+    /// A frame using every primitive, a group, and most shape properties
+    fn scene() -> Scene {
+        let mut painter = Painter::new();
+        painter.start((320, 240));
+        painter
+            .add_primitive(
+                Primitive::Circle {
+                    center: Vec2::new(10.0, 20.0),
+                    radius: 5.5,
+                },
+                Vec4::new(1.0, 0.5, 0.25, 1.0),
+            )
+            .with_glow(Vec3::new(0.0, 1.0, 0.0), 4.0)
+            .with_tag(7);
+        painter.begin_group();
+        painter.add_primitive(
+            Primitive::Rect {
+                center: Vec2::new(100.0, 80.0),
+                half_extents: Vec2::new(40.0, 20.0),
+                corner_radius: CornerRadius {
+                    top_left: 1.0,
+                    top_right: 2.0,
+                    bottom_right: 3.0,
+                    bottom_left: 4.0,
+                },
+            },
+            Vec4::ONE,
+        );
+        painter
+            .add_primitive(
+                Primitive::Line {
+                    p1: Vec2::new(0.0, 0.0),
+                    p2: Vec2::new(300.0, 200.0),
+                },
+                Vec4::new(0.0, 0.0, 1.0, 0.5),
+            )
+            .with_line_width(3.0)
+            .with_distance_offset(-1.5);
+        painter.end_group();
+        painter.add_primitive(
+            Primitive::Triangle {
+                p1: Vec2::new(0.0, 0.0),
+                p2: Vec2::new(10.0, 0.0),
+                p3: Vec2::new(0.0, 10.0),
+            },
+            Vec4::ONE,
+        );
+        painter
+            .add_primitive(
+                Primitive::CircleSector {
+                    center: Vec2::new(200.0, 100.0),
+                    radius_inner: 10.0,
+                    radius_outer: 30.0,
+                    angle_start: 0.25,
+                    angle_end: 2.5,
+                },
+                Vec4::ONE,
+            )
+            .with_layer(-2);
+        painter
+            .add_primitive(
+                Primitive::PolyQuad {
+                    points: [
+                        Vec2::new(0.0, 0.0),
+                        Vec2::new(20.0, 5.0),
+                        Vec2::new(25.0, 30.0),
+                        Vec2::new(-5.0, 20.0),
+                    ],
+                },
+                Vec4::ONE,
+            )
+            .with_texture_id(TextureId::default())
+            .with_texture_uv((0.25, 0.5), (0.75, 1.0))
+            .with_texture_is_mtsdf();
+
+        let mut scene = Scene::default();
+        painter.finish(|shapes, _| scene = Scene::capture(shapes, (320, 240)));
+        scene
+    }
+
+    #[test]
+    fn replay_keeps_shapes_and_groups() {
+        let scene = scene();
+        let mut painter = Painter::new();
+        painter.start(scene.resolution);
+        scene.replay(&mut painter);
+        painter.finish(|shapes, _| {
+            // Group IDs are renumbered, but the same shapes are grouped together
+            assert_eq!(
+                shape_groups(shapes).collect::<Vec<_>>(),
+                shape_groups(&scene.shapes).collect::<Vec<_>>()
+            );
+            let without_group_ids = |shapes: &[Shape]| {
+                let mut shapes = shapes.to_vec();
+                shapes.iter_mut().for_each(|shape| shape.group_id = 0);
+                shapes
+            };
+            assert_eq!(without_group_ids(shapes), without_group_ids(&scene.shapes));
+        });
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn bytes_round_trip() {
+        let scene = scene();
+        let bytes = scene.to_bytes().unwrap();
+        assert!(bytes.starts_with(b"MDRS\x01"));
+        assert_eq!(Scene::from_bytes(&bytes).unwrap(), scene);
+        assert_eq!(
+            Scene::from_bytes(&Scene::default().to_bytes().unwrap()).unwrap(),
+            Scene::default()
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn ron_round_trip() {
+        let scene = scene();
+        let ron = scene.to_ron().unwrap();
+        assert!(ron.contains("CircleSector"));
+        assert_eq!(Scene::from_ron(&ron).unwrap(), scene);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn rejects_invalid_bytes() {
+        let is_rejected =
+            |bytes: &[u8]| matches!(Scene::from_bytes(bytes), Err(Error::SceneSerialization(_)));
+        let bytes = scene().to_bytes().unwrap();
+
+        // Bad magic
+        assert!(is_rejected(b""));
+        assert!(is_rejected(b"MDR"));
+        assert!(is_rejected(&[b"MDRT", &bytes[4..]].concat()));
+
+        assert_eq!(
+            Scene::from_bytes(b"MDR").err(),
+            Some(Error::SceneSerialization(SceneError::NotAScene))
+        );
+
+        // Missing or unknown version
+        assert!(is_rejected(b"MDRS"));
+        assert!(is_rejected(&[b"MDRS\x02", &bytes[5..]].concat()));
+        assert!(is_rejected(&[b"MDRS\x00", &bytes[5..]].concat()));
+        assert_eq!(
+            Scene::from_bytes(b"MDRS").err(),
+            Some(Error::SceneSerialization(SceneError::MissingVersion))
+        );
+        assert_eq!(
+            Scene::from_bytes(&[b"MDRS\x02", &bytes[5..]].concat()).err(),
+            Some(Error::SceneSerialization(SceneError::UnsupportedVersion(2)))
+        );
+
+        // Truncated data
+        for len in [5, 6, bytes.len() / 2, bytes.len() - 1] {
+            assert!(is_rejected(&bytes[..len]), "truncated to {len} bytes");
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn rejects_invalid_ron() {
+        let ron = scene().to_ron().unwrap();
+        for text in [
+            "",
+            "(",
+            &ron[..ron.len() / 2],
+            &ron.replace("Circle", "Ellipse"),
+        ] {
+            assert!(matches!(
+                Scene::from_ron(text),
+                Err(Error::SceneSerialization(SceneError::Decode(_)))
+            ));
+        }
+    }
+}
@@ -0,0 +1,904 @@
+//! Painting egui's output with mondrian, with the `egui` feature.
+//!
+//! [`EguiPainter`] translates the shapes of an egui frame into shapes on a [`Painter`], so rects, circles, lines and
+//! paths are drawn with crisp SDF edges. Text and images become textured rects, sampling egui's textures which the
+//! painter mirrors into the [`WgpuRenderer`]. Shapes without a matching primitive, like Béziers, ellipses and meshes,
+//! are tessellated by epaint and drawn as triangles.
+//!
+//! Some of egui's output is approximated:
+//! - mondrian can't clip its primitives, so unrounded rects, text and images are cut to their clip rect, and other
+//!   shapes crossing the edge of their clip rect are tessellated and drawn as triangles cut to it. Only shadows are
+//!   drawn whole, as their blur can't be tessellated into evenly colored triangles.
+//! - Open paths are stroked with round caps and joins, where egui uses butt caps and miter joins.
+//! - Shadows are always black, with the opacity of the shadow color.
+//! - Mesh triangles are drawn in the average of their vertex colors, without interpolating them. Textures are mapped
+//!   onto the bounds of each triangle, so triangles with rotated or skewed textures, like rotated text and images, are
+//!   skipped and reported as [`Error::UnsupportedMesh`] warnings.
+//! - Paint callbacks are skipped.
+
+use std::collections::HashMap;
+
+use epaint::{
+    ClippedShape, Color32, ColorMode, ImageData, Mesh, Pos2, Rect, RectShape, Shape as EguiShape,
+    Stroke, StrokeKind, TessellationOptions, Tessellator, TextureId as EguiTextureId, Vertex,
+    textures::{TextureFilter as EguiTextureFilter, TextureWrapMode, TexturesDelta},
+};
+use glam::{Vec2, Vec4, vec2};
+
+use crate::{
+    CornerRadius, Painter, Primitive, TextureId,
+    backend::{
+        sampler::{SamplerOptions, TextureAddressMode, TextureFilter},
+        texture::{TextureDataFormat, TextureOptions},
+        wgpu::WgpuRenderer,
+    },
+    error::Error,
+};
+
+/// Paints the shapes of egui frames, eg. from `egui::FullOutput::shapes`.
+///
+/// Each frame, apply egui's texture changes with [`EguiPainter::update_textures`], add the shapes to the painter with
+/// [`EguiPainter::paint`], and free the textures egui no longer uses with [`EguiPainter::free_textures`] after
+/// rendering.
+#[derive(Debug, Default)]
+pub struct EguiPainter {
+    textures: HashMap<EguiTextureId, EguiTexture>,
+    /// Whether skipped mesh triangles were already reported, until a frame has none
+    warned_skipped_triangles: bool,
+}
+
+#[derive(Debug)]
+struct EguiTexture {
+    id: TextureId,
+    size: [usize; 2],
+    /// Whether the texture was created from egui's texture changes, rather than registered by the app
+    managed: bool,
+}
+
+impl EguiPainter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Uses a texture of the renderer for an egui user texture, eg. one allocated with
+    /// `egui::TextureId::User` for an image widget.
+    pub fn register_user_texture(&mut self, egui_id: EguiTextureId, texture_id: TextureId) {
+        self.textures.insert(
+            egui_id,
+            EguiTexture {
+                id: texture_id,
+                size: [0, 0],
+                managed: false,
+            },
+        );
+    }
+
+    /// Creates and updates egui's textures, eg. from `egui::FullOutput::textures_delta`. Call this before painting the
+    /// frame's shapes.
+    pub fn update_textures(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        renderer: &mut WgpuRenderer,
+        delta: &TexturesDelta,
+    ) -> Result<(), Error> {
+        for (egui_id, image_delta) in &delta.set {
+            let ImageData::Color(image) = &image_delta.image;
+            let data: Vec<u8> = image
+                .pixels
+                .iter()
+                .flat_map(Color32::to_srgba_unmultiplied)
+                .collect();
+            let size = (image.size[0] as u32, image.size[1] as u32);
+
+            if let Some([x, y]) = image_delta.pos {
+                // egui only updates part of textures it created before
+                if let Some(texture) = self.textures.get(egui_id) {
+                    renderer.update_texture(
+                        device,
+                        queue,
+                        texture.id,
+                        (x as u32, y as u32),
+                        size,
+                        &data,
+                    )?;
+                }
+                continue;
+            }
+
+            if let Some(texture) = self.textures.remove(egui_id)
+                && texture.managed
+            {
+                renderer.unregister_texture(texture.id);
+            }
+            let options = &image_delta.options;
+            let id = renderer.create_texture(
+                device,
+                queue,
+                size,
+                &data,
+                TextureOptions {
+                    format: TextureDataFormat::Rgba8,
+                    sampler: SamplerOptions {
+                        filter: match options.magnification {
+                            EguiTextureFilter::Nearest => TextureFilter::Nearest,
+                            EguiTextureFilter::Linear => TextureFilter::Linear,
+                        },
+                        address_mode: match options.wrap_mode {
+                            TextureWrapMode::ClampToEdge => TextureAddressMode::ClampToEdge,
+                            TextureWrapMode::Repeat => TextureAddressMode::Repeat,
+                            TextureWrapMode::MirroredRepeat => TextureAddressMode::MirrorRepeat,
+                        },
+                    },
+                    generate_mipmaps: options.mipmap_mode.is_some(),
+                },
+            )?;
+            self.textures.insert(
+                *egui_id,
+                EguiTexture {
+                    id,
+                    size: image.size,
+                    managed: true,
+                },
+            );
+        }
+        Ok(())
+    }
+
+    /// Frees the textures egui no longer uses. Call this after rendering the frame.
+    pub fn free_textures(&mut self, renderer: &mut WgpuRenderer, delta: &TexturesDelta) {
+        for egui_id in &delta.free {
+            if let Some(texture) = self.textures.remove(egui_id)
+                && texture.managed
+            {
+                renderer.unregister_texture(texture.id);
+            }
+        }
+    }
+
+    /// Adds egui's shapes to the painter. Shapes are positioned in points, and scaled by `pixels_per_point`.
+    ///
+    /// Mesh triangles that can't be drawn are reported once through the painter's warning handler, see
+    /// [`Painter::set_warning_handler`].
+    pub fn paint(&mut self, painter: &mut Painter, shapes: &[ClippedShape], pixels_per_point: f32) {
+        let font_texture_size = self
+            .textures
+            .get(&EguiTextureId::default())
+            .map_or([1, 1], |texture| texture.size);
+        let options = TessellationOptions {
+            feathering: false,
+            ..Default::default()
+        };
+        let mut frame = Frame {
+            textures: &self.textures,
+            painter,
+            scale: pixels_per_point,
+            clip: Rect::EVERYTHING,
+            tessellator: Tessellator::new(pixels_per_point, options, font_texture_size, Vec::new()),
+            skipped_triangles: 0,
+        };
+        for ClippedShape { clip_rect, shape } in shapes {
+            frame.clip = *clip_rect;
+            frame.tessellator.set_clip_rect(*clip_rect);
+            frame.shape(shape);
+        }
+
+        let skipped_triangles = frame.skipped_triangles;
+        if skipped_triangles > 0 && !self.warned_skipped_triangles {
+            painter.warn(Error::UnsupportedMesh { skipped_triangles });
+        }
+        self.warned_skipped_triangles = skipped_triangles > 0;
+    }
+}
+
+/// Translates the shapes of a frame
+struct Frame<'a> {
+    textures: &'a HashMap<EguiTextureId, EguiTexture>,
+    painter: &'a mut Painter,
+    scale: f32,
+    /// Clip rect of the current shape, in points
+    clip: Rect,
+    tessellator: Tessellator,
+    /// Textured mesh triangles that couldn't be drawn
+    skipped_triangles: usize,
+}
+
+impl Frame<'_> {
+    fn shape(&mut self, shape: &EguiShape) {
+        let bounds = shape.visual_bounding_rect();
+        if !self.clip.intersects(bounds) {
+            return;
+        }
+        let clipped = !self.clip.contains_rect(bounds);
+        match shape {
+            EguiShape::Noop | EguiShape::Callback(_) => {}
+            EguiShape::Vec(shapes) => {
+                for shape in shapes {
+                    self.shape(shape);
+                }
+            }
+            // Rects without rounded corners, and shadows, take care of the clip rect themselves
+            EguiShape::Rect(rect)
+                if rect.corner_radius == epaint::CornerRadius::ZERO || rect.blur_width > 0.0 =>
+            {
+                self.rect(rect)
+            }
+            // Tessellated triangles are cut to the clip rect, see `Frame::mesh`
+            EguiShape::Circle(_)
+            | EguiShape::Ellipse(_)
+            | EguiShape::LineSegment { .. }
+            | EguiShape::Rect(_)
+            | EguiShape::Path(_)
+                if clipped =>
+            {
+                self.tessellate(shape)
+            }
+            EguiShape::Circle(circle) => {
+                self.circle(circle.center, circle.radius, circle.fill, circle.stroke)
+            }
+            EguiShape::Ellipse(ellipse) if ellipse.radius.x == ellipse.radius.y => self.circle(
+                ellipse.center,
+                ellipse.radius.x,
+                ellipse.fill,
+                ellipse.stroke,
+            ),
+            EguiShape::LineSegment { points, stroke } => self.line_segment(*points, *stroke),
+            EguiShape::Rect(rect) => self.rect(rect),
+            EguiShape::Path(path)
+                if path.stroke.kind != StrokeKind::Inside || path.stroke.is_empty() =>
+            {
+                let ColorMode::Solid(stroke_color) = path.stroke.color else {
+                    return self.tessellate(shape);
+                };
+                let stroke = Stroke::new(path.stroke.width, stroke_color);
+                if path.closed && path.points.len() >= 3 {
+                    self.polygon(&path.points, path.fill, stroke, path.stroke.kind);
+                } else {
+                    self.polyline(&path.points, stroke);
+                }
+            }
+            EguiShape::Text(text) => {
+                let mut mesh = Mesh::default();
+                self.tessellator.tessellate_text(text, &mut mesh);
+                self.mesh(&mesh);
+            }
+            EguiShape::Mesh(mesh) => self.mesh(mesh),
+            _ => self.tessellate(shape),
+        }
+    }
+
+    fn point(&self, point: Pos2) -> Vec2 {
+        vec2(point.x, point.y) * self.scale
+    }
+
+    fn fill(&mut self, primitive: Primitive, color: Color32) {
+        if color.a() > 0 {
+            self.painter.add_primitive(primitive, to_vec4(color));
+        }
+    }
+
+    fn stroke(&mut self, primitive: Primitive, stroke: Stroke, kind: StrokeKind) {
+        if stroke.is_empty() {
+            return;
+        }
+        let width = stroke.width * self.scale;
+        self.painter
+            .add_primitive(primitive, to_vec4(stroke.color))
+            .with_distance_offset(stroke_offset(kind, width))
+            .with_line_width(width);
+    }
+
+    fn circle(&mut self, center: Pos2, radius: f32, fill: Color32, stroke: Stroke) {
+        let circle = Primitive::Circle {
+            center: self.point(center),
+            radius: radius * self.scale,
+        };
+        self.fill(circle, fill);
+        // egui strokes circles on their outside
+        self.stroke(circle, stroke, StrokeKind::Outside);
+    }
+
+    fn line_segment(&mut self, [a, b]: [Pos2; 2], stroke: Stroke) {
+        let (a, b) = (self.point(a), self.point(b));
+        let half_width = (b - a).normalize_or_zero().perp() * stroke.width * self.scale / 2.0;
+        if half_width == Vec2::ZERO || stroke.color.a() == 0 {
+            return;
+        }
+        // A quad rather than a line, for the butt caps egui draws
+        self.fill(
+            Primitive::PolyQuad {
+                points: [
+                    a + half_width,
+                    b + half_width,
+                    b - half_width,
+                    a - half_width,
+                ],
+            },
+            stroke.color,
+        );
+    }
+
+    fn rect(&mut self, shape: &RectShape) {
+        let radius = shape.corner_radius;
+        if shape.blur_width > 0.0 {
+            // egui blurs shadows across their edge, while mondrian fades them out from it
+            let rect = shape.rect.shrink(shape.blur_width / 2.0);
+            let primitive = self.rect_primitive(rect, radius);
+            self.painter
+                .add_primitive(primitive, to_vec4(shape.fill))
+                .with_shadow(
+                    shape.fill.to_srgba_unmultiplied()[3] as f32 / 255.0,
+                    shape.blur_width * self.scale,
+                );
+            return;
+        }
+
+        let rounded = radius != epaint::CornerRadius::ZERO;
+        let texture = shape
+            .brush
+            .as_ref()
+            .and_then(|brush| Some((self.textures.get(&brush.fill_texture_id)?.id, brush.uv)));
+        if let Some((texture_id, uv)) = texture {
+            let rect = if rounded {
+                shape.rect
+            } else {
+                shape.rect.intersect(self.clip)
+            };
+            if shape.fill.a() > 0 && rect.is_positive() {
+                let uv = clip_uv(shape.rect, uv, rect);
+                let primitive = self.rect_primitive(rect, radius);
+                self.painter
+                    .add_primitive(primitive, to_vec4(shape.fill))
+                    .with_texture_id(texture_id)
+                    .with_texture_uv(vec2(uv.min.x, uv.min.y), vec2(uv.max.x, uv.max.y));
+            }
+        } else if rounded {
+            self.fill(self.rect_primitive(shape.rect, radius), shape.fill);
+        } else {
+            self.clipped_rect(shape.rect, shape.fill);
+        }
+
+        if rounded {
+            self.stroke(
+                self.rect_primitive(shape.rect, radius),
+                shape.stroke,
+                shape.stroke_kind,
+            );
+        } else if !shape.stroke.is_empty() {
+            // Unrounded outlines are drawn as four bars, which keeps their corners sharp and lets them be clipped
+            let center = shape
+                .rect
+                .expand(-stroke_offset(shape.stroke_kind, shape.stroke.width));
+            let inner = center.shrink(shape.stroke.width / 2.0);
+            let outer = center.expand(shape.stroke.width / 2.0);
+            if !inner.is_positive() {
+                self.clipped_rect(outer, shape.stroke.color);
+                return;
+            }
+            self.painter.begin_group();
+            for bar in [
+                Rect::from_x_y_ranges(outer.x_range(), outer.top()..=inner.top()),
+                Rect::from_x_y_ranges(outer.x_range(), inner.bottom()..=outer.bottom()),
+                Rect::from_x_y_ranges(outer.left()..=inner.left(), inner.y_range()),
+                Rect::from_x_y_ranges(inner.right()..=outer.right(), inner.y_range()),
+            ] {
+                self.clipped_rect(bar, shape.stroke.color);
+            }
+            self.painter.end_group();
+        }
+    }
+
+    /// Fills an unrounded rect, cut to the clip rect
+    fn clipped_rect(&mut self, rect: Rect, color: Color32) {
+        let rect = rect.intersect(self.clip);
+        if rect.is_positive() {
+            self.fill(self.rect_primitive(rect, epaint::CornerRadius::ZERO), color);
+        }
+    }
+
+    fn rect_primitive(&self, rect: Rect, radius: epaint::CornerRadius) -> Primitive {
+        let scale = self.scale;
+        Primitive::Rect {
+            center: self.point(rect.center()),
+            half_extents: vec2(rect.width(), rect.height()) * scale / 2.0,
+            // The field each corner's radius is read from, see `sd_rounded_rect` in the shader
+            corner_radius: CornerRadius {
+                top_left: radius.se as f32 * scale,
+                top_right: radius.ne as f32 * scale,
+                bottom_right: radius.sw as f32 * scale,
+                bottom_left: radius.nw as f32 * scale,
+            },
+        }
+    }
+
+    fn triangle_primitive(&self, [a, b, c]: [Pos2; 3]) -> Primitive {
+        Primitive::Triangle {
+            p1: self.point(a),
+            p2: self.point(b),
+            p3: self.point(c),
+        }
+    }
+
+    /// Fills and strokes a closed path, which egui requires to be convex when filled
+    fn polygon(&mut self, points: &[Pos2], fill: Color32, stroke: Stroke, kind: StrokeKind) {
+        let triangles: Vec<Primitive> = points[1..]
+            .windows(2)
+            .map(|pair| Primitive::Triangle {
+                p1: self.point(points[0]),
+                p2: self.point(pair[0]),
+                p3: self.point(pair[1]),
+            })
+            .collect();
+        if fill.a() > 0 {
+            self.painter.begin_group();
+            for &triangle in &triangles {
+                self.fill(triangle, fill);
+            }
+            self.painter.end_group();
+        }
+        // Offsetting the triangles one by one only grows their union correctly, so inside strokes are tessellated
+        // instead, see `Frame::shape`
+        if !stroke.is_empty() {
+            self.painter.begin_group();
+            for &triangle in &triangles {
+                self.stroke(triangle, stroke, kind);
+            }
+            self.painter.end_group();
+        }
+    }
+
+    fn polyline(&mut self, points: &[Pos2], stroke: Stroke) {
+        if stroke.is_empty() {
+            return;
+        }
+        let half_width = stroke.width * self.scale / 2.0;
+        self.painter.begin_group();
+        for pair in points.windows(2) {
+            self.painter
+                .add_primitive(
+                    Primitive::Line {
+                        p1: self.point(pair[0]),
+                        p2: self.point(pair[1]),
+                    },
+                    to_vec4(stroke.color),
+                )
+                .with_distance_offset(-half_width);
+        }
+        self.painter.end_group();
+    }
+
+    fn tessellate(&mut self, shape: &EguiShape) {
+        let mut mesh = Mesh::default();
+        self.tessellator.tessellate_shape(shape.clone(), &mut mesh);
+        self.mesh(&mesh);
+    }
+
+    /// Draws pairs of triangles forming axis-aligned rects as rects, and other triangles one by one, grouping runs of
+    /// triangles of the same color
+    fn mesh(&mut self, mesh: &Mesh) {
+        let texture = self
+            .textures
+            .get(&mesh.texture_id)
+            .map(|texture| texture.id);
+        let triangles: Vec<[usize; 3]> = mesh
+            .indices
+            .chunks_exact(3)
+            .map(|t| [t[0] as usize, t[1] as usize, t[2] as usize])
+            .collect();
+        let mut run_color = None;
+        let mut i = 0;
+        while i < triangles.len() {
+            let quad = triangles
+                .get(i + 1)
+                .and_then(|&next| mesh_quad(mesh, triangles[i], next));
+            if let Some(quad) = quad {
+                i += 2;
+                if quad.color.a() == 0 || !self.clip.intersects(quad.rect) {
+                    continue;
+                }
+                let rect = quad.rect.intersect(self.clip);
+                match (quad.uv, texture) {
+                    (Some(uv), Some(texture_id)) => {
+                        let uv = clip_uv(quad.rect, uv, rect);
+                        end_run(self.painter, &mut run_color);
+                        self.painter
+                            .add_primitive(
+                                self.rect_primitive(rect, epaint::CornerRadius::ZERO),
+                                to_vec4(quad.color),
+                            )
+                            .with_texture_id(texture_id)
+                            .with_texture_uv(vec2(uv.min.x, uv.min.y), vec2(uv.max.x, uv.max.y));
+                    }
+                    (Some(_), None) => {}
+                    (None, _) => {
+                        end_run(self.painter, &mut run_color);
+                        self.clipped_rect(rect, quad.color);
+                    }
+                }
+                continue;
+            }
+
+            let vertices = triangles[i].map(|index| &mesh.vertices[index]);
+            i += 1;
+            let color = average_color(vertices);
+            let bounds = Rect::from_points(&vertices.map(|vertex| vertex.pos));
+            if color.a() == 0 || !self.clip.intersects(bounds) {
+                continue;
+            }
+            let pieces = clip_triangle(vertices.map(|vertex| vertex.pos), self.clip);
+
+            let textured = vertices[1..]
+                .iter()
+                .any(|vertex| vertex.uv != vertices[0].uv);
+            if textured {
+                let Some(texture_id) = texture else {
+                    continue;
+                };
+                let Some(uv) = triangle_uv(vertices, bounds) else {
+                    self.skipped_triangles += 1;
+                    continue;
+                };
+                end_run(self.painter, &mut run_color);
+                for piece in pieces {
+                    let uv = clip_uv(bounds, uv, Rect::from_points(&piece));
+                    self.painter
+                        .add_primitive(self.triangle_primitive(piece), to_vec4(color))
+                        .with_texture_id(texture_id)
+                        .with_texture_uv(vec2(uv.min.x, uv.min.y), vec2(uv.max.x, uv.max.y));
+                }
+                continue;
+            }
+
+            if run_color != Some(color) {
+                end_run(self.painter, &mut run_color);
+                self.painter.begin_group();
+                run_color = Some(color);
+            }
+            for piece in pieces {
+                self.fill(self.triangle_primitive(piece), color);
+            }
+        }
+        end_run(self.painter, &mut run_color);
+    }
+}
+
+/// Ends the group of a run of mesh triangles of the same color
+fn end_run(painter: &mut Painter, run_color: &mut Option<Color32>) {
+    if run_color.take().is_some() {
+        painter.end_group();
+    }
+}
+
+/// An axis-aligned rect formed by two mesh triangles
+struct MeshQuad {
+    rect: Rect,
+    /// UV rect mapped onto the rect, or `None` if all corners sample the same texel
+    uv: Option<Rect>,
+    color: Color32,
+}
+
+/// Finds the rect formed by two triangles sharing a diagonal, with a single color and a texture mapped linearly onto it
+fn mesh_quad(mesh: &Mesh, a: [usize; 3], b: [usize; 3]) -> Option<MeshQuad> {
+    let shared: Vec<usize> = a.into_iter().filter(|index| b.contains(index)).collect();
+    let [first, second] = shared[..] else {
+        return None;
+    };
+    let corner_a = a.into_iter().find(|index| !shared.contains(index))?;
+    let corner_b = b.into_iter().find(|index| !shared.contains(index))?;
+    let corners = [first, corner_a, second, corner_b].map(|index| &mesh.vertices[index]);
+
+    let rect = Rect::from_points(&corners.map(|vertex| vertex.pos));
+    let diagonal_a = corners[0].pos - corners[2].pos;
+    let diagonal_b = corners[1].pos - corners[3].pos;
+    let is_rect = rect.is_positive()
+        && diagonal_a.x.abs() == rect.width()
+        && diagonal_a.y.abs() == rect.height()
+        && diagonal_b.x.abs() == rect.width()
+        && diagonal_b.y.abs() == rect.height();
+    if !is_rect
+        || corners
+            .iter()
+            .any(|vertex| vertex.color != corners[0].color)
+    {
+        return None;
+    }
+
+    // UVs at the top left and bottom right corners, which may be flipped
+    let uv_at = |x: f32, y: f32| {
+        corners
+            .iter()
+            .find(|vertex| vertex.pos.x == x && vertex.pos.y == y)
+            .map(|vertex| vertex.uv)
+    };
+    let uv_min = uv_at(rect.min.x, rect.min.y)?;
+    let uv_max = uv_at(rect.max.x, rect.max.y)?;
+    let linear = corners.iter().all(|vertex| {
+        let t = (vertex.pos - rect.min) / rect.size();
+        let expected = uv_min + (uv_max - uv_min) * t;
+        (vertex.uv - expected).length() <= 1e-5
+    });
+    if !linear {
+        return None;
+    }
+    Some(MeshQuad {
+        rect,
+        uv: (uv_min != uv_max).then_some(Rect {
+            min: uv_min,
+            max: uv_max,
+        }),
+        color: corners[0].color,
+    })
+}
+
+/// The UV rect mapped onto the bounds of a triangle, or `None` if its texture is rotated or skewed, which can't be
+/// expressed as a UV rect
+fn triangle_uv(vertices: [&Vertex; 3], bounds: Rect) -> Option<Rect> {
+    let [a, b, c] = vertices;
+    let (edge_b, edge_c) = (b.pos - a.pos, c.pos - a.pos);
+    let det = edge_b.x * edge_c.y - edge_b.y * edge_c.x;
+    if det.abs() < 1e-6 {
+        // Degenerate triangles cover no pixels, so any mapping will do
+        return Some(Rect::from_min_max(a.uv, a.uv));
+    }
+    let (uv_b, uv_c) = (b.uv - a.uv, c.uv - a.uv);
+    // Derivatives of the UVs along the screen axes
+    let du_dx = (uv_b.x * edge_c.y - uv_c.x * edge_b.y) / det;
+    let du_dy = (uv_c.x * edge_b.x - uv_b.x * edge_c.x) / det;
+    let dv_dx = (uv_b.y * edge_c.y - uv_c.y * edge_b.y) / det;
+    let dv_dy = (uv_c.y * edge_b.x - uv_b.y * edge_c.x) / det;
+
+    // Allow a tenth of a texel of error across the triangle, on textures up to 1024 texels wide
+    let tolerance = 1e-4 / bounds.size().max_elem().max(1.0);
+    if du_dy.abs() > tolerance || dv_dx.abs() > tolerance {
+        return None;
+    }
+    let uv_at = |point: Pos2| {
+        let offset = point - a.pos;
+        Pos2::new(a.uv.x + du_dx * offset.x, a.uv.y + dv_dy * offset.y)
+    };
+    Some(Rect {
+        min: uv_at(bounds.min),
+        max: uv_at(bounds.max),
+    })
+}
+
+/// Average of the premultiplied vertex colors, which mondrian can't interpolate
+fn average_color(vertices: [&Vertex; 3]) -> Color32 {
+    let sum = vertices.iter().fold([0u32; 4], |sum, vertex| {
+        let color = vertex.color.to_array();
+        std::array::from_fn(|i| sum[i] + color[i] as u32)
+    });
+    let [r, g, b, a] = sum.map(|channel| ((channel + 1) / 3) as u8);
+    Color32::from_rgba_premultiplied(r, g, b, a)
+}
+
+/// The part of `uv` mapped onto the `clipped` part of `rect`
+fn clip_uv(rect: Rect, uv: Rect, clipped: Rect) -> Rect {
+    let uv_at = |point: Pos2| uv.min + (uv.max - uv.min) * ((point - rect.min) / rect.size());
+    Rect {
+        min: uv_at(clipped.min),
+        max: uv_at(clipped.max),
+    }
+}
+
+/// Cuts a triangle to the clip rect, returning the triangles covering the part inside it
+fn clip_triangle(triangle: [Pos2; 3], clip: Rect) -> Vec<[Pos2; 3]> {
+    if clip.contains_rect(Rect::from_points(&triangle)) {
+        return vec![triangle];
+    }
+    // Sutherland-Hodgman clipping against each edge of the rect, keeping the points on its inner side
+    let edges: [(usize, f32, bool); 4] = [
+        (0, clip.min.x, true),
+        (0, clip.max.x, false),
+        (1, clip.min.y, true),
+        (1, clip.max.y, false),
+    ];
+    let mut polygon = triangle.to_vec();
+    for (axis, bound, is_min) in edges {
+        let inside = |p: Pos2| {
+            if is_min {
+                p[axis] >= bound
+            } else {
+                p[axis] <= bound
+            }
+        };
+        let input = std::mem::take(&mut polygon);
+        for (j, &current) in input.iter().enumerate() {
+            let previous = input[(j + input.len() - 1) % input.len()];
+            if inside(current) != inside(previous) {
+                let t = (bound - previous[axis]) / (current[axis] - previous[axis]);
+                let mut crossing = previous + (current - previous) * t;
+                // Avoid rounding errors placing the crossing just outside the clip rect
+                crossing[axis] = bound;
+                polygon.push(crossing);
+            }
+            if inside(current) {
+                polygon.push(current);
+            }
+        }
+        if polygon.is_empty() {
+            return Vec::new();
+        }
+    }
+    // The clipped polygon stays convex, so it can be split into a fan
+    polygon[1..]
+        .windows(2)
+        .map(|pair| [polygon[0], pair[0], pair[1]])
+        .collect()
+}
+
+/// The distance offset moving an outline, which mondrian centers on the shape's edge, to egui's stroke position
+fn stroke_offset(kind: StrokeKind, width: f32) -> f32 {
+    match kind {
+        StrokeKind::Inside => width / 2.0,
+        StrokeKind::Middle => 0.0,
+        StrokeKind::Outside => -width / 2.0,
+    }
+}
+
+/// egui's colors are premultiplied, while mondrian blends colors with straight alpha
+fn to_vec4(color: Color32) -> Vec4 {
+    Vec4::from_array(color.to_srgba_unmultiplied().map(|c| c as f32 / 255.0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vertex(x: f32, y: f32, u: f32, v: f32) -> Vertex {
+        Vertex {
+            pos: Pos2::new(x, y),
+            uv: Pos2::new(u, v),
+            color: Color32::WHITE,
+        }
+    }
+
+    fn uv(vertices: [Vertex; 3]) -> Option<Rect> {
+        let bounds = Rect::from_points(&vertices.map(|vertex| vertex.pos));
+        triangle_uv([&vertices[0], &vertices[1], &vertices[2]], bounds)
+    }
+
+    #[test]
+    fn maps_scaled_textures_onto_triangle_bounds() {
+        let uv = uv([
+            vertex(10.0, 10.0, 0.25, 0.5),
+            vertex(30.0, 10.0, 0.75, 0.5),
+            vertex(20.0, 30.0, 0.5, 1.0),
+        ]);
+        assert_eq!(
+            uv,
+            Some(Rect::from_min_max(
+                Pos2::new(0.25, 0.5),
+                Pos2::new(0.75, 1.0)
+            ))
+        );
+    }
+
+    #[test]
+    fn keeps_flipped_textures() {
+        let uv = uv([
+            vertex(0.0, 0.0, 1.0, 0.0),
+            vertex(10.0, 0.0, 0.0, 0.0),
+            vertex(0.0, 10.0, 1.0, 1.0),
+        ]);
+        assert_eq!(
+            uv,
+            Some(Rect {
+                min: Pos2::new(1.0, 0.0),
+                max: Pos2::new(0.0, 1.0),
+            })
+        );
+    }
+
+    #[test]
+    fn rejects_rotated_textures() {
+        let uv = uv([
+            vertex(0.0, 0.0, 0.0, 0.0),
+            vertex(10.0, 0.0, 0.0, 1.0),
+            vertex(0.0, 10.0, 1.0, 0.0),
+        ]);
+        assert_eq!(uv, None);
+    }
+
+    #[test]
+    fn averages_vertex_colors() {
+        let vertices = [Color32::RED, Color32::BLUE, Color32::TRANSPARENT].map(|color| Vertex {
+            color,
+            ..Default::default()
+        });
+        assert_eq!(
+            average_color([&vertices[0], &vertices[1], &vertices[2]]),
+            Color32::from_rgba_premultiplied(85, 0, 85, 170)
+        );
+    }
+
+    /// Paints the shapes with a clip rect, returning the bounds of the painted primitives
+    fn paint_clipped(clip_rect: Rect, shapes: Vec<EguiShape>) -> Vec<crate::shape::BoundingBox> {
+        let shapes: Vec<ClippedShape> = shapes
+            .into_iter()
+            .map(|shape| ClippedShape { clip_rect, shape })
+            .collect();
+        let mut painter = Painter::new();
+        painter.start((200, 200));
+        EguiPainter::new().paint(&mut painter, &shapes, 1.0);
+        let mut bounds = Vec::new();
+        painter.finish(|shapes, _| bounds = shapes.iter().map(|shape| shape.bounds()).collect());
+        bounds
+    }
+
+    fn area(triangles: &[[Pos2; 3]]) -> f32 {
+        triangles
+            .iter()
+            .map(|[a, b, c]| ((*b - *a).x * (*c - *a).y - (*b - *a).y * (*c - *a).x).abs() / 2.0)
+            .sum()
+    }
+
+    #[test]
+    fn cuts_half_clipped_shapes_to_the_clip_rect() {
+        let clip = Rect::from_min_max(Pos2::new(0.0, 0.0), Pos2::new(100.0, 50.0));
+        let bounds = paint_clipped(
+            clip,
+            vec![
+                EguiShape::circle_filled(Pos2::new(50.0, 50.0), 20.0, Color32::WHITE),
+                EguiShape::circle_stroke(
+                    Pos2::new(100.0, 25.0),
+                    10.0,
+                    Stroke::new(2.0, Color32::WHITE),
+                ),
+                EguiShape::rect_filled(
+                    Rect::from_min_max(Pos2::new(80.0, 10.0), Pos2::new(120.0, 40.0)),
+                    4.0,
+                    Color32::WHITE,
+                ),
+            ],
+        );
+        assert!(!bounds.is_empty());
+        for bounds in bounds {
+            assert!(
+                bounds.min.x >= clip.min.x - 1e-3
+                    && bounds.min.y >= clip.min.y - 1e-3
+                    && bounds.max.x <= clip.max.x + 1e-3
+                    && bounds.max.y <= clip.max.y + 1e-3,
+                "{bounds:?} exceeds the clip rect"
+            );
+        }
+    }
+
+    #[test]
+    fn draws_shapes_inside_the_clip_rect_whole() {
+        let clip = Rect::from_min_max(Pos2::new(0.0, 0.0), Pos2::new(100.0, 100.0));
+        let bounds = paint_clipped(
+            clip,
+            vec![EguiShape::circle_filled(
+                Pos2::new(50.0, 50.0),
+                20.0,
+                Color32::WHITE,
+            )],
+        );
+        assert_eq!(bounds.len(), 1);
+    }
+
+    #[test]
+    fn clips_triangles() {
+        let clip = Rect::from_min_max(Pos2::new(0.0, 0.0), Pos2::new(10.0, 10.0));
+        let triangle = [
+            Pos2::new(-10.0, 0.0),
+            Pos2::new(10.0, 0.0),
+            Pos2::new(10.0, 10.0),
+        ];
+        let pieces = clip_triangle(triangle, clip);
+        // The part of the triangle inside the clip rect is a trapezoid between x = 0 and x = 10
+        assert!((area(&pieces) - 75.0).abs() < 1e-3, "{}", area(&pieces));
+        for point in pieces.iter().flatten() {
+            assert!(clip.expand(1e-4).contains(*point), "{point:?}");
+        }
+
+        assert!(clip_triangle(triangle.map(|p| p + epaint::vec2(0.0, 50.0)), clip).is_empty());
+        let inside = [
+            Pos2::new(1.0, 1.0),
+            Pos2::new(9.0, 1.0),
+            Pos2::new(1.0, 9.0),
+        ];
+        assert_eq!(clip_triangle(inside, clip), vec![inside]);
+    }
+}
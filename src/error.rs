@@ -0,0 +1,279 @@
+use std::fmt;
+
+use crate::shape::TextureId;
+
+/// Errors and warnings reported by mondrian
+#[derive(Clone, Debug, PartialEq)]
+pub enum Error {
+    /// `start` was called again before the previous frame was finished
+    AlreadyStarted,
+    /// `finish` was called without a matching `start`
+    NotStarted,
+    /// A shape has non-finite or negative geometry. The shape is drawn as an empty shape instead.
+    InvalidGeometry {
+        shape_index: usize,
+        reason: GeometryError,
+    },
+    /// A shape or texture operation referenced a texture that isn't registered, or was evicted from the atlas
+    UnknownTexture(TextureId),
+    /// More distinct textures were used in a single frame than the renderer can bind
+    TooManyTextures { max: u32 },
+    /// Texture data doesn't match the size and format of the texture
+    InvalidTextureData { expected_len: usize, len: usize },
+    /// A texture update region extends past the texture
+    TextureRegionOutOfBounds,
+    /// The texture's format can't be written to from the CPU
+    UnsupportedTextureFormat(wgpu::TextureFormat),
+    /// The image doesn't fit into the atlas, either because it is too large or because all pages are full
+    AtlasFull,
+    /// The atlas configuration can't be changed after atlas textures have been registered
+    AtlasInUse,
+    /// Font atlas metrics couldn't be parsed
+    InvalidFontAtlas(FontAtlasError),
+    /// A font file couldn't be parsed
+    InvalidFont(String),
+    /// A scene couldn't be encoded or decoded
+    SceneSerialization(SceneError),
+    /// An SVG document couldn't be parsed
+    InvalidSvg(SvgError),
+    /// Mesh triangles with a rotated or skewed texture were skipped, as textures are only mapped onto axis-aligned
+    /// bounds
+    UnsupportedMesh { skipped_triangles: usize },
+    /// The runner couldn't create its window, GPU device or surface, or lost the device while running
+    Runner(RunnerError),
+}
+
+/// Why a shape's geometry is invalid
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GeometryError {
+    /// A coordinate, size, color or style value is NaN or infinite
+    NonFinite,
+    /// A radius, extent or line width is negative, or a rect's min corner lies past its max corner
+    NegativeSize,
+}
+
+/// Why font atlas metrics couldn't be loaded
+#[derive(Clone, Debug, PartialEq)]
+pub enum FontAtlasError {
+    /// The JSON metrics are malformed or missing a field
+    Json {
+        line: usize,
+        column: usize,
+        message: String,
+    },
+    /// The atlas `type` isn't one written by msdf-atlas-gen
+    UnsupportedAtlasType(String),
+    /// The atlas `yOrigin` is neither `bottom` nor `top`
+    UnsupportedYOrigin(String),
+    /// A glyph or kerning pair names a value that isn't a unicode scalar value. `line` is set for CSV rows.
+    InvalidCodePoint {
+        code_point: u32,
+        line: Option<usize>,
+    },
+    /// A CSV row holds a value that isn't a number
+    InvalidNumber { line: usize },
+    /// A CSV row doesn't have the 10 expected columns
+    WrongColumnCount { line: usize, columns: usize },
+    /// The atlas width or height is zero
+    EmptyAtlas,
+}
+
+/// Why a scene couldn't be encoded or decoded
+#[derive(Clone, Debug, PartialEq)]
+pub enum SceneError {
+    /// The data doesn't start with the scene magic bytes
+    NotAScene,
+    /// The data ends right after the magic bytes
+    MissingVersion,
+    /// The scene was written by a different version of the binary format
+    UnsupportedVersion(u8),
+    /// The encoder failed, with its message
+    Encode(String),
+    /// The data is truncated or malformed, with the decoder's message
+    Decode(String),
+}
+
+/// Why an SVG document couldn't be parsed
+#[derive(Clone, Debug, PartialEq)]
+pub enum SvgError {
+    /// The document isn't well-formed XML. `position` is the byte offset of the error.
+    Syntax { position: u64, message: String },
+    /// An attribute is malformed or holds an invalid escape sequence
+    Attribute(String),
+    /// The document has no `<svg>` element
+    MissingRoot,
+    /// The root element isn't `<svg>`, holds the element's name
+    UnexpectedRoot(String),
+}
+
+/// Why the runner failed
+#[derive(Clone, Debug, PartialEq)]
+pub enum RunnerError {
+    /// The event loop couldn't be created or failed while running
+    EventLoop(String),
+    /// The window couldn't be created
+    Window(String),
+    /// The window's surface couldn't be created
+    CreateSurface(String),
+    /// No adapter can draw to the window's surface
+    Adapter(wgpu::RequestAdapterError),
+    /// The adapter couldn't create a device
+    Device(String),
+    /// The adapter can't present to the window's surface
+    UnsupportedSurface,
+    /// The next surface texture couldn't be acquired, eg. because the device was lost
+    SurfaceTexture(wgpu::SurfaceError),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::AlreadyStarted => write!(f, "start() called before finish()"),
+            Error::NotStarted => write!(f, "finish() called before start()"),
+            Error::InvalidGeometry {
+                shape_index,
+                reason,
+            } => write!(f, "shape {shape_index} has invalid geometry: {reason}"),
+            Error::UnknownTexture(id) => write!(f, "texture {id:?} is not registered"),
+            Error::TooManyTextures { max } => write!(
+                f,
+                "reached maximum number of textures ({max}), some textures will not be available"
+            ),
+            Error::InvalidTextureData { expected_len, len } => write!(
+                f,
+                "texture data is {len} bytes, expected {expected_len} tightly packed bytes"
+            ),
+            Error::TextureRegionOutOfBounds => write!(f, "texture region is out of bounds"),
+            Error::UnsupportedTextureFormat(format) => {
+                write!(f, "texture format {format:?} can't be written to")
+            }
+            Error::AtlasFull => write!(f, "image does not fit in the texture atlas"),
+            Error::AtlasInUse => write!(
+                f,
+                "atlas configuration can't be changed after atlas textures have been registered"
+            ),
+            Error::InvalidFontAtlas(reason) => write!(f, "invalid font atlas: {reason}"),
+            Error::InvalidFont(message) => write!(f, "invalid font: {message}"),
+            Error::SceneSerialization(reason) => write!(f, "scene serialization failed: {reason}"),
+            Error::InvalidSvg(reason) => write!(f, "invalid SVG: {reason}"),
+            Error::UnsupportedMesh { skipped_triangles } => write!(
+                f,
+                "skipped {skipped_triangles} mesh triangles with rotated or skewed textures"
+            ),
+            Error::Runner(reason) => write!(f, "runner failed: {reason}"),
+        }
+    }
+}
+
+impl fmt::Display for GeometryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GeometryError::NonFinite => write!(f, "non-finite value"),
+            GeometryError::NegativeSize => write!(f, "negative size"),
+        }
+    }
+}
+
+impl fmt::Display for FontAtlasError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FontAtlasError::Json {
+                line,
+                column,
+                message,
+            } => write!(f, "{message} at line {line} column {column}"),
+            FontAtlasError::UnsupportedAtlasType(kind) => {
+                write!(f, "unsupported atlas type {kind:?}")
+            }
+            FontAtlasError::UnsupportedYOrigin(origin) => {
+                write!(f, "unsupported yOrigin {origin:?}")
+            }
+            FontAtlasError::InvalidCodePoint {
+                code_point,
+                line: Some(line),
+            } => write!(f, "invalid code point {code_point} on line {line}"),
+            FontAtlasError::InvalidCodePoint {
+                code_point,
+                line: None,
+            } => write!(f, "invalid code point {code_point}"),
+            FontAtlasError::InvalidNumber { line } => write!(f, "invalid number on line {line}"),
+            FontAtlasError::WrongColumnCount { line, columns } => {
+                write!(f, "expected 10 columns on line {line}, found {columns}")
+            }
+            FontAtlasError::EmptyAtlas => write!(f, "atlas width and height must be non-zero"),
+        }
+    }
+}
+
+impl fmt::Display for SceneError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SceneError::NotAScene => write!(f, "not a mondrian scene"),
+            SceneError::MissingVersion => write!(f, "missing scene version"),
+            SceneError::UnsupportedVersion(version) => {
+                write!(f, "unsupported scene version {version}")
+            }
+            SceneError::Encode(message) | SceneError::Decode(message) => write!(f, "{message}"),
+        }
+    }
+}
+
+impl fmt::Display for SvgError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SvgError::Syntax { position, message } => write!(f, "{message} at byte {position}"),
+            SvgError::Attribute(message) => write!(f, "{message}"),
+            SvgError::MissingRoot => write!(f, "missing <svg> element"),
+            SvgError::UnexpectedRoot(name) => {
+                write!(f, "root element is <{name}> instead of <svg>")
+            }
+        }
+    }
+}
+
+impl fmt::Display for RunnerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RunnerError::EventLoop(message) => write!(f, "event loop error: {message}"),
+            RunnerError::Window(message) => write!(f, "couldn't create window: {message}"),
+            RunnerError::CreateSurface(message) => {
+                write!(f, "couldn't create surface: {message}")
+            }
+            RunnerError::Adapter(error) => write!(f, "{error}"),
+            RunnerError::Device(message) => write!(f, "couldn't create device: {message}"),
+            RunnerError::UnsupportedSurface => write!(f, "surface is not supported by the adapter"),
+            RunnerError::SurfaceTexture(error) => write!(f, "{error}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// Callback receiving warnings
+pub type WarningHandler = Box<dyn FnMut(&Error) + Send>;
+
+/// Channel for problems that don't prevent rendering, eg. invalid shapes or missing textures.
+///
+/// Warnings are passed to the handler set with [`Diagnostics::set_handler`], or logged through the `log` crate otherwise.
+#[derive(Default)]
+pub struct Diagnostics {
+    handler: Option<WarningHandler>,
+}
+
+impl Diagnostics {
+    pub fn set_handler(&mut self, handler: impl FnMut(&Error) + Send + 'static) {
+        self.handler = Some(Box::new(handler));
+    }
+
+    /// Removes the handler, logging warnings through the `log` crate again
+    pub fn clear_handler(&mut self) {
+        self.handler = None;
+    }
+
+    pub(crate) fn warn(&mut self, warning: Error) {
+        match &mut self.handler {
+            Some(handler) => handler(&warning),
+            None => log::warn!("{warning}"),
+        }
+    }
+}
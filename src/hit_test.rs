@@ -0,0 +1,208 @@
+//! CPU queries against finished frames, evaluating shape groups the same way the shader does
+
+use glam::Vec2;
+
+use crate::{binner::ShapeBinner, sdf, shape::Shape};
+
+/// Distance from `point` to a shape group, including distance offsets and the group's outline, and the index of the
+/// group's shape closest to the point.
+///
+/// SDF textures can't be sampled on the CPU, so shapes using them are approximated by their primitive.
+pub(crate) fn group_distance(group: &[Shape], point: Vec2) -> (f32, usize) {
+    let mut dist = f32::INFINITY;
+    let mut closest = 0;
+    for (i, shape) in group.iter().enumerate() {
+        let shape_dist = shape.primitive.distance(point) + shape.distance_offset;
+        if shape_dist < dist {
+            closest = i;
+        }
+        dist = sdf::union(dist, shape_dist);
+    }
+    // The last shape of a group defines its style
+    if let Some(style) = group.last()
+        && style.line_width > 0.0
+    {
+        dist = sdf::outline(dist, style.line_width / 2.0);
+    }
+    (dist, closest)
+}
+
+/// Index of the topmost shape covering `point`, looking only at the groups binned into the tile containing it
+pub(crate) fn hit_test(shapes: &[Shape], binner: &ShapeBinner, point: Vec2) -> Option<usize> {
+    let (width, height) = binner.resolution;
+    if !(point.x >= 0.0 && point.y >= 0.0 && point.x < width as f32 && point.y < height as f32) {
+        return None;
+    }
+    let (tiles_x, _) = binner.tile_count();
+    let tile = (point.y as u32 / binner.tile_size) * tiles_x + point.x as u32 / binner.tile_size;
+    let range = binner.tile_ranges.get(tile as usize..tile as usize + 2)?;
+    let indices = binner
+        .shape_indices
+        .get(range[0] as usize..range[1] as usize)?;
+
+    // Tiles list whole groups in draw order, so the last group that covers the point is on top
+    let mut hit = None;
+    for group in indices.chunk_by(|&a, &b| {
+        shapes.get(a as usize).map(|s| s.group_id) == shapes.get(b as usize).map(|s| s.group_id)
+    }) {
+        let start = group[0] as usize;
+        let end = *group.last().unwrap() as usize + 1;
+        let Some(group) = shapes.get(start..end) else {
+            continue;
+        };
+        let (dist, closest) = group_distance(group, point);
+        if dist <= 0.0 {
+            hit = Some(start + closest);
+        }
+    }
+    hit
+}
+
+/// Distance from `point` to the group containing the shape at `index`
+pub(crate) fn distance_to(shapes: &[Shape], index: usize, point: Vec2) -> Option<f32> {
+    let group_id = shapes.get(index)?.group_id;
+    let start = shapes[..index]
+        .iter()
+        .rposition(|s| s.group_id != group_id)
+        .map_or(0, |i| i + 1);
+    let end = shapes[index..]
+        .iter()
+        .position(|s| s.group_id != group_id)
+        .map_or(shapes.len(), |i| index + i);
+    Some(group_distance(&shapes[start..end], point).0)
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::Vec4;
+
+    use crate::painter::Painter;
+
+    fn tag(painter: &Painter, point: (f32, f32)) -> Option<u64> {
+        painter.hit_test(point).and_then(|handle| handle.tag())
+    }
+
+    #[test]
+    fn topmost_group_wins() {
+        let mut painter = Painter::new();
+        painter.start((128, 128));
+        painter.begin_group();
+        painter
+            .add_filled_circle((40.0, 40.0), 20.0, Vec4::ONE)
+            .with_tag(0);
+        painter
+            .add_filled_circle((60.0, 40.0), 20.0, Vec4::ONE)
+            .with_tag(1);
+        painter.end_group();
+        painter.begin_group();
+        painter
+            .add_filled_circle((80.0, 40.0), 20.0, Vec4::ONE)
+            .with_tag(2);
+        painter.end_group();
+        painter.finish(|_, _| {});
+
+        assert_eq!(tag(&painter, (25.0, 40.0)), Some(0));
+        // Within a group, the closest shape is returned
+        assert_eq!(tag(&painter, (55.0, 40.0)), Some(1));
+        // Where groups overlap, the later one is on top
+        assert_eq!(tag(&painter, (70.0, 40.0)), Some(2));
+        assert_eq!(tag(&painter, (40.0, 100.0)), None);
+    }
+
+    #[test]
+    fn outlines_are_hollow() {
+        let mut painter = Painter::new();
+        painter.start((128, 128));
+        painter
+            .add_circle((64.0, 64.0), 30.0, Vec4::ONE, 4.0)
+            .with_tag(0);
+        painter.finish(|_, _| {});
+
+        assert_eq!(tag(&painter, (94.0, 64.0)), Some(0));
+        assert_eq!(tag(&painter, (64.0, 35.0)), Some(0));
+        assert_eq!(tag(&painter, (64.0, 64.0)), None);
+        assert_eq!(tag(&painter, (100.0, 64.0)), None);
+    }
+
+    #[test]
+    fn distance_offsets_grow_shapes() {
+        let mut painter = Painter::new();
+        painter.start((128, 128));
+        painter
+            .add_filled_circle((64.0, 64.0), 10.0, Vec4::ONE)
+            .with_distance_offset(-5.0)
+            .with_tag(0);
+        let handle = painter.last_handle().unwrap();
+        painter.finish(|_, _| {});
+
+        assert_eq!(tag(&painter, (78.0, 64.0)), Some(0));
+        assert_eq!(tag(&painter, (80.0, 64.0)), None);
+        let distance = painter.distance_to(handle, (84.0, 64.0)).unwrap();
+        assert!((distance - 5.0).abs() < 1e-4, "{distance}");
+        let distance = painter.distance_to(handle, (64.0, 64.0)).unwrap();
+        assert!((distance + 15.0).abs() < 1e-4, "{distance}");
+    }
+
+    #[test]
+    fn distance_includes_outline() {
+        let mut painter = Painter::new();
+        painter.start((128, 128));
+        painter.add_circle((64.0, 64.0), 30.0, Vec4::ONE, 4.0);
+        let handle = painter.last_handle().unwrap();
+        painter.finish(|_, _| {});
+
+        let distance = painter.distance_to(handle, (64.0, 64.0)).unwrap();
+        assert!((distance - 28.0).abs() < 1e-4, "{distance}");
+        let distance = painter.distance_to(handle, (94.0, 64.0)).unwrap();
+        assert!((distance + 2.0).abs() < 1e-4, "{distance}");
+    }
+
+    #[test]
+    fn ignores_points_off_screen() {
+        let mut painter = Painter::new();
+        painter.start((64, 64));
+        // Covers the whole screen and beyond
+        painter
+            .add_filled_circle((32.0, 32.0), 100.0, Vec4::ONE)
+            .with_tag(0);
+        painter.finish(|_, _| {});
+
+        assert_eq!(tag(&painter, (0.0, 0.0)), Some(0));
+        assert_eq!(tag(&painter, (63.5, 63.5)), Some(0));
+        assert_eq!(tag(&painter, (-1.0, 10.0)), None);
+        assert_eq!(tag(&painter, (10.0, -1.0)), None);
+        assert_eq!(tag(&painter, (64.0, 10.0)), None);
+        assert_eq!(tag(&painter, (10.0, 64.0)), None);
+        assert_eq!(tag(&painter, (f32::NAN, 10.0)), None);
+    }
+
+    #[test]
+    fn hit_tests_finished_frame_after_resize() {
+        let mut painter = Painter::new();
+        painter.start((640, 480));
+        painter
+            .add_filled_circle((600.0, 400.0), 20.0, Vec4::ONE)
+            .with_tag(0);
+        painter
+            .add_filled_circle((20.0, 20.0), 10.0, Vec4::ONE)
+            .with_tag(1);
+        painter.finish(|_, _| {});
+
+        // Shrinking moves the binner to the new resolution before the next frame is finished
+        painter.start((100, 100));
+        assert_eq!(tag(&painter, (600.0, 400.0)), Some(0));
+        assert_eq!(tag(&painter, (20.0, 20.0)), Some(1));
+        painter.finish(|_, _| {});
+
+        painter.start((50, 50));
+        painter
+            .add_filled_circle((20.0, 20.0), 10.0, Vec4::ONE)
+            .with_tag(2);
+        painter.finish(|_, _| {});
+
+        // Growing must not read tiles beyond the finished frame
+        painter.start((1280, 960));
+        assert_eq!(tag(&painter, (20.0, 20.0)), Some(2));
+        assert_eq!(tag(&painter, (600.0, 400.0)), None);
+    }
+}
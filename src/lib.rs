@@ -1,7 +1,25 @@
 pub mod backend;
 pub mod binner;
+mod capture;
+#[cfg(feature = "egui")]
+pub mod egui;
+pub mod error;
+mod hit_test;
 pub mod painter;
+#[cfg(feature = "runner")]
+pub mod runner;
+pub mod scene;
+pub mod sdf;
 pub mod shape;
+#[cfg(feature = "svg")]
+pub mod svg;
+pub mod text;
 
+pub use capture::Scene;
+pub use error::{
+    Diagnostics, Error, FontAtlasError, GeometryError, RunnerError, SceneError, SvgError,
+};
 pub use painter::Painter;
-pub use shape::{CornerRadius, Primitive, Shape, TextureId};
+pub use scene::RetainedScene;
+pub use shape::{CornerRadius, GroupStyle, Primitive, Shape, ShapeHandle, TextureFit, TextureId};
+pub use text::{FontAtlas, TextAlign, TextStyle};
@@ -1,17 +1,36 @@
 use crate::{
-    binner::ShapeBinner,
-    shape::{CornerRadius, Primitive, Shape},
+    binner::{BinningMode, ShapeBinner, shape_groups},
+    error::{Diagnostics, Error},
+    hit_test,
+    shape::{CornerRadius, GroupStyle, Primitive, Shape, ShapeHandle, TextureMapping},
+    text::{FontAtlas, TextLayout, TextStyle},
 };
 use glam::{Vec2, Vec4};
 
 pub struct Painter {
     shapes: Vec<Shape>,
     next_group_id: u32,
-    in_group: bool,
-    first_shape_in_group: usize,
+    /// Groups that have been begun but not ended yet, innermost last
+    open_groups: Vec<OpenGroup>,
+    layer: i32,
+    /// Generation of the current frame, see [`ShapeHandle::generation`]
+    generation: u32,
+    /// Shapes of the most recently finished frame, binned by `binner`, kept for hit testing
+    finished_shapes: Vec<Shape>,
+    finished_generation: u32,
+    /// Submission index of each finished shape, in draw order. Empty if no shapes were reordered by layer.
+    finished_order: Vec<u32>,
+    /// Draw order position of each finished shape, by submission index. Empty if no shapes were reordered by layer.
+    finished_positions: Vec<u32>,
 
+    /// Scale applied to the shapes when a frame is finished, see [`Painter::set_scale`]
+    scale: f32,
     binner: ShapeBinner,
+    /// Tile lists of the most recently finished frame, kept for hit testing while `binner` already follows the next
+    /// frame's resolution
+    finished_binner: ShapeBinner,
     started: bool,
+    diagnostics: Diagnostics,
 }
 
 impl Painter {
@@ -19,78 +38,402 @@ impl Painter {
         Self {
             shapes: Vec::new(),
             next_group_id: 0,
-            in_group: false,
-            first_shape_in_group: 0,
+            open_groups: Vec::new(),
+            layer: 0,
+            generation: 1,
+            finished_shapes: Vec::new(),
+            finished_generation: 0,
+            finished_order: Vec::new(),
+            finished_positions: Vec::new(),
 
+            scale: 1.0,
             binner: ShapeBinner::new(32, (0, 0)),
+            finished_binner: ShapeBinner::new(32, (0, 0)),
             started: false,
+            diagnostics: Diagnostics::default(),
         }
     }
 
+    /// Adds a shape to the current frame, in the open group if there is one.
+    ///
+    /// The returned reference is only borrowed until the next call. To find the shape again later, eg. to modify it or
+    /// to hit test the finished frame, take its handle with [`Painter::last_handle`] right after adding it, or give it
+    /// a tag with [`Shape::with_tag`].
     pub fn add_shape(&mut self, mut shape: Shape) -> &mut Shape {
-        shape.group_id = if self.in_group {
-            self.next_group_id
-        } else {
-            self.first_shape_in_group = self.shapes.len();
-            self.next_group_id += 1;
-            self.next_group_id - 1
+        shape.group_id = match self.open_groups.last() {
+            Some(group) => group.group_id,
+            None => self.new_group_id(),
         };
         self.shapes.push(shape);
         self.shapes.last_mut().unwrap()
     }
 
     fn clear_shapes(&mut self) {
+        self.finished_generation = self.generation;
+        self.generation = self.generation.wrapping_add(1);
         self.shapes.clear();
         self.next_group_id = 0;
-        self.in_group = false;
+        self.open_groups.clear();
+        self.layer = 0;
     }
 
+    /// Starts a new frame.
+    ///
+    /// # Panics
+    /// If the previous frame wasn't finished. See [`Painter::try_start`] for a non-panicking version.
     pub fn start(&mut self, resolution: (u32, u32)) {
+        if let Err(e) = self.try_start(resolution) {
+            panic!("Painter::{e}");
+        }
+    }
+
+    /// Starts a new frame, or returns [`Error::AlreadyStarted`] if the previous frame wasn't finished.
+    pub fn try_start(&mut self, resolution: (u32, u32)) -> Result<(), Error> {
         if self.started {
-            panic!("Painter::start() called before Painter::finish()");
+            return Err(Error::AlreadyStarted);
         }
         self.binner.update_resolution(resolution);
         self.started = true;
+        Ok(())
     }
 
+    /// Bins the frame's shapes and passes them to `f`.
+    ///
+    /// # Panics
+    /// If the frame wasn't started. See [`Painter::try_finish`] for a non-panicking version.
     pub fn finish<F: FnOnce(&[Shape], &ShapeBinner)>(&mut self, f: F) {
+        if let Err(e) = self.try_finish(f) {
+            panic!("Painter::{e}");
+        }
+    }
+
+    /// Bins the frame's shapes and passes them to `f`, or returns [`Error::NotStarted`] if the frame wasn't started.
+    ///
+    /// Shapes with invalid geometry are reported as warnings and replaced with empty shapes.
+    pub fn try_finish<F: FnOnce(&[Shape], &ShapeBinner)>(&mut self, f: F) -> Result<(), Error> {
         if !self.started {
-            panic!("Painter::finish() called before Painter::start()");
+            return Err(Error::NotStarted);
         }
 
+        self.end_open_groups();
+        self.scale_shapes();
+        self.validate_shapes();
+        self.sort_layers();
         self.binner.bin_shapes(&self.shapes);
         f(&self.shapes, &self.binner);
+        self.keep_finished_tiles();
+        std::mem::swap(&mut self.shapes, &mut self.finished_shapes);
         self.clear_shapes();
         self.started = false;
+        Ok(())
     }
 
-    /// Begin a group of shapes. All shapes added while in a group will share the same group ID.
-    ///
-    /// End the group with `end_group()`.
+    /// Finishes the frame without binning, for callers that maintain their own [`ShapeBinner`].
+    pub(crate) fn try_finish_unbinned<F: FnOnce(&[Shape])>(&mut self, f: F) -> Result<(), Error> {
+        if !self.started {
+            return Err(Error::NotStarted);
+        }
+
+        self.end_open_groups();
+        self.scale_shapes();
+        self.validate_shapes();
+        self.sort_layers();
+        f(&self.shapes);
+        self.finished_shapes.clear();
+        self.clear_shapes();
+        self.started = false;
+        Ok(())
+    }
+
+    fn scale_shapes(&mut self) {
+        if self.scale != 1.0 {
+            for shape in &mut self.shapes {
+                shape.scale(self.scale);
+            }
+        }
+    }
+
+    /// Moves the frame's tile lists to `finished_binner`. They are rebuilt from scratch when the next frame is binned.
+    fn keep_finished_tiles(&mut self) {
+        let finished = &mut self.finished_binner;
+        finished.tile_size = self.binner.tile_size;
+        finished.resolution = self.binner.resolution;
+        std::mem::swap(&mut finished.tile_ranges, &mut self.binner.tile_ranges);
+        std::mem::swap(&mut finished.shape_indices, &mut self.binner.shape_indices);
+    }
+
+    fn validate_shapes(&mut self) {
+        for (shape_index, shape) in self.shapes.iter_mut().enumerate() {
+            if let Err(reason) = shape.validate() {
+                self.diagnostics.warn(Error::InvalidGeometry {
+                    shape_index,
+                    reason,
+                });
+                *shape = shape.empty();
+            }
+        }
+    }
+
+    /// Stably sorts the shape groups by layer, keeping the shapes of each group contiguous
+    fn sort_layers(&mut self) {
+        self.finished_order.clear();
+        self.finished_positions.clear();
+        if self.shapes.is_sorted_by_key(|shape| shape.layer) {
+            return;
+        }
+
+        let mut groups: Vec<_> = shape_groups(&self.shapes).collect();
+        groups.sort_by_key(|group| self.shapes[group.end - 1].layer);
+        self.finished_order
+            .extend(groups.into_iter().flatten().map(|index| index as u32));
+        self.finished_positions.resize(self.shapes.len(), 0);
+        for (position, &index) in self.finished_order.iter().enumerate() {
+            self.finished_positions[index as usize] = position as u32;
+        }
+
+        let sorted = self
+            .finished_order
+            .iter()
+            .map(|&index| self.shapes[index as usize].clone())
+            .collect();
+        self.shapes = sorted;
+    }
+
+    /// Sets the handler that receives warnings, eg. about shapes with invalid geometry.
+    /// Without a handler, warnings are logged through the `log` crate.
+    pub fn set_warning_handler(&mut self, handler: impl FnMut(&Error) + Send + 'static) {
+        self.diagnostics.set_handler(handler);
+    }
+
+    /// Reports a warning to the warning handler, for shapes added by other modules
+    #[cfg(feature = "egui")]
+    pub(crate) fn warn(&mut self, warning: Error) {
+        self.diagnostics.warn(warning);
+    }
+
+    /// Begins a group of shapes. Shapes added while in a group are joined into a single shape, which is drawn with the
+    /// style of the last shape added to the group.
     ///
-    /// Calling `begin_group()` while already in a group will start a new group.
+    /// End the group with `end_group()`. Groups can be nested, but a group begun with `begin_group()` inside another
+    /// group is flattened into it: its shapes join the enclosing group's single shape, and ending it doesn't split the
+    /// enclosing group. Use [`Painter::begin_group_with_style`] for a nested group that is drawn on its own.
     pub fn begin_group(&mut self) {
-        if self.in_group {
-            self.next_group_id += 1;
-        }
-        self.in_group = true;
-        self.first_shape_in_group = self.shapes.len();
+        self.open_group(None);
     }
 
+    /// Begins a group of shapes drawn with the given style, instead of the style of the group's last shape.
+    /// The style is applied to the group's shapes when the group ends.
+    ///
+    /// Groups can only be joined with their shapes' union, so a styled group begun inside another group is drawn as a
+    /// separate shape, on top of the enclosing group's shapes added before it. Shapes added to the enclosing group
+    /// after the styled group ends are drawn on top of it.
+    pub fn begin_group_with_style(&mut self, style: GroupStyle) {
+        self.open_group(Some(style));
+    }
+
+    fn open_group(&mut self, style: Option<GroupStyle>) {
+        let group_id = match self.open_groups.last() {
+            Some(parent) if style.is_none() => parent.group_id,
+            _ => self.new_group_id(),
+        };
+        self.open_groups.push(OpenGroup {
+            style,
+            group_id,
+            runs: vec![group_id],
+            first_shape: self.shapes.len(),
+        });
+    }
+
+    /// Ends the innermost group
     pub fn end_group(&mut self) {
-        self.in_group = false;
+        let Some(group) = self.open_groups.pop() else {
+            return;
+        };
+        let nested = !self.open_groups.is_empty();
+        if nested && group.style.is_none() {
+            // The enclosing group continues where the nested group left off
+            let parent = self.open_groups.last_mut().unwrap();
+            parent
+                .runs
+                .extend(group.runs.iter().skip_while(|&&id| id == parent.group_id));
+            parent.group_id = group.group_id;
+            return;
+        }
+        if nested {
+            // The enclosing group continues with a new run of shapes, drawn on top of the styled group
+            let resume_id = self.new_group_id();
+            let parent = self.open_groups.last_mut().unwrap();
+            parent.runs.push(resume_id);
+            parent.group_id = resume_id;
+        }
+
+        let shapes = &mut self.shapes[group.first_shape..];
+        let style = match group.style {
+            Some(style) => style,
+            // Runs split up by styled groups are drawn separately, so give them all the style of the last shape
+            None if group.runs.len() > 1 => {
+                match shapes
+                    .iter()
+                    .rfind(|shape| group.runs.contains(&shape.group_id))
+                {
+                    Some(last) => GroupStyle::from(last),
+                    None => return,
+                }
+            }
+            None => return,
+        };
+        for shape in shapes
+            .iter_mut()
+            .filter(|shape| group.runs.contains(&shape.group_id))
+        {
+            shape.with_style(&style);
+        }
+    }
+
+    fn end_open_groups(&mut self) {
+        while !self.open_groups.is_empty() {
+            self.end_group();
+        }
+    }
+
+    fn new_group_id(&mut self) -> u32 {
         self.next_group_id += 1;
+        self.next_group_id - 1
+    }
+
+    /// Sets the layer of shapes added after this call, until the end of the frame. See [`Shape::layer`].
+    ///
+    /// Useful for overlays like popups and tooltips, which can be submitted from anywhere but need to be drawn on top.
+    pub fn set_layer(&mut self, layer: i32) {
+        self.layer = layer;
+    }
+
+    /// The layer shapes are currently added to
+    pub fn layer(&self) -> i32 {
+        self.layer
+    }
+
+    /// Sets the scale applied to all shapes when the frame is finished, eg. the display's scale factor to paint in
+    /// logical rather than physical pixels. Defaults to 1.
+    ///
+    /// Shapes are added and accessed through [`Painter::shape`] unscaled, while finished frames, and with them
+    /// [`Painter::hit_test`] and [`Painter::distance_to`], are in scaled pixels.
+    pub fn set_scale(&mut self, scale: f32) {
+        self.scale = scale;
+    }
+
+    pub fn scale(&self) -> f32 {
+        self.scale
+    }
+
+    /// Sets how shapes are assigned to tiles when binning. Defaults to [`BinningMode::Precise`].
+    pub fn set_binning_mode(&mut self, mode: BinningMode) {
+        self.binner.mode = mode;
+    }
+
+    /// Returns the topmost shape covering `point` in the most recently finished frame.
+    ///
+    /// Shapes are evaluated like the shader does, including groups, distance offsets and outlines, but without
+    /// antialiasing. Shapes with SDF textures are tested against their primitive. Within a group, the shape closest to
+    /// the point is returned.
+    pub fn hit_test(&self, point: impl Into<Vec2>) -> Option<ShapeHandle> {
+        hit_test::hit_test(&self.finished_shapes, &self.finished_binner, point.into())
+            .map(|position| self.finished_handle(&self.finished_shapes, position))
+    }
+
+    /// Signed distance from `point` to the group of `shape` in the most recently finished frame, negative inside.
+    ///
+    /// Useful for hover effects that start before the pointer reaches the shape. Returns `None` if the handle doesn't
+    /// refer to a shape of that frame.
+    pub fn distance_to(&self, shape: ShapeHandle, point: impl Into<Vec2>) -> Option<f32> {
+        let position = self.finished_position(shape)?;
+        hit_test::distance_to(&self.finished_shapes, position, point.into())
+    }
+
+    /// A shape of the most recently finished frame, or `None` if the handle is from another frame
+    pub fn finished_shape(&self, shape: ShapeHandle) -> Option<&Shape> {
+        self.finished_shapes.get(self.finished_position(shape)?)
+    }
+
+    /// Position of a shape of the most recently finished frame in draw order, or `None` if the handle is from another
+    /// frame
+    pub(crate) fn finished_position(&self, shape: ShapeHandle) -> Option<usize> {
+        if shape.generation != self.finished_generation {
+            return None;
+        }
+        if self.finished_positions.is_empty() {
+            return Some(shape.index());
+        }
+        self.finished_positions
+            .get(shape.index())
+            .map(|&position| position as usize)
+    }
+
+    /// Handle of the shape at `position` in draw order, in the given shapes of the most recently finished frame
+    pub(crate) fn finished_handle(&self, shapes: &[Shape], position: usize) -> ShapeHandle {
+        let index = self
+            .finished_order
+            .get(position)
+            .map_or(position as u32, |&index| index);
+        ShapeHandle {
+            index,
+            generation: self.finished_generation,
+            tag: shapes.get(position).and_then(|shape| shape.tag),
+        }
+    }
+
+    /// Handle of the most recently added shape of the current frame.
+    ///
+    /// The handle can be used to look up or modify the shape with [`Painter::shape`] and [`Painter::shape_mut`] until
+    /// the frame is finished, and with [`Painter::finished_shape`] afterwards.
+    pub fn last_handle(&self) -> Option<ShapeHandle> {
+        let shape = self.shapes.last()?;
+        Some(ShapeHandle {
+            index: self.shapes.len() as u32 - 1,
+            generation: self.generation,
+            tag: shape.tag,
+        })
+    }
+
+    /// A shape of the current frame, or `None` if the handle is from another frame
+    pub fn shape(&self, shape: ShapeHandle) -> Option<&Shape> {
+        if shape.generation != self.generation {
+            return None;
+        }
+        self.shapes.get(shape.index())
+    }
+
+    /// Modifies a shape of the current frame. Returns `None` if the handle is from another frame.
+    pub fn shape_mut(&mut self, shape: ShapeHandle) -> Option<&mut Shape> {
+        if shape.generation != self.generation {
+            return None;
+        }
+        self.shapes.get_mut(shape.index())
     }
 }
 
+/// A group that has been begun but not ended yet
+struct OpenGroup {
+    style: Option<GroupStyle>,
+    /// Group ID of shapes added to the group now
+    group_id: u32,
+    /// Group IDs of the group's runs of shapes, which are split up by nested styled groups
+    runs: Vec<u32>,
+    first_shape: usize,
+}
+
 impl Default for Painter {
     fn default() -> Self {
         Self::new()
     }
 }
 
-// Shape helper methods
+/// Shape helper methods.
+///
+/// Each adds a single shape like [`Painter::add_shape`] and returns it to be styled further. Its handle is available
+/// from [`Painter::last_handle`] until the next shape is added.
 impl Painter {
+    /// Adds a filled primitive in the current layer
     pub fn add_primitive(&mut self, primitive: Primitive, color: Vec4) -> &mut Shape {
         let shape = Shape {
             primitive,
@@ -99,8 +442,11 @@ impl Painter {
             line_width: 0.0,
             group_id: 0,
             texture_id: None,
+            texture_mapping: Default::default(),
             flags: Default::default(),
             glow: Vec4::ZERO,
+            layer: self.layer,
+            tag: None,
         };
         self.add_shape(shape)
     }
@@ -163,7 +509,6 @@ impl Painter {
     ) -> &mut Shape {
         let min = min.into();
         let max = max.into();
-        assert!(min.x <= max.x && min.y <= max.y, "Invalid rectangle bounds");
         let center = (min + max) * 0.5;
         let half_extents = (max - min) * 0.5;
         let corner_radius = corner_radius.into();
@@ -364,4 +709,413 @@ impl Painter {
         let color = color.into();
         self.add_primitive(Primitive::PolyQuad { points }, color)
     }
+
+    /// Draws text with its top-left corner at `position`, as one textured rect per glyph.
+    ///
+    /// Every glyph is drawn as its own styled group, so inside of a group the text is drawn on top of the group's
+    /// shapes added before it. Returns the layout, eg. to get the size of the text. [`Painter::last_handle`] returns
+    /// the handle of the last glyph.
+    pub fn add_text(
+        &mut self,
+        font: &FontAtlas,
+        position: impl Into<Vec2>,
+        text: &str,
+        style: &TextStyle,
+    ) -> TextLayout {
+        let position = position.into();
+        let layout = font.layout(text, style);
+        let mut glyph_style = GroupStyle {
+            glow: style.glow,
+            line_width: style.line_width,
+            flags: font.atlas.kind.shape_flags(),
+            ..GroupStyle::new(style.color)
+        };
+        for glyph in &layout.glyphs {
+            glyph_style = glyph_style.with_texture(
+                font.texture_id,
+                TextureMapping {
+                    uv_min: glyph.uv_min,
+                    uv_max: glyph.uv_max,
+                    sdf_range: font.atlas.distance_range,
+                    ..Default::default()
+                },
+            );
+            self.begin_group_with_style(glyph_style);
+            self.add_filled_rect(position + glyph.min, position + glyph.max, 0.0, style.color)
+                .with_distance_offset(style.distance_offset);
+            self.end_group();
+        }
+        layout
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Runs a frame and returns the finished shapes' tags and group IDs, in draw order
+    fn paint(f: impl FnOnce(&mut Painter)) -> Vec<(u64, u32)> {
+        let mut painter = Painter::new();
+        painter.start((64, 64));
+        f(&mut painter);
+        let mut shapes = Vec::new();
+        painter.finish(|finished, _| {
+            shapes = finished
+                .iter()
+                .map(|shape| (shape.tag.unwrap(), shape.group_id))
+                .collect();
+        });
+        shapes
+    }
+
+    fn add(painter: &mut Painter, tag: u64) -> &mut Shape {
+        painter
+            .add_filled_circle((16.0, 16.0), 8.0, Vec4::ONE)
+            .with_tag(tag)
+    }
+
+    #[test]
+    fn ungrouped_shapes_are_separate_groups() {
+        let shapes = paint(|painter| {
+            add(painter, 0);
+            add(painter, 1);
+        });
+        assert_ne!(shapes[0].1, shapes[1].1);
+    }
+
+    #[test]
+    fn nested_unstyled_groups_are_flattened() {
+        let shapes = paint(|painter| {
+            painter.begin_group();
+            add(painter, 0);
+            painter.begin_group();
+            add(painter, 1);
+            painter.end_group();
+            add(painter, 2);
+            painter.end_group();
+            add(painter, 3);
+        });
+        let group = shapes[0].1;
+        assert_eq!(shapes[1].1, group);
+        assert_eq!(shapes[2].1, group);
+        assert_ne!(shapes[3].1, group);
+    }
+
+    #[test]
+    fn styled_group_splits_the_enclosing_group() {
+        let mut painter = Painter::new();
+        painter.start((64, 64));
+        painter.begin_group();
+        add(&mut painter, 0).color = Vec4::splat(0.5);
+        add(&mut painter, 1);
+        painter.begin_group_with_style(GroupStyle::new(Vec4::new(1.0, 0.0, 0.0, 1.0)));
+        add(&mut painter, 2);
+        add(&mut painter, 3);
+        painter.end_group();
+        add(&mut painter, 4).color = Vec4::new(0.0, 0.0, 1.0, 1.0);
+        painter.end_group();
+
+        painter.finish(|shapes, _| {
+            // Parent, styled child on top of it, then the parent resumed on top of the child
+            let tags: Vec<_> = shapes.iter().map(|shape| shape.tag.unwrap()).collect();
+            assert_eq!(tags, [0, 1, 2, 3, 4]);
+            let groups: Vec<_> = shape_groups(shapes).collect();
+            assert_eq!(groups, [0..2, 2..4, 4..5]);
+
+            // The child has its own style, both runs of the parent the style of the parent's last shape
+            for shape in &shapes[2..4] {
+                assert_eq!(shape.color, Vec4::new(1.0, 0.0, 0.0, 1.0));
+            }
+            for shape in shapes[0..2].iter().chain(&shapes[4..]) {
+                assert_eq!(shape.color, Vec4::new(0.0, 0.0, 1.0, 1.0));
+            }
+        });
+    }
+
+    #[test]
+    fn nested_styled_groups_resume_their_parents() {
+        let shapes = paint(|painter| {
+            painter.begin_group();
+            add(painter, 0);
+            painter.begin_group_with_style(GroupStyle::new(Vec4::ONE));
+            add(painter, 1);
+            painter.begin_group_with_style(GroupStyle::new(Vec4::ONE));
+            add(painter, 2);
+            painter.end_group();
+            add(painter, 3);
+            painter.end_group();
+            add(painter, 4);
+            painter.end_group();
+        });
+        let tags: Vec<_> = shapes.iter().map(|&(tag, _)| tag).collect();
+        assert_eq!(tags, [0, 1, 2, 3, 4]);
+        let mut groups: Vec<_> = shapes.iter().map(|&(_, group)| group).collect();
+        groups.dedup();
+        assert_eq!(groups.len(), 5);
+    }
+
+    #[test]
+    fn layers_keep_groups_together() {
+        let shapes = paint(|painter| {
+            painter.set_layer(1);
+            painter.begin_group();
+            add(painter, 0);
+            add(painter, 1);
+            painter.end_group();
+
+            painter.set_layer(0);
+            add(painter, 2);
+
+            // A group is drawn in the layer of its last shape
+            painter.begin_group();
+            add(painter, 3);
+            painter.set_layer(-1);
+            add(painter, 4);
+            painter.end_group();
+
+            painter.set_layer(1);
+            add(painter, 5);
+        });
+        let tags: Vec<_> = shapes.iter().map(|&(tag, _)| tag).collect();
+        assert_eq!(tags, [3, 4, 2, 0, 1, 5]);
+        assert_eq!(shapes[0].1, shapes[1].1);
+        assert_eq!(shapes[3].1, shapes[4].1);
+        assert_ne!(shapes[4].1, shapes[5].1);
+    }
+
+    #[test]
+    fn layers_keep_split_group_runs_in_order() {
+        let shapes = paint(|painter| {
+            painter.set_layer(1);
+            painter.begin_group();
+            add(painter, 0);
+            painter.begin_group_with_style(GroupStyle::new(Vec4::ONE));
+            add(painter, 1);
+            painter.end_group();
+            add(painter, 2);
+            painter.end_group();
+
+            painter.set_layer(0);
+            add(painter, 3);
+        });
+        let tags: Vec<_> = shapes.iter().map(|&(tag, _)| tag).collect();
+        assert_eq!(tags, [3, 0, 1, 2]);
+    }
+
+    #[test]
+    fn scales_finished_shapes() {
+        let mut painter = Painter::new();
+        painter.set_scale(2.0);
+        painter.start((200, 200));
+        painter
+            .add_circle((20.0, 30.0), 10.0, Vec4::ONE, 2.0)
+            .with_tag(0);
+        let handle = painter.last_handle().unwrap();
+        assert_eq!(
+            painter.shape(handle).unwrap().primitive,
+            Primitive::Circle {
+                center: Vec2::new(20.0, 30.0),
+                radius: 10.0
+            }
+        );
+        painter.finish(|_, _| {});
+
+        let shape = painter.finished_shape(handle).unwrap();
+        assert_eq!(
+            shape.primitive,
+            Primitive::Circle {
+                center: Vec2::new(40.0, 60.0),
+                radius: 20.0
+            }
+        );
+        assert_eq!(shape.line_width, 4.0);
+        // Hit tests use the scaled frame
+        assert_eq!(painter.hit_test((60.0, 60.0)), Some(handle));
+        assert_eq!(painter.hit_test((30.0, 30.0)), None);
+    }
+
+    #[test]
+    fn looks_up_shapes_by_handle() {
+        let mut painter = Painter::new();
+        painter.start((64, 64));
+        assert_eq!(painter.last_handle(), None);
+        add(&mut painter, 0);
+        let first = painter.last_handle().unwrap();
+        add(&mut painter, 1);
+        let second = painter.last_handle().unwrap();
+        assert_eq!((first.index(), second.index()), (0, 1));
+        assert_eq!(first.generation(), second.generation());
+        assert_eq!((first.tag(), second.tag()), (Some(0), Some(1)));
+        assert_eq!(painter.shape(first).unwrap().tag, Some(0));
+        assert_eq!(painter.shape(second).unwrap().tag, Some(1));
+
+        painter.shape_mut(first).unwrap().with_line_width(3.0);
+        painter.finish(|_, _| {});
+        assert_eq!(painter.finished_shape(first).unwrap().line_width, 3.0);
+        assert_eq!(painter.finished_shape(second).unwrap().line_width, 0.0);
+    }
+
+    #[test]
+    fn rejects_handles_of_other_frames() {
+        let mut painter = Painter::new();
+        painter.start((64, 64));
+        add(&mut painter, 0);
+        let handle = painter.last_handle().unwrap();
+        painter.finish(|_, _| {});
+
+        painter.start((64, 64));
+        add(&mut painter, 1);
+        let next = painter.last_handle().unwrap();
+        // The handle refers to the finished frame now, not to the shape at the same index of the current one
+        assert_eq!(next.index(), handle.index());
+        assert_ne!(next.generation(), handle.generation());
+        assert_eq!(painter.shape(handle), None);
+        assert_eq!(painter.shape_mut(handle), None);
+        assert_eq!(painter.finished_shape(handle).unwrap().tag, Some(0));
+        assert_eq!(painter.finished_shape(next), None);
+
+        painter.finish(|_, _| {});
+        assert_eq!(painter.finished_shape(handle), None);
+        assert_eq!(painter.distance_to(handle, (16.0, 16.0)), None);
+        assert_eq!(painter.finished_shape(next).unwrap().tag, Some(1));
+    }
+
+    #[test]
+    fn handles_survive_layer_sorting() {
+        let mut painter = Painter::new();
+        painter.start((64, 64));
+        painter.set_layer(1);
+        add(&mut painter, 0);
+        let top = painter.last_handle().unwrap();
+        painter.set_layer(0);
+        add(&mut painter, 1);
+        let bottom = painter.last_handle().unwrap();
+        let mut drawn = Vec::new();
+        painter.finish(|shapes, _| drawn = shapes.iter().map(|shape| shape.tag).collect());
+
+        // The first shape is drawn last, but keeps its submission index
+        assert_eq!(drawn, [Some(1), Some(0)]);
+        assert_eq!(painter.finished_position(top), Some(1));
+        assert_eq!(painter.finished_position(bottom), Some(0));
+        assert_eq!(painter.finished_shape(top).unwrap().tag, Some(0));
+        assert_eq!(painter.finished_shape(bottom).unwrap().tag, Some(1));
+        // Both shapes cover the point, the one in the higher layer is on top
+        assert_eq!(painter.hit_test((16.0, 16.0)), Some(top));
+    }
+
+    #[test]
+    fn validates_shape_geometry() {
+        use crate::error::GeometryError::{NegativeSize, NonFinite};
+
+        let validate = |add: fn(&mut Painter)| {
+            let mut painter = Painter::new();
+            painter.start((64, 64));
+            add(&mut painter);
+            painter.shapes[0].validate()
+        };
+        assert_eq!(
+            validate(|p| {
+                add(p, 0);
+            }),
+            Ok(())
+        );
+        assert_eq!(
+            validate(|p| {
+                p.add_filled_circle((f32::NAN, 16.0), 8.0, Vec4::ONE);
+            }),
+            Err(NonFinite)
+        );
+        assert_eq!(
+            validate(|p| {
+                p.add_filled_circle((16.0, 16.0), f32::INFINITY, Vec4::ONE);
+            }),
+            Err(NonFinite)
+        );
+        assert_eq!(
+            validate(|p| {
+                p.add_filled_circle((16.0, 16.0), 8.0, Vec4::splat(f32::NAN));
+            }),
+            Err(NonFinite)
+        );
+        assert_eq!(
+            validate(|p| {
+                p.add_filled_circle((16.0, 16.0), -1.0, Vec4::ONE);
+            }),
+            Err(NegativeSize)
+        );
+        assert_eq!(
+            validate(|p| {
+                add(p, 0).with_line_width(-1.0);
+            }),
+            Err(NegativeSize)
+        );
+        assert_eq!(
+            validate(|p| {
+                p.add_filled_rect_center_size((16.0, 16.0), (-1.0, 4.0), 0.0, Vec4::ONE);
+            }),
+            Err(NegativeSize)
+        );
+        assert_eq!(
+            validate(|p| {
+                p.add_filled_rect((4.0, 4.0), (8.0, 8.0), -1.0, Vec4::ONE);
+            }),
+            Err(NegativeSize)
+        );
+        // Inverted rects have negative half extents
+        assert_eq!(
+            validate(|p| {
+                p.add_filled_rect((20.0, 4.0), (10.0, 8.0), 0.0, Vec4::ONE);
+            }),
+            Err(NegativeSize)
+        );
+        assert_eq!(
+            validate(|p| {
+                p.add_filled_rect((4.0, 4.0), (8.0, f32::NEG_INFINITY), 0.0, Vec4::ONE);
+            }),
+            Err(NonFinite)
+        );
+    }
+
+    #[test]
+    fn replaces_invalid_shapes_and_warns() {
+        let warnings = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let mut painter = Painter::new();
+        let handler_warnings = warnings.clone();
+        painter.set_warning_handler(move |warning| {
+            handler_warnings.lock().unwrap().push(warning.clone())
+        });
+
+        painter.start((64, 64));
+        add(&mut painter, 0);
+        painter
+            .add_filled_circle((16.0, 16.0), -8.0, Vec4::ONE)
+            .with_tag(1);
+        add(&mut painter, 2).with_distance_offset(f32::NAN);
+        let mut finished = Vec::new();
+        painter.finish(|shapes, _| finished = shapes.to_vec());
+
+        assert_eq!(
+            *warnings.lock().unwrap(),
+            [
+                Error::InvalidGeometry {
+                    shape_index: 1,
+                    reason: crate::error::GeometryError::NegativeSize,
+                },
+                Error::InvalidGeometry {
+                    shape_index: 2,
+                    reason: crate::error::GeometryError::NonFinite,
+                },
+            ]
+        );
+        // Invalid shapes are drawn as empty shapes, keeping their tags
+        assert_eq!(finished[0].primitive.validate(), Ok(()));
+        assert_ne!(finished[0].color, Vec4::ZERO);
+        for shape in &finished[1..] {
+            assert_eq!(shape.validate(), Ok(()));
+            assert_eq!(shape.color, Vec4::ZERO);
+            assert!(shape.bounds().max.cmple(Vec2::ZERO).all());
+        }
+        assert_eq!(finished[1].tag, Some(1));
+        assert_eq!(finished[2].tag, Some(2));
+    }
 }
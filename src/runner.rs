@@ -0,0 +1,395 @@
+//! A window and event loop running an [`App`], with the `runner` feature.
+//!
+//! [`run`] opens a window, sets up the GPU device, surface and renderer, and paints a frame with the app whenever the
+//! window is redrawn. The surface follows the window's size, and window events are forwarded to the app.
+//!
+//! Shapes are painted in physical pixels by default. With [`RunnerOptions::logical_pixels`], they are painted in
+//! logical pixels instead, and the runner scales them by [`Context::scale_factor`] through [`Painter::set_scale`], so
+//! they keep their size on displays of any DPI. When the scale factor changes, eg. after moving the window to another
+//! display, the surface follows the window's new physical size and a frame is drawn with the new scale factor.
+
+use std::sync::Arc;
+
+pub use winit;
+use winit::{
+    application::ApplicationHandler,
+    dpi::LogicalSize,
+    event::WindowEvent,
+    event_loop::{ActiveEventLoop, ControlFlow, EventLoop},
+    window::{Window, WindowId},
+};
+
+use crate::{
+    Painter,
+    backend::wgpu::{RendererFeatures, WgpuRenderer},
+    error::{Error, RunnerError},
+};
+
+/// An application run by [`run`]
+pub trait App {
+    /// Called once the window and GPU device are created, eg. to load textures
+    fn init(&mut self, context: &mut Context) {
+        let _ = context;
+    }
+
+    /// Called for every window event, after the runner handled it. The runner resizes the surface, redraws after scale
+    /// factor changes and exits when the window is closed. Event positions are in physical pixels, like
+    /// [`Painter::hit_test`].
+    fn window_event(&mut self, context: &mut Context, event: &WindowEvent) {
+        let _ = (context, event);
+    }
+
+    /// Paints a frame. The painter is already started at [`Context::resolution`], and is finished and rendered by the
+    /// runner afterwards. With [`RunnerOptions::logical_pixels`], the frame spans [`Context::logical_size`] instead.
+    fn draw(&mut self, painter: &mut Painter, context: &mut Context);
+}
+
+/// Window and rendering settings of [`run`]
+#[derive(Clone, Debug, PartialEq)]
+pub struct RunnerOptions {
+    pub title: String,
+    /// Initial size of the window, in logical pixels
+    pub size: (u32, u32),
+    /// Falls back to the surface's default present mode when not supported
+    pub present_mode: wgpu::PresentMode,
+    /// Color the surface is cleared to before rendering the shapes
+    pub clear_color: wgpu::Color,
+    /// Redraw continuously, eg. for animations. Otherwise, frames are only drawn when the window needs to be redrawn,
+    /// or after [`Context::request_redraw`].
+    pub continuous: bool,
+    /// Paint in logical pixels, scaling the shapes by the window's scale factor
+    pub logical_pixels: bool,
+}
+
+impl Default for RunnerOptions {
+    fn default() -> Self {
+        Self {
+            title: "mondrian".to_string(),
+            size: (1200, 900),
+            present_mode: wgpu::PresentMode::Fifo,
+            clear_color: wgpu::Color::BLACK,
+            continuous: true,
+            logical_pixels: false,
+        }
+    }
+}
+
+impl RunnerOptions {
+    pub fn with_title(mut self, title: impl Into<String>) -> Self {
+        self.title = title.into();
+        self
+    }
+
+    pub fn with_size(mut self, width: u32, height: u32) -> Self {
+        self.size = (width, height);
+        self
+    }
+
+    pub fn with_present_mode(mut self, present_mode: wgpu::PresentMode) -> Self {
+        self.present_mode = present_mode;
+        self
+    }
+
+    pub fn with_clear_color(mut self, clear_color: wgpu::Color) -> Self {
+        self.clear_color = clear_color;
+        self
+    }
+
+    pub fn with_continuous(mut self, continuous: bool) -> Self {
+        self.continuous = continuous;
+        self
+    }
+
+    pub fn with_logical_pixels(mut self, logical_pixels: bool) -> Self {
+        self.logical_pixels = logical_pixels;
+        self
+    }
+}
+
+/// The window and GPU resources of a running app
+pub struct Context<'a> {
+    pub window: &'a Window,
+    pub device: &'a wgpu::Device,
+    pub queue: &'a wgpu::Queue,
+    pub renderer: &'a mut WgpuRenderer,
+    resolution: (u32, u32),
+    exit: &'a mut bool,
+}
+
+impl Context<'_> {
+    /// Size of the surface, in physical pixels
+    pub fn resolution(&self) -> (u32, u32) {
+        self.resolution
+    }
+
+    /// Size of the surface, in logical pixels
+    pub fn logical_size(&self) -> (f32, f32) {
+        let scale = self.scale_factor();
+        (
+            self.resolution.0 as f32 / scale,
+            self.resolution.1 as f32 / scale,
+        )
+    }
+
+    /// Number of physical pixels per logical pixel
+    pub fn scale_factor(&self) -> f32 {
+        self.window.scale_factor() as f32
+    }
+
+    /// Draws another frame, when not redrawing continuously
+    pub fn request_redraw(&self) {
+        self.window.request_redraw();
+    }
+
+    /// Closes the window and returns from [`run`] after the current event
+    pub fn exit(&mut self) {
+        *self.exit = true;
+    }
+}
+
+/// Opens a window and runs the app until the window is closed or the app exits.
+///
+/// The surface uses a non-sRGB format where available, so colors are written to the screen as they are.
+pub fn run(options: RunnerOptions, app: impl App) -> Result<(), Error> {
+    let event_loop =
+        EventLoop::new().map_err(|e| Error::Runner(RunnerError::EventLoop(e.to_string())))?;
+    event_loop.set_control_flow(if options.continuous {
+        ControlFlow::Poll
+    } else {
+        ControlFlow::Wait
+    });
+    let mut runner = Runner {
+        options,
+        app,
+        painter: Painter::new(),
+        gpu: None,
+        result: Ok(()),
+    };
+    event_loop
+        .run_app(&mut runner)
+        .map_err(|e| Error::Runner(RunnerError::EventLoop(e.to_string())))?;
+    runner.result
+}
+
+struct Runner<A> {
+    options: RunnerOptions,
+    app: A,
+    painter: Painter,
+    gpu: Option<Gpu>,
+    /// The first error that stopped the event loop
+    result: Result<(), Error>,
+}
+
+/// Everything created once the event loop is running
+struct Gpu {
+    window: Arc<Window>,
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    surface: wgpu::Surface<'static>,
+    surface_config: wgpu::SurfaceConfiguration,
+    renderer: WgpuRenderer,
+}
+
+impl Gpu {
+    fn new(event_loop: &ActiveEventLoop, options: &RunnerOptions) -> Result<Self, Error> {
+        let (width, height) = options.size;
+        let window = event_loop
+            .create_window(
+                Window::default_attributes()
+                    .with_title(&options.title)
+                    .with_inner_size(LogicalSize::new(width, height)),
+            )
+            .map_err(|e| Error::Runner(RunnerError::Window(e.to_string())))?;
+        let window = Arc::new(window);
+
+        let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor::from_env_or_default());
+        let surface = instance
+            .create_surface(window.clone())
+            .map_err(|e| Error::Runner(RunnerError::CreateSurface(e.to_string())))?;
+        let adapter = pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions {
+            power_preference: wgpu::PowerPreference::HighPerformance,
+            compatible_surface: Some(&surface),
+            force_fallback_adapter: false,
+        }))
+        .map_err(|e| Error::Runner(RunnerError::Adapter(e)))?;
+
+        // Only request the optional features the adapter supports, the renderer falls back to its compatibility path otherwise
+        let features = adapter.features() & RendererFeatures::OPTIONAL_FEATURES;
+        let renderer_features = RendererFeatures::from_features(features, &adapter.limits());
+        let (device, queue) = pollster::block_on(adapter.request_device(&wgpu::DeviceDescriptor {
+            required_features: features,
+            required_limits: renderer_features.required_limits(adapter.limits()),
+            ..Default::default()
+        }))
+        .map_err(|e| Error::Runner(RunnerError::Device(e.to_string())))?;
+
+        let size = window.inner_size();
+        let mut surface_config = surface
+            .get_default_config(&adapter, size.width.max(1), size.height.max(1))
+            .ok_or(Error::Runner(RunnerError::UnsupportedSurface))?;
+        let capabilities = surface.get_capabilities(&adapter);
+        if let Some(&format) = capabilities.formats.iter().find(|format| !format.is_srgb()) {
+            surface_config.format = format;
+        }
+        if capabilities.present_modes.contains(&options.present_mode) {
+            surface_config.present_mode = options.present_mode;
+        } else {
+            log::warn!(
+                "Present mode {:?} is not supported, using {:?}",
+                options.present_mode,
+                surface_config.present_mode
+            );
+        }
+        surface.configure(&device, &surface_config);
+
+        let renderer =
+            WgpuRenderer::with_features(&device, surface_config.format, renderer_features);
+        Ok(Self {
+            window,
+            device,
+            queue,
+            surface,
+            surface_config,
+            renderer,
+        })
+    }
+
+    fn resize(&mut self, width: u32, height: u32) {
+        if width == 0 || height == 0 {
+            return;
+        }
+        self.surface_config.width = width;
+        self.surface_config.height = height;
+        self.surface.configure(&self.device, &self.surface_config);
+    }
+
+    fn context<'a>(&'a mut self, exit: &'a mut bool) -> Context<'a> {
+        Context {
+            window: &self.window,
+            device: &self.device,
+            queue: &self.queue,
+            renderer: &mut self.renderer,
+            resolution: (self.surface_config.width, self.surface_config.height),
+            exit,
+        }
+    }
+}
+
+impl<A: App> Runner<A> {
+    fn draw(&mut self, gpu: &mut Gpu, exit: &mut bool) -> Result<(), Error> {
+        // Minimized windows have no size, and can't be drawn to
+        let size = gpu.window.inner_size();
+        if size.width == 0 || size.height == 0 {
+            return Ok(());
+        }
+
+        let frame = match gpu.surface.get_current_texture() {
+            Ok(frame) => frame,
+            // The surface changed since it was configured, try again with the next frame
+            Err(wgpu::SurfaceError::Lost | wgpu::SurfaceError::Outdated) => {
+                gpu.surface.configure(&gpu.device, &gpu.surface_config);
+                gpu.window.request_redraw();
+                return Ok(());
+            }
+            Err(wgpu::SurfaceError::Timeout) => return Ok(()),
+            Err(e) => return Err(Error::Runner(RunnerError::SurfaceTexture(e))),
+        };
+        let view = frame
+            .texture
+            .create_view(&wgpu::TextureViewDescriptor::default());
+
+        let mut context = gpu.context(exit);
+        if self.options.logical_pixels {
+            self.painter.set_scale(context.scale_factor());
+        }
+        self.painter.try_start(context.resolution)?;
+        self.app.draw(&mut self.painter, &mut context);
+
+        let mut encoder = gpu
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        let clear_color = self.options.clear_color;
+        self.painter.try_finish(|shapes, binner| {
+            gpu.renderer
+                .prepare(&gpu.device, &gpu.queue, shapes, binner);
+            let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Runner Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(clear_color),
+                        store: wgpu::StoreOp::Store,
+                    },
+                    depth_slice: None,
+                })],
+                depth_stencil_attachment: None,
+                occlusion_query_set: None,
+                timestamp_writes: None,
+            });
+            gpu.renderer.render(&mut pass);
+        })?;
+
+        gpu.queue.submit(Some(encoder.finish()));
+        gpu.window.pre_present_notify();
+        frame.present();
+        Ok(())
+    }
+}
+
+impl<A: App> ApplicationHandler for Runner<A> {
+    fn resumed(&mut self, event_loop: &ActiveEventLoop) {
+        if self.gpu.is_some() {
+            return;
+        }
+        let mut gpu = match Gpu::new(event_loop, &self.options) {
+            Ok(gpu) => gpu,
+            Err(e) => {
+                self.result = Err(e);
+                event_loop.exit();
+                return;
+            }
+        };
+        let mut exit = false;
+        self.app.init(&mut gpu.context(&mut exit));
+        if exit {
+            event_loop.exit();
+        }
+        gpu.window.request_redraw();
+        self.gpu = Some(gpu);
+    }
+
+    fn window_event(
+        &mut self,
+        event_loop: &ActiveEventLoop,
+        _window_id: WindowId,
+        event: WindowEvent,
+    ) {
+        let Some(mut gpu) = self.gpu.take() else {
+            return;
+        };
+        let mut exit = false;
+        match &event {
+            WindowEvent::CloseRequested => exit = true,
+            WindowEvent::Resized(size) => gpu.resize(size.width, size.height),
+            // winit resizes the window to its new physical size right after, and the surface follows with the
+            // `Resized` event. Apps scaling their shapes need a new frame even when not redrawing continuously.
+            WindowEvent::ScaleFactorChanged { .. } => gpu.window.request_redraw(),
+            WindowEvent::RedrawRequested => {
+                if let Err(e) = self.draw(&mut gpu, &mut exit) {
+                    self.result = Err(e);
+                    exit = true;
+                }
+                if self.options.continuous {
+                    gpu.window.request_redraw();
+                }
+            }
+            _ => {}
+        }
+        self.app.window_event(&mut gpu.context(&mut exit), &event);
+        self.gpu = Some(gpu);
+        if exit {
+            event_loop.exit();
+        }
+    }
+}
@@ -0,0 +1,437 @@
+use std::ops::Range;
+
+use glam::Vec2;
+
+use crate::{
+    Error, Painter, Shape, ShapeHandle,
+    binner::{ShapeBinner, TileRect, shape_groups},
+    hit_test,
+};
+
+/// Retained-mode layer on top of [`Painter`].
+///
+/// Shapes are still submitted immediate-mode style every frame, but the scene diffs them against the previous frame.
+/// Only tiles touched by changed shape groups are re-binned, and the resulting [`SceneDamage`] describes which parts of
+/// the GPU buffers need to be re-uploaded and which regions of the screen changed.
+pub struct RetainedScene {
+    painter: Painter,
+    binner: ShapeBinner,
+
+    shapes: Vec<Shape>,
+    previous_shapes: Vec<Shape>,
+    previous_tile_ranges: Vec<u32>,
+    previous_shape_indices: Vec<u32>,
+
+    /// Resolution of the previous frame, `None` until the first frame has been finished
+    previous_resolution: Option<(u32, u32)>,
+    resolution: (u32, u32),
+
+    changed_shapes: Vec<bool>,
+    dirty_tiles: Vec<bool>,
+    damage: SceneDamage,
+}
+
+/// Describes what changed in a [`RetainedScene`] since the previous frame
+#[derive(Clone, Debug, Default)]
+pub struct SceneDamage {
+    /// Set when the scene was rebuilt from scratch (first frame, or the resolution changed).
+    /// All buffers must be fully uploaded and the whole screen redrawn.
+    pub full: bool,
+    /// Range of shapes that have been added or modified
+    pub shapes: Range<usize>,
+    /// Range of `ShapeBinner::tile_ranges` that changed
+    pub tile_ranges: Range<usize>,
+    /// Range of `ShapeBinner::shape_indices` that changed
+    pub shape_indices: Range<usize>,
+    /// Regions of the screen whose contents changed, in tiles
+    pub dirty_rects: Vec<TileRect>,
+}
+
+impl SceneDamage {
+    /// Returns true if nothing visible changed, in which case the host can skip rendering and presenting the frame.
+    pub fn is_empty(&self) -> bool {
+        !self.full && self.dirty_rects.is_empty()
+    }
+}
+
+impl RetainedScene {
+    pub fn new() -> Self {
+        Self {
+            painter: Painter::new(),
+            binner: ShapeBinner::new(32, (0, 0)),
+
+            shapes: Vec::new(),
+            previous_shapes: Vec::new(),
+            previous_tile_ranges: Vec::new(),
+            previous_shape_indices: Vec::new(),
+
+            previous_resolution: None,
+            resolution: (0, 0),
+
+            changed_shapes: Vec::new(),
+            dirty_tiles: Vec::new(),
+            damage: SceneDamage::default(),
+        }
+    }
+
+    /// Starts a new frame, returning the painter to submit shapes with.
+    ///
+    /// # Panics
+    /// If the previous frame wasn't finished. See [`RetainedScene::try_start`] for a non-panicking version.
+    pub fn start(&mut self, resolution: (u32, u32)) -> &mut Painter {
+        match self.try_start(resolution) {
+            Ok(painter) => painter,
+            Err(e) => panic!("RetainedScene::{e}"),
+        }
+    }
+
+    pub fn try_start(&mut self, resolution: (u32, u32)) -> Result<&mut Painter, Error> {
+        self.painter.try_start(resolution)?;
+        self.resolution = resolution;
+        Ok(&mut self.painter)
+    }
+
+    /// The painter used to submit shapes for the current frame
+    pub fn painter(&mut self) -> &mut Painter {
+        &mut self.painter
+    }
+
+    /// Diffs the frame against the previous one and passes the shapes, binner and damage to `f`.
+    ///
+    /// # Panics
+    /// If the frame wasn't started. See [`RetainedScene::try_finish`] for a non-panicking version.
+    pub fn finish<F: FnOnce(&[Shape], &ShapeBinner, &SceneDamage)>(&mut self, f: F) {
+        if let Err(e) = self.try_finish(f) {
+            panic!("RetainedScene::{e}");
+        }
+    }
+
+    pub fn try_finish<F: FnOnce(&[Shape], &ShapeBinner, &SceneDamage)>(
+        &mut self,
+        f: F,
+    ) -> Result<(), Error> {
+        let mut shapes = std::mem::take(&mut self.previous_shapes);
+        shapes.clear();
+        self.painter
+            .try_finish_unbinned(|frame_shapes| shapes.extend_from_slice(frame_shapes))?;
+        self.previous_shapes = std::mem::replace(&mut self.shapes, shapes);
+
+        self.update_damage();
+        f(&self.shapes, &self.binner, &self.damage);
+        Ok(())
+    }
+
+    /// Damage of the most recently finished frame
+    pub fn damage(&self) -> &SceneDamage {
+        &self.damage
+    }
+
+    /// Returns the topmost shape covering `point` in the most recently finished frame.
+    /// See [`Painter::hit_test`].
+    pub fn hit_test(&self, point: impl Into<Vec2>) -> Option<ShapeHandle> {
+        hit_test::hit_test(&self.shapes, &self.binner, point.into())
+            .map(|position| self.painter.finished_handle(&self.shapes, position))
+    }
+
+    /// Signed distance from `point` to the group of `shape` in the most recently finished frame.
+    /// See [`Painter::distance_to`].
+    pub fn distance_to(&self, shape: ShapeHandle, point: impl Into<Vec2>) -> Option<f32> {
+        let position = self.painter.finished_position(shape)?;
+        hit_test::distance_to(&self.shapes, position, point.into())
+    }
+
+    /// A shape of the most recently finished frame, or `None` if the handle is from another frame
+    pub fn finished_shape(&self, shape: ShapeHandle) -> Option<&Shape> {
+        self.shapes.get(self.painter.finished_position(shape)?)
+    }
+
+    /// Discards the retained state, forcing the next frame to be rebuilt from scratch.
+    pub fn invalidate(&mut self) {
+        self.previous_resolution = None;
+    }
+
+    fn update_damage(&mut self) {
+        self.previous_tile_ranges
+            .clone_from(&self.binner.tile_ranges);
+        self.previous_shape_indices
+            .clone_from(&self.binner.shape_indices);
+
+        let (tiles_x, tiles_y) = self.binner.tile_count();
+        let full = self.previous_resolution != Some(self.resolution);
+        self.previous_resolution = Some(self.resolution);
+        if full {
+            self.binner.update_resolution(self.resolution);
+            self.binner.bin_shapes(&self.shapes);
+
+            let (tiles_x, tiles_y) = self.binner.tile_count();
+            self.damage = SceneDamage {
+                full: true,
+                shapes: 0..self.shapes.len(),
+                tile_ranges: 0..self.binner.tile_ranges.len(),
+                shape_indices: 0..self.binner.shape_indices.len(),
+                dirty_rects: vec![TileRect {
+                    x: 0,
+                    y: 0,
+                    width: tiles_x,
+                    height: tiles_y,
+                }],
+            };
+            return;
+        }
+
+        let num_shapes = self.shapes.len().max(self.previous_shapes.len());
+        self.changed_shapes.clear();
+        self.changed_shapes
+            .extend((0..num_shapes).map(|i| self.shapes.get(i) != self.previous_shapes.get(i)));
+
+        // A change to any shape in a group can affect the whole group, both where it was and where it is now
+        self.dirty_tiles.clear();
+        self.dirty_tiles.resize((tiles_x * tiles_y) as usize, false);
+        for shapes in [&self.previous_shapes, &self.shapes] {
+            for group in shape_groups(shapes) {
+                if self.changed_shapes[group.clone()].contains(&true) {
+                    self.binner.for_each_covered_tile(&shapes[group], |tile| {
+                        self.dirty_tiles[tile] = true
+                    });
+                }
+            }
+        }
+
+        if self.dirty_tiles.contains(&true) {
+            self.binner.rebin_tiles(&self.shapes, &self.dirty_tiles);
+        }
+
+        let first_changed = self.changed_shapes.iter().position(|&c| c);
+        let shapes = match first_changed {
+            Some(start) => {
+                let end = self.changed_shapes.iter().rposition(|&c| c).unwrap() + 1;
+                start.min(self.shapes.len())..end.min(self.shapes.len())
+            }
+            None => 0..0,
+        };
+
+        self.damage = SceneDamage {
+            full: false,
+            shapes,
+            tile_ranges: changed_range(&self.previous_tile_ranges, &self.binner.tile_ranges),
+            shape_indices: changed_range(&self.previous_shape_indices, &self.binner.shape_indices),
+            dirty_rects: merge_dirty_tiles(&self.dirty_tiles, tiles_x),
+        };
+    }
+}
+
+impl Default for RetainedScene {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Returns the range of elements in `new` that differ from `old`
+fn changed_range<T: PartialEq>(old: &[T], new: &[T]) -> Range<usize> {
+    let common = old.len().min(new.len());
+    let Some(start) = (0..common).position(|i| old[i] != new[i]) else {
+        return common..new.len();
+    };
+
+    let end = if new.len() > old.len() {
+        new.len()
+    } else {
+        (start..common).rposition(|i| old[i] != new[i]).unwrap() + start + 1
+    };
+    start..end
+}
+
+/// Merges the dirty tile mask into rectangles, by joining horizontal runs of dirty tiles with identical runs in the row above.
+fn merge_dirty_tiles(dirty_tiles: &[bool], tiles_x: u32) -> Vec<TileRect> {
+    let mut rects: Vec<TileRect> = Vec::new();
+    // Indices into `rects` of the rectangles that ended on the previous row
+    let mut open_rects: Vec<usize> = Vec::new();
+    let mut next_open_rects: Vec<usize> = Vec::new();
+
+    if tiles_x == 0 {
+        return rects;
+    }
+
+    for (y, row) in dirty_tiles.chunks(tiles_x as usize).enumerate() {
+        let y = y as u32;
+        let mut x = 0;
+        while x < row.len() {
+            if !row[x] {
+                x += 1;
+                continue;
+            }
+
+            let start = x;
+            while x < row.len() && row[x] {
+                x += 1;
+            }
+
+            let (start, width) = (start as u32, (x - start) as u32);
+            let continued = open_rects.iter().copied().find(|&i| {
+                let rect = &rects[i];
+                rect.x == start && rect.width == width && rect.y + rect.height == y
+            });
+            match continued {
+                Some(i) => {
+                    rects[i].height += 1;
+                    next_open_rects.push(i);
+                }
+                None => {
+                    rects.push(TileRect {
+                        x: start,
+                        y,
+                        width,
+                        height: 1,
+                    });
+                    next_open_rects.push(rects.len() - 1);
+                }
+            }
+        }
+
+        std::mem::swap(&mut open_rects, &mut next_open_rects);
+        next_open_rects.clear();
+    }
+
+    rects
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RESOLUTION: (u32, u32) = (320, 320);
+
+    /// Finishes a frame of small circles, which each lie inside a single 32px tile
+    fn frame(scene: &mut RetainedScene, resolution: (u32, u32), centers: &[(f32, f32)]) {
+        let painter = scene.start(resolution);
+        for &center in centers {
+            painter.add_filled_circle(center, 6.0, [1.0, 1.0, 1.0, 1.0]);
+        }
+        scene.finish(|_, _, _| {});
+    }
+
+    fn is_dirty(damage: &SceneDamage, (x, y): (u32, u32)) -> bool {
+        damage.dirty_rects.iter().any(|rect| {
+            (rect.x..rect.x + rect.width).contains(&x)
+                && (rect.y..rect.y + rect.height).contains(&y)
+        })
+    }
+
+    #[test]
+    fn first_frame_is_full_damage() {
+        let mut scene = RetainedScene::new();
+        frame(&mut scene, RESOLUTION, &[(16.0, 16.0)]);
+        let damage = scene.damage();
+        assert!(damage.full);
+        assert_eq!(damage.shapes, 0..1);
+        assert_eq!(
+            damage.dirty_rects,
+            [TileRect {
+                x: 0,
+                y: 0,
+                width: 10,
+                height: 10
+            }]
+        );
+    }
+
+    #[test]
+    fn unchanged_scene_has_no_damage() {
+        let mut scene = RetainedScene::new();
+        frame(&mut scene, RESOLUTION, &[(16.0, 16.0), (100.0, 100.0)]);
+        frame(&mut scene, RESOLUTION, &[(16.0, 16.0), (100.0, 100.0)]);
+        let damage = scene.damage();
+        assert!(damage.is_empty());
+        assert!(damage.shapes.is_empty());
+        assert!(damage.tile_ranges.is_empty());
+        assert!(damage.shape_indices.is_empty());
+    }
+
+    #[test]
+    fn moved_shape_damages_old_and_new_tiles() {
+        let mut scene = RetainedScene::new();
+        frame(&mut scene, RESOLUTION, &[(16.0, 16.0), (300.0, 300.0)]);
+        frame(&mut scene, RESOLUTION, &[(208.0, 16.0), (300.0, 300.0)]);
+        let damage = scene.damage();
+        assert!(!damage.full);
+        assert_eq!(damage.shapes, 0..1);
+        assert!(is_dirty(damage, (0, 0)));
+        assert!(is_dirty(damage, (6, 0)));
+        assert!(!is_dirty(damage, (3, 0)));
+        assert!(!is_dirty(damage, (9, 9)));
+    }
+
+    #[test]
+    fn added_and_removed_shapes_are_damaged() {
+        let mut scene = RetainedScene::new();
+        frame(&mut scene, RESOLUTION, &[(16.0, 16.0)]);
+
+        frame(&mut scene, RESOLUTION, &[(16.0, 16.0), (144.0, 144.0)]);
+        let damage = scene.damage();
+        assert_eq!(damage.shapes, 1..2);
+        assert!(is_dirty(damage, (4, 4)));
+        assert!(!is_dirty(damage, (0, 0)));
+
+        frame(&mut scene, RESOLUTION, &[(16.0, 16.0)]);
+        let damage = scene.damage();
+        assert!(damage.shapes.is_empty());
+        assert!(is_dirty(damage, (4, 4)));
+        assert!(!is_dirty(damage, (0, 0)));
+    }
+
+    #[test]
+    fn resolution_change_is_full_damage() {
+        let mut scene = RetainedScene::new();
+        frame(&mut scene, RESOLUTION, &[(16.0, 16.0)]);
+        frame(&mut scene, (640, 320), &[(16.0, 16.0)]);
+        let damage = scene.damage();
+        assert!(damage.full);
+        assert_eq!(
+            damage.dirty_rects,
+            [TileRect {
+                x: 0,
+                y: 0,
+                width: 20,
+                height: 10
+            }]
+        );
+    }
+
+    #[test]
+    fn changed_range_covers_differences() {
+        assert_eq!(changed_range(&[1, 2, 3], &[1, 2, 3]), 3..3);
+        assert_eq!(changed_range(&[1, 2, 3], &[1, 5, 3]), 1..2);
+        assert_eq!(changed_range(&[1, 2, 3], &[1, 2, 3, 4]), 3..4);
+        assert_eq!(changed_range(&[1, 2, 3], &[1, 5, 3, 4]), 1..4);
+        assert_eq!(changed_range(&[1, 2, 3], &[1, 2]), 2..2);
+        assert_eq!(changed_range(&[1, 2, 3], &[4, 2]), 0..1);
+    }
+
+    #[test]
+    fn dirty_tiles_merge_into_rects() {
+        #[rustfmt::skip]
+        let dirty = [
+            true,  true,  false, false,
+            true,  true,  false, true,
+            false, false, false, true,
+        ];
+        assert_eq!(
+            merge_dirty_tiles(&dirty, 4),
+            [
+                TileRect {
+                    x: 0,
+                    y: 0,
+                    width: 2,
+                    height: 2
+                },
+                TileRect {
+                    x: 3,
+                    y: 1,
+                    width: 1,
+                    height: 2
+                },
+            ]
+        );
+        assert!(merge_dirty_tiles(&[false; 8], 4).is_empty());
+    }
+}
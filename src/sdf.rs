@@ -0,0 +1,182 @@
+//! Signed distance functions, ported from the shader's `sdf.wgsl` so the CPU can evaluate shapes exactly like the GPU.
+//!
+//! Distances are negative inside of a shape and positive outside. Functions taking a point relative to the shape's
+//! center expect `p - center`, like [`Primitive::distance`] passes it.
+//!
+//! `tests/sdf.rs` compares these against the shader on a software adapter, keep both in sync.
+
+use glam::{Vec2, Vec4, vec2};
+
+use crate::shape::Primitive;
+
+impl Primitive {
+    /// Signed distance from `p` to the primitive's edge
+    pub fn distance(&self, p: Vec2) -> f32 {
+        match *self {
+            Primitive::Circle { center, radius } => circle(p - center, radius),
+            Primitive::Triangle { p1, p2, p3 } => triangle(p, p1, p2, p3),
+            Primitive::Rect {
+                center,
+                half_extents,
+                corner_radius,
+            } => rounded_rect(
+                p - center,
+                half_extents,
+                Vec4::new(
+                    corner_radius.top_left,
+                    corner_radius.top_right,
+                    corner_radius.bottom_right,
+                    corner_radius.bottom_left,
+                ),
+            ),
+            Primitive::Line { p1, p2 } => line(p, p1, p2),
+            Primitive::CircleSector {
+                center,
+                radius_inner,
+                radius_outer,
+                angle_start,
+                angle_end,
+            } => sector(
+                p - center,
+                radius_inner,
+                radius_outer,
+                angle_start,
+                angle_end,
+            ),
+            Primitive::PolyQuad { points } => quad(p, &points),
+        }
+    }
+}
+
+/// Union of two distances
+pub fn union(d1: f32, d2: f32) -> f32 {
+    d1.min(d2)
+}
+
+/// Outline of the given thickness on each side of the edge
+pub fn outline(d: f32, thickness: f32) -> f32 {
+    d.abs() - thickness
+}
+
+/// WGSL's `sign`, which unlike [`f32::signum`] returns zero for zero
+fn sign(x: f32) -> f32 {
+    if x > 0.0 {
+        1.0
+    } else if x < 0.0 {
+        -1.0
+    } else {
+        0.0
+    }
+}
+
+/// Circle centered on the origin
+pub fn circle(p: Vec2, radius: f32) -> f32 {
+    p.length() - radius
+}
+
+/// Rect centered on the origin, with `radii` in the order of the [`crate::CornerRadius`] fields.
+/// The radius of each quadrant is picked like the shader does.
+pub fn rounded_rect(p: Vec2, half_extents: Vec2, radii: Vec4) -> f32 {
+    let (r_pos_y, r_neg_y) = if p.x > 0.0 {
+        (radii.x, radii.y)
+    } else {
+        (radii.z, radii.w)
+    };
+    let r = if p.y > 0.0 { r_pos_y } else { r_neg_y };
+
+    let q = p.abs() - half_extents + r;
+    q.x.max(q.y).min(0.0) + q.max(Vec2::ZERO).length() - r
+}
+
+/// Unsigned distance to a line segment
+pub fn line(p: Vec2, a: Vec2, b: Vec2) -> f32 {
+    let pa = p - a;
+    let ba = b - a;
+    let h = (pa.dot(ba) / ba.dot(ba)).clamp(0.0, 1.0);
+    (pa - ba * h).length()
+}
+
+/// Triangle with vertices in either winding order
+pub fn triangle(p: Vec2, p0: Vec2, p1: Vec2, p2: Vec2) -> f32 {
+    let (e0, e1, e2) = (p1 - p0, p2 - p1, p0 - p2);
+    let (v0, v1, v2) = (p - p0, p - p1, p - p2);
+    let pq0 = v0 - e0 * (v0.dot(e0) / e0.dot(e0)).clamp(0.0, 1.0);
+    let pq1 = v1 - e1 * (v1.dot(e1) / e1.dot(e1)).clamp(0.0, 1.0);
+    let pq2 = v2 - e2 * (v2.dot(e2) / e2.dot(e2)).clamp(0.0, 1.0);
+    let s = sign(e0.x * e2.y - e0.y * e2.x);
+    let d = vec2(pq0.dot(pq0), s * (v0.x * e0.y - v0.y * e0.x))
+        .min(vec2(pq1.dot(pq1), s * (v1.x * e1.y - v1.y * e1.x)))
+        .min(vec2(pq2.dot(pq2), s * (v2.x * e2.y - v2.y * e2.x)));
+    -d.x.sqrt() * sign(d.y)
+}
+
+/// Ring sector centered on the origin, spanning from `start_angle` counter-clockwise to `end_angle`
+pub fn sector(
+    p: Vec2,
+    inner_radius: f32,
+    outer_radius: f32,
+    start_angle: f32,
+    end_angle: f32,
+) -> f32 {
+    use std::f32::consts::TAU;
+
+    let delta = (end_angle - start_angle).rem_euclid(TAU);
+    if delta <= 1e-6 {
+        return p.length();
+    }
+    if delta >= TAU - 1e-6 {
+        let r = p.length();
+        return (r - outer_radius).max(inner_radius - r);
+    }
+
+    // Rotate so the sector is symmetric around the x axis
+    let mid = start_angle + 0.5 * delta;
+    let (sm, cm) = mid.sin_cos();
+    let q = vec2(cm * p.x + sm * p.y, -sm * p.x + cm * p.y);
+
+    let r = q.length();
+    let theta = q.y.atan2(q.x);
+    let half = 0.5 * delta;
+
+    if theta.abs() <= half {
+        return if r < inner_radius {
+            inner_radius - r
+        } else if r > outer_radius {
+            r - outer_radius
+        } else {
+            -(r - inner_radius).min(outer_radius - r)
+        };
+    }
+
+    // Outside of the angular span, the closest point lies on the nearer straight edge
+    let side = if theta > 0.0 { 1.0 } else { -1.0 };
+    let (sh, ch) = half.sin_cos();
+    let u = vec2(ch, side * sh);
+    let t = q.dot(u).clamp(inner_radius, outer_radius);
+    (q - u * t).length()
+}
+
+/// Arbitrary quad, including concave and self-intersecting ones, inside by the even-odd rule
+pub fn quad(p: Vec2, v: &[Vec2; 4]) -> f32 {
+    let mut d = (p - v[0]).dot(p - v[0]);
+    let mut s = 1.0;
+
+    let mut j = v.len() - 1;
+    for i in 0..v.len() {
+        let (vi, vj) = (v[i], v[j]);
+        let e = vj - vi;
+        let w = p - vi;
+        let b = w - e * (w.dot(e) / e.dot(e)).clamp(0.0, 1.0);
+        d = d.min(b.dot(b));
+
+        // Flip the sign for every edge crossed by a ray from `p`, to tell inside from outside
+        let cross = e.x * w.y - e.y * w.x;
+        if (p.y >= vi.y && p.y < vj.y && cross > 0.0) || (p.y < vi.y && p.y >= vj.y && cross < 0.0)
+        {
+            s = -s;
+        }
+        j = i;
+    }
+
+    s * d.sqrt()
+}
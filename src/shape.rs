@@ -1,11 +1,42 @@
 use bitflags::bitflags;
 use glam::{Vec2, Vec3, Vec4};
 
+use crate::error::GeometryError;
+
 slotmap::new_key_type! {
     pub struct TextureId;
 }
 
-#[derive(Clone, Debug)]
+/// Refers to a shape submitted to a [`crate::Painter`], eg. to modify it after more shapes were added, or to identify
+/// the shape found by hit testing.
+///
+/// Handles are only valid for the frame they were created in, see [`crate::Painter::last_handle`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ShapeHandle {
+    pub(crate) index: u32,
+    pub(crate) generation: u32,
+    pub(crate) tag: Option<u64>,
+}
+
+impl ShapeHandle {
+    /// Index of the shape in the frame's shape list, in submission order
+    pub fn index(&self) -> usize {
+        self.index as usize
+    }
+
+    /// Frame the shape was submitted in
+    pub fn generation(&self) -> u32 {
+        self.generation
+    }
+
+    /// The shape's tag at the time the handle was created, see [`Shape::with_tag`]
+    pub fn tag(&self) -> Option<u64> {
+        self.tag
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Shape {
     pub primitive: Primitive,
     pub color: Vec4,
@@ -21,7 +52,14 @@ pub struct Shape {
     /// An optional texture ID for the shape. If set, the shape will be sample from the given texture, using the SDF as a clip mask.
     /// The shape's color will be multiplied with the texture color.
     pub texture_id: Option<TextureId>,
+    /// Controls which part of the texture is sampled, and how it is fit to the group bounds.
+    pub texture_mapping: TextureMapping,
     pub flags: ShapeFlags,
+    /// Shapes in higher layers are drawn on top of shapes in lower layers, regardless of submission order. Within a
+    /// layer, shapes are drawn in submission order. A group is drawn in the layer of its last shape.
+    pub layer: i32,
+    /// An optional user-defined value identifying the shape, returned in [`ShapeHandle`]s. Not used for rendering.
+    pub tag: Option<u64>,
 }
 
 impl Shape {
@@ -31,9 +69,7 @@ impl Shape {
     pub fn culling_bounds(&self) -> BoundingBox {
         // cohae: Right now this is the same as bounds(), but in the future, glow/shadows will require culling bounds to be larger than the actual shape bounds.
         let mut bounds = self.primitive.bounds();
-        bounds.grow(-self.distance_offset);
-        bounds.grow(self.line_width * 0.5);
-        bounds.grow(self.glow.w.abs());
+        bounds.grow(self.culling_margin());
         bounds
     }
 
@@ -47,21 +83,162 @@ impl Shape {
         bounds
     }
 
+    /// Distance by which the shape extends beyond its primitive, taking into account distance offset, line width and glow.
+    ///
+    /// This is the margin used by [`Shape::culling_bounds`], and may be negative for shapes shrunk by a positive distance offset.
+    pub fn culling_margin(&self) -> f32 {
+        -self.distance_offset + self.line_width * 0.5 + self.glow.w.abs()
+    }
+
+    /// Conservatively tests whether the shape may cover any pixel inside the given bounds.
+    ///
+    /// Unlike testing against [`Shape::culling_bounds`], this takes the actual primitive geometry into account, so a diagonal line
+    /// will not be considered to overlap tiles in the corners of its bounding box.
+    pub fn intersects(&self, bounds: &BoundingBox) -> bool {
+        // SDF textures replace the primitive distance entirely, so only the bounds can be relied on
+        if self.has_sdf_texture() {
+            return self.culling_bounds().intersects(bounds);
+        }
+
+        // Anti-aliasing blends up to one pixel outside of the shape edge
+        let margin = self.culling_margin().max(0.0) + 1.0;
+        self.primitive.intersects(bounds, margin)
+    }
+
+    /// Scales the shape's geometry and every size in pixels, like its outline and glow, around the origin
+    pub fn scale(&mut self, scale: f32) {
+        self.primitive = self.primitive.scaled(scale);
+        self.distance_offset *= scale;
+        self.line_width *= scale;
+        self.glow.w *= scale;
+        if let TextureFit::Tile(size) = &mut self.texture_mapping.fit {
+            *size *= scale;
+        }
+    }
+
+    /// Checks the shape for non-finite values and negative sizes, which can't be binned or rendered.
+    pub fn validate(&self) -> Result<(), GeometryError> {
+        let mapping = &self.texture_mapping;
+        if !(self.color.is_finite()
+            && self.glow.is_finite()
+            && self.distance_offset.is_finite()
+            && self.line_width.is_finite()
+            && mapping.uv_min.is_finite()
+            && mapping.uv_max.is_finite()
+            && mapping.sdf_range.is_finite())
+        {
+            return Err(GeometryError::NonFinite);
+        }
+        if self.line_width < 0.0 || mapping.sdf_range <= 0.0 {
+            return Err(GeometryError::NegativeSize);
+        }
+        if let TextureFit::Tile(size) = mapping.fit {
+            if !size.is_finite() {
+                return Err(GeometryError::NonFinite);
+            }
+            if size.cmple(Vec2::ZERO).any() {
+                return Err(GeometryError::NegativeSize);
+            }
+        }
+        self.primitive.validate()
+    }
+
+    /// Whether the shape's distance comes from an SDF or MTSDF texture instead of its primitive
+    pub(crate) fn has_sdf_texture(&self) -> bool {
+        self.texture_id.is_some()
+            && self
+                .flags
+                .intersects(ShapeFlags::TEXTURE_SDF | ShapeFlags::TEXTURE_MTSDF)
+    }
+
+    /// A shape that doesn't draw anything, used in place of shapes with invalid geometry.
+    /// Keeps the shape's group, layer and tag, so draw order and handles are unaffected.
+    pub(crate) fn empty(&self) -> Self {
+        Self {
+            primitive: Primitive::Circle {
+                center: Vec2::ZERO,
+                radius: 0.0,
+            },
+            color: Vec4::ZERO,
+            glow: Vec4::ZERO,
+            distance_offset: 0.0,
+            line_width: 0.0,
+            group_id: self.group_id,
+            texture_id: None,
+            texture_mapping: TextureMapping::default(),
+            flags: ShapeFlags::empty(),
+            layer: self.layer,
+            tag: self.tag,
+        }
+    }
+
+    /// Offsets the shape's distance field, in pixels. Negative offsets grow the shape, positive offsets shrink it.
+    /// For SDF and MTSDF textures the offset is applied to the distances stored in the texture, eg. to change the weight of text.
     pub fn with_distance_offset(&mut self, offset: f32) -> &mut Self {
         self.distance_offset = offset;
         self
     }
 
+    /// Draws only an outline of this width, centered on the shape's edge
     pub fn with_line_width(&mut self, line_width: f32) -> &mut Self {
         self.line_width = line_width;
         self
     }
 
+    /// Replaces the shape's group-level style, see [`GroupStyle`]
+    pub fn with_style(&mut self, style: &GroupStyle) -> &mut Self {
+        self.color = style.color;
+        self.glow = style.glow;
+        self.line_width = style.line_width;
+        self.texture_id = style.texture_id;
+        self.texture_mapping = style.texture_mapping;
+        self.flags = style.flags;
+        self
+    }
+
+    /// Moves the shape to another layer, see [`Shape::layer`]
+    pub fn with_layer(&mut self, layer: i32) -> &mut Self {
+        self.layer = layer;
+        self
+    }
+
+    /// Tags the shape with a user-defined value, eg. the ID of the widget it belongs to
+    pub fn with_tag(&mut self, tag: u64) -> &mut Self {
+        self.tag = Some(tag);
+        self
+    }
+
     pub fn with_texture_id(&mut self, texture_id: TextureId) -> &mut Self {
         self.texture_id = Some(texture_id);
         self
     }
 
+    /// Samples only the given region of the texture, in UV coordinates. Useful for drawing sprites from a sprite sheet.
+    ///
+    /// A `min` larger than `max` flips the texture along that axis.
+    pub fn with_texture_uv(&mut self, min: impl Into<Vec2>, max: impl Into<Vec2>) -> &mut Self {
+        self.texture_mapping.uv_min = min.into();
+        self.texture_mapping.uv_max = max.into();
+        self
+    }
+
+    /// Flips the sampled texture region horizontally and/or vertically
+    pub fn with_texture_flip(&mut self, flip_x: bool, flip_y: bool) -> &mut Self {
+        let mapping = &mut self.texture_mapping;
+        if flip_x {
+            std::mem::swap(&mut mapping.uv_min.x, &mut mapping.uv_max.x);
+        }
+        if flip_y {
+            std::mem::swap(&mut mapping.uv_min.y, &mut mapping.uv_max.y);
+        }
+        self
+    }
+
+    pub fn with_texture_fit(&mut self, fit: TextureFit) -> &mut Self {
+        self.texture_mapping.fit = fit;
+        self
+    }
+
     pub fn with_flags(&mut self, flags: ShapeFlags) -> &mut Self {
         self.flags = flags;
         self
@@ -77,6 +254,13 @@ impl Shape {
         self
     }
 
+    /// Sets the distance range of an SDF or MTSDF texture, in texels of the texture. This is the `pxRange` the
+    /// texture was generated with, eg. by msdfgen. Defaults to 4.
+    pub fn with_texture_sdf_range(&mut self, range: f32) -> &mut Self {
+        self.texture_mapping.sdf_range = range;
+        self
+    }
+
     pub fn with_glow(&mut self, color: Vec3, size: f32) -> &mut Self {
         self.glow = Vec4::new(color.x, color.y, color.z, size);
         self
@@ -88,7 +272,117 @@ impl Shape {
     }
 }
 
-#[derive(Clone, Copy, Debug)]
+/// Style of a shape group: everything the shader takes from the group as a whole rather than from its individual shapes.
+///
+/// Groups without an explicit style use the style of their last shape. See [`crate::Painter::begin_group_with_style`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct GroupStyle {
+    pub color: Vec4,
+    pub glow: Vec4,
+    /// When non-zero, the group is drawn as an outline of this width
+    pub line_width: f32,
+    pub texture_id: Option<TextureId>,
+    pub texture_mapping: TextureMapping,
+    pub flags: ShapeFlags,
+}
+
+impl GroupStyle {
+    /// A filled group of the given color
+    pub fn new(color: impl Into<Vec4>) -> Self {
+        Self {
+            color: color.into(),
+            glow: Vec4::ZERO,
+            line_width: 0.0,
+            texture_id: None,
+            texture_mapping: TextureMapping::default(),
+            flags: ShapeFlags::empty(),
+        }
+    }
+
+    pub fn with_line_width(mut self, line_width: f32) -> Self {
+        self.line_width = line_width;
+        self
+    }
+
+    pub fn with_glow(mut self, color: Vec3, size: f32) -> Self {
+        self.glow = Vec4::new(color.x, color.y, color.z, size);
+        self
+    }
+
+    pub fn with_shadow(mut self, opacity: f32, size: f32) -> Self {
+        self.glow = Vec4::new(opacity, opacity, opacity, -size);
+        self
+    }
+
+    /// Fills the group with a texture, mapped onto the bounds of the whole group
+    pub fn with_texture(mut self, texture_id: TextureId, mapping: TextureMapping) -> Self {
+        self.texture_id = Some(texture_id);
+        self.texture_mapping = mapping;
+        self
+    }
+
+    pub fn with_flags(mut self, flags: ShapeFlags) -> Self {
+        self.flags = flags;
+        self
+    }
+}
+
+impl From<&Shape> for GroupStyle {
+    /// The style a shape gives the group it is the last shape of
+    fn from(shape: &Shape) -> Self {
+        Self {
+            color: shape.color,
+            glow: shape.glow,
+            line_width: shape.line_width,
+            texture_id: shape.texture_id,
+            texture_mapping: shape.texture_mapping,
+            flags: shape.flags,
+        }
+    }
+}
+
+/// Describes how a shape's texture is mapped onto the group bounds
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TextureMapping {
+    /// Top-left corner of the sampled texture region, in UV coordinates
+    pub uv_min: Vec2,
+    /// Bottom-right corner of the sampled texture region, in UV coordinates
+    pub uv_max: Vec2,
+    pub fit: TextureFit,
+    /// Width of the distance range of SDF and MTSDF textures, in texels
+    pub sdf_range: f32,
+}
+
+impl Default for TextureMapping {
+    fn default() -> Self {
+        Self {
+            uv_min: Vec2::ZERO,
+            uv_max: Vec2::ONE,
+            fit: TextureFit::Stretch,
+            sdf_range: 4.0,
+        }
+    }
+}
+
+/// How the sampled texture region is fit to the group bounds
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TextureFit {
+    /// Stretch the region over the bounds, ignoring its aspect ratio
+    #[default]
+    Stretch,
+    /// Scale the region uniformly to fit inside the bounds, centered. Parts of the bounds not covered by the texture are transparent.
+    Contain,
+    /// Scale the region uniformly to cover the bounds, centered. Parts of the texture outside of the bounds are cropped.
+    Cover,
+    /// Repeat the region across the bounds, with each repetition being the given size in pixels
+    Tile(Vec2),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Primitive {
     Circle {
         center: Vec2,
@@ -121,6 +415,109 @@ pub enum Primitive {
 }
 
 impl Primitive {
+    /// Checks the primitive for non-finite coordinates and negative sizes
+    pub fn validate(&self) -> Result<(), GeometryError> {
+        let (finite, negative) = match *self {
+            Primitive::Circle { center, radius } => {
+                (center.is_finite() && radius.is_finite(), radius < 0.0)
+            }
+            Primitive::Triangle { p1, p2, p3 } => {
+                (p1.is_finite() && p2.is_finite() && p3.is_finite(), false)
+            }
+            Primitive::Rect {
+                center,
+                half_extents,
+                corner_radius,
+            } => {
+                let radii = [
+                    corner_radius.top_left,
+                    corner_radius.top_right,
+                    corner_radius.bottom_right,
+                    corner_radius.bottom_left,
+                ];
+                (
+                    center.is_finite()
+                        && half_extents.is_finite()
+                        && radii.iter().all(|r| r.is_finite()),
+                    half_extents.cmplt(Vec2::ZERO).any() || radii.iter().any(|&r| r < 0.0),
+                )
+            }
+            Primitive::Line { p1, p2 } => (p1.is_finite() && p2.is_finite(), false),
+            Primitive::CircleSector {
+                center,
+                radius_inner,
+                radius_outer,
+                angle_start,
+                angle_end,
+            } => (
+                center.is_finite()
+                    && radius_inner.is_finite()
+                    && radius_outer.is_finite()
+                    && angle_start.is_finite()
+                    && angle_end.is_finite(),
+                radius_inner < 0.0 || radius_outer < radius_inner,
+            ),
+            Primitive::PolyQuad { points } => (points.iter().all(|p| p.is_finite()), false),
+        };
+
+        if !finite {
+            Err(GeometryError::NonFinite)
+        } else if negative {
+            Err(GeometryError::NegativeSize)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// The primitive scaled around the origin
+    pub fn scaled(&self, scale: f32) -> Primitive {
+        match *self {
+            Primitive::Circle { center, radius } => Primitive::Circle {
+                center: center * scale,
+                radius: radius * scale,
+            },
+            Primitive::Triangle { p1, p2, p3 } => Primitive::Triangle {
+                p1: p1 * scale,
+                p2: p2 * scale,
+                p3: p3 * scale,
+            },
+            Primitive::Rect {
+                center,
+                half_extents,
+                corner_radius,
+            } => Primitive::Rect {
+                center: center * scale,
+                half_extents: half_extents * scale,
+                corner_radius: CornerRadius {
+                    top_left: corner_radius.top_left * scale,
+                    top_right: corner_radius.top_right * scale,
+                    bottom_right: corner_radius.bottom_right * scale,
+                    bottom_left: corner_radius.bottom_left * scale,
+                },
+            },
+            Primitive::Line { p1, p2 } => Primitive::Line {
+                p1: p1 * scale,
+                p2: p2 * scale,
+            },
+            Primitive::CircleSector {
+                center,
+                radius_inner,
+                radius_outer,
+                angle_start,
+                angle_end,
+            } => Primitive::CircleSector {
+                center: center * scale,
+                radius_inner: radius_inner * scale,
+                radius_outer: radius_outer * scale,
+                angle_start,
+                angle_end,
+            },
+            Primitive::PolyQuad { points } => Primitive::PolyQuad {
+                points: points.map(|p| p * scale),
+            },
+        }
+    }
+
     pub fn bounds(&self) -> BoundingBox {
         match *self {
             Primitive::Circle { center, radius } => BoundingBox {
@@ -164,10 +561,126 @@ impl Primitive {
             }
         }
     }
+
+    /// Tests whether the primitive, grown by `margin`, overlaps the given bounds.
+    ///
+    /// The test is conservative: it never returns `false` for an overlapping primitive, but may return `true` for primitives
+    /// that come close to the bounds without touching them (eg. rounded rect corners, or the empty part of a circle sector).
+    pub fn intersects(&self, bounds: &BoundingBox, margin: f32) -> bool {
+        match *self {
+            Primitive::Circle { center, radius } => {
+                bounds.distance_squared_to_point(center) <= (radius + margin).powi(2)
+            }
+            Primitive::Rect {
+                center,
+                half_extents,
+                ..
+            } => {
+                let mut rect = BoundingBox {
+                    min: center - half_extents,
+                    max: center + half_extents,
+                };
+                rect.grow(margin);
+                rect.intersects(bounds)
+            }
+            Primitive::Line { p1, p2 } => {
+                // Cheap rejection against the square-cornered grown box first, the exact distance is only needed near the corners
+                hull_intersects_box(&[p1, p2], bounds, margin)
+                    && segment_box_distance_squared(p1, p2, bounds) <= margin * margin
+            }
+            Primitive::CircleSector {
+                center,
+                radius_inner,
+                radius_outer,
+                ..
+            } => {
+                let nearest_sq = bounds.distance_squared_to_point(center);
+                let farthest_sq = (center - bounds.min)
+                    .abs()
+                    .max((center - bounds.max).abs())
+                    .length_squared();
+                let inner = (radius_inner - margin).max(0.0);
+                nearest_sq <= (radius_outer + margin).powi(2) && farthest_sq >= inner * inner
+            }
+            Primitive::Triangle { p1, p2, p3 } => {
+                hull_intersects_box(&[p1, p2, p3], bounds, margin)
+            }
+            Primitive::PolyQuad { points } => hull_intersects_box(&points, bounds, margin),
+        }
+    }
+}
+
+/// Separating axis test between the convex hull of `points` and `bounds` grown by `margin`.
+///
+/// Every pair of points is used as a candidate axis, which covers all hull edges without having to compute the hull itself.
+/// This makes the test valid for concave and self-intersecting polygons as well, at the cost of being conservative for them.
+fn hull_intersects_box(points: &[Vec2], bounds: &BoundingBox, margin: f32) -> bool {
+    let mut grown = *bounds;
+    grown.grow(margin);
+
+    let hull_bounds = points.iter().fold(BoundingBox::EMPTY, |acc, &p| {
+        acc.union(&BoundingBox { min: p, max: p })
+    });
+    if !hull_bounds.intersects(&grown) {
+        return false;
+    }
+
+    let box_center = (grown.min + grown.max) * 0.5;
+    let box_half_extents = (grown.max - grown.min) * 0.5;
+    for (i, &a) in points.iter().enumerate() {
+        for &b in &points[i + 1..] {
+            let axis = (b - a).perp();
+            if axis == Vec2::ZERO {
+                continue;
+            }
+
+            let (hull_min, hull_max) = points.iter().fold((f32::MAX, f32::MIN), |(min, max), p| {
+                let d = p.dot(axis);
+                (min.min(d), max.max(d))
+            });
+            let box_mid = box_center.dot(axis);
+            let box_radius = box_half_extents.dot(axis.abs());
+            if hull_max < box_mid - box_radius || box_mid + box_radius < hull_min {
+                return false;
+            }
+        }
+    }
+
+    true
+}
+
+/// Squared shortest distance between the segment `a`-`b` and `bounds`, or 0 if they overlap.
+fn segment_box_distance_squared(a: Vec2, b: Vec2, bounds: &BoundingBox) -> f32 {
+    if hull_intersects_box(&[a, b], bounds, 0.0) {
+        return 0.0;
+    }
+
+    // Without overlap, the closest point pair always involves a segment endpoint or a box corner
+    let endpoint_dist_sq = bounds
+        .distance_squared_to_point(a)
+        .min(bounds.distance_squared_to_point(b));
+    bounds
+        .corners()
+        .iter()
+        .map(|&c| point_segment_distance_squared(c, a, b))
+        .fold(endpoint_dist_sq, f32::min)
+}
+
+fn point_segment_distance_squared(p: Vec2, a: Vec2, b: Vec2) -> f32 {
+    let pa = p - a;
+    let ba = b - a;
+    let len_sq = ba.length_squared();
+    let h = if len_sq > 0.0 {
+        (pa.dot(ba) / len_sq).clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+    (pa - ba * h).length_squared()
 }
 
 #[repr(C)]
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct CornerRadius {
     pub top_left: f32,
     pub top_right: f32,
@@ -199,6 +712,7 @@ impl From<[f32; 4]> for CornerRadius {
 
 #[repr(C)]
 #[derive(Clone, Copy, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct BoundingBox {
     pub min: Vec2,
     pub max: Vec2,
@@ -225,6 +739,32 @@ impl BoundingBox {
             max: self.max.max(other.max),
         }
     }
+
+    pub fn intersects(&self, other: &BoundingBox) -> bool {
+        self.min.x <= other.max.x
+            && self.max.x >= other.min.x
+            && self.min.y <= other.max.y
+            && self.max.y >= other.min.y
+    }
+
+    /// Distance from the point to the closest point in the bounding box, or 0 if the point is inside.
+    pub fn distance_to_point(&self, point: Vec2) -> f32 {
+        self.distance_squared_to_point(point).sqrt()
+    }
+
+    pub fn distance_squared_to_point(&self, point: Vec2) -> f32 {
+        (point.max(self.min).min(self.max) - point).length_squared()
+    }
+
+    /// Returns the corners in clockwise order, starting at `min`.
+    pub fn corners(&self) -> [Vec2; 4] {
+        [
+            self.min,
+            Vec2::new(self.max.x, self.min.y),
+            self.max,
+            Vec2::new(self.min.x, self.max.y),
+        ]
+    }
 }
 
 impl Default for BoundingBox {
@@ -234,7 +774,8 @@ impl Default for BoundingBox {
 }
 
 bitflags! {
-    #[derive(Default, Clone, Copy, Debug)]
+    #[derive(Default, Clone, Copy, Debug, PartialEq, Eq)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
     pub struct ShapeFlags: u8 {
         const TEXTURE_SDF = 1 << 0;
         const TEXTURE_MTSDF = 1 << 1;
@@ -0,0 +1,648 @@
+//! Writing painted frames as SVG documents
+
+use std::f32::consts::{PI, TAU};
+use std::fmt::Write;
+
+use glam::{Vec2, Vec3, Vec4, Vec4Swizzles, vec2};
+
+use crate::{
+    binner::shape_groups,
+    shape::{Primitive, Shape, ShapeFlags},
+};
+
+/// Writes a frame's shapes, eg. the ones passed to [`crate::Painter::finish`], as an SVG document.
+///
+/// Groups are drawn as the union of their shapes, and outlined groups as the band around the edge of the union, so
+/// the document matches the rendered frame up to anti-aliasing. Glows and shadows are approximated with blur filters.
+///
+/// Textures can't be exported. Groups with SDF textures, like text, are left out, and other textured groups are
+/// filled with their color.
+pub fn export(shapes: &[Shape], resolution: (u32, u32)) -> String {
+    let mut exporter = Exporter {
+        size: vec2(resolution.0 as f32, resolution.1 as f32),
+        defs: String::new(),
+        body: String::new(),
+        next_id: 0,
+    };
+    for group in shape_groups(shapes) {
+        exporter.group(&shapes[group]);
+    }
+
+    let (width, height) = resolution;
+    let mut svg = format!(
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="{height}" viewBox="0 0 {width} {height}">"#
+    );
+    svg.push('\n');
+    if !exporter.defs.is_empty() {
+        let _ = write!(svg, "<defs>\n{}</defs>\n", exporter.defs);
+    }
+    // Frames are drawn onto black
+    let _ = writeln!(
+        svg,
+        r#"<rect width="{width}" height="{height}" fill="black"/>"#
+    );
+    svg.push_str(&exporter.body);
+    svg.push_str("</svg>\n");
+    svg
+}
+
+struct Exporter {
+    size: Vec2,
+    /// Masks, filters and shared geometry
+    defs: String,
+    body: String,
+    next_id: u32,
+}
+
+impl Exporter {
+    fn id(&mut self, prefix: &str) -> String {
+        self.next_id += 1;
+        format!("{prefix}{}", self.next_id)
+    }
+
+    /// Attributes making masks and filters cover the whole frame, instead of the bounds of the element using them,
+    /// which don't include strokes
+    fn frame_units(&self, units: &str) -> String {
+        format!(
+            r#"{units}="userSpaceOnUse" x="0" y="0" width="{}" height="{}""#,
+            self.size.x, self.size.y
+        )
+    }
+
+    fn group(&mut self, group: &[Shape]) {
+        // The last shape of a group defines its style
+        let Some(style) = group.last() else {
+            return;
+        };
+        if style
+            .flags
+            .intersects(ShapeFlags::TEXTURE_SDF | ShapeFlags::TEXTURE_MTSDF)
+        {
+            return;
+        }
+        let has_glow = style.glow.w != 0.0;
+        if style.color.w <= 0.0 && !has_glow {
+            return;
+        }
+
+        let mut geometry = String::new();
+        let mut mask = String::new();
+        if style.line_width > 0.0 {
+            // The outline is the band within half the line width of the union's edge: the union grown by half the
+            // line width, minus the union shrunk by it
+            let half_width = style.line_width / 2.0;
+            let mut inside = format!(
+                r#"<rect width="{}" height="{}" fill="white"/>"#,
+                self.size.x, self.size.y
+            );
+            inside.push('\n');
+            for shape in group {
+                self.region(&mut inside, shape, -half_width, "black");
+                self.region(&mut geometry, shape, half_width, "currentColor");
+            }
+            let id = self.id("outline");
+            let units = self.frame_units("maskUnits");
+            let _ = write!(self.defs, "<mask id=\"{id}\" {units}>\n{inside}</mask>\n");
+            mask = format!(r#" mask="url(#{id})""#);
+        } else {
+            for shape in group {
+                self.region(&mut geometry, shape, 0.0, "currentColor");
+            }
+        }
+        if geometry.is_empty() {
+            return;
+        }
+
+        let color = hex_color(style.color.xyz());
+        let opacity = if style.color.w < 1.0 {
+            format!(r#" opacity="{}""#, num(style.color.w))
+        } else {
+            String::new()
+        };
+        if !has_glow {
+            let _ = write!(
+                self.body,
+                "<g color=\"{color}\"{opacity}{mask}>\n{geometry}</g>\n"
+            );
+            return;
+        }
+
+        // The glow is drawn from the same geometry, below the group
+        let id = self.id("group");
+        let _ = write!(self.defs, "<g id=\"{id}\"{mask}>\n{geometry}</g>\n");
+        let filter = self.glow_filter(style.glow);
+        let _ = writeln!(
+            self.body,
+            r##"<use href="#{id}" filter="url(#{filter})"/>"##
+        );
+        if style.color.w > 0.0 {
+            let _ = writeln!(
+                self.body,
+                r##"<use href="#{id}" color="{color}"{opacity}/>"##
+            );
+        }
+    }
+
+    /// Writes a filter drawing only the glow or shadow of the element it's applied to, and returns its ID
+    fn glow_filter(&mut self, glow: Vec4) -> String {
+        // Shadows store their opacity in the color and a negative size, see `Shape::with_shadow`
+        let (color, opacity) = if glow.w < 0.0 {
+            (Vec3::ZERO, glow.x)
+        } else {
+            (glow.xyz().normalize_or_zero(), glow.xyz().length())
+        };
+        let id = self.id("glow");
+        let units = self.frame_units("filterUnits");
+        // The shader fades glows out linearly over their size. A blur of a third of the size, doubled in strength so
+        // it starts out fully opaque at the edge, roughly matches that.
+        let _ = write!(
+            self.defs,
+            concat!(
+                "<filter id=\"{id}\" {units}>\n",
+                "<feGaussianBlur in=\"SourceAlpha\" stdDeviation=\"{deviation}\"/>\n",
+                "<feComponentTransfer result=\"alpha\"><feFuncA type=\"linear\" slope=\"2\"/></feComponentTransfer>\n",
+                "<feFlood flood-color=\"{color}\" flood-opacity=\"{opacity}\"/>\n",
+                "<feComposite operator=\"in\" in2=\"alpha\"/>\n",
+                "</filter>\n",
+            ),
+            id = id,
+            units = units,
+            deviation = num(glow.w.abs() / 3.0),
+            color = hex_color(color),
+            opacity = num(opacity.min(1.0)),
+        );
+        id
+    }
+
+    /// Writes the area where the shape's distance, including its distance offset, is at most `grow`
+    fn region(&mut self, out: &mut String, shape: &Shape, grow: f32, paint: &str) {
+        let grow = grow - shape.distance_offset;
+        match shape.primitive {
+            Primitive::Circle { center, radius } => circle(out, center, radius + grow, paint),
+            Primitive::Rect {
+                center,
+                half_extents,
+                corner_radius,
+            } => {
+                let half_extents = half_extents + grow;
+                if half_extents.min_element() <= 0.0 {
+                    return;
+                }
+                let max_radius = half_extents.min_element();
+                // Top left, top right, bottom right and bottom left on screen, picked for each quadrant like the
+                // shader does
+                let radii = [
+                    corner_radius.bottom_left,
+                    corner_radius.top_right,
+                    corner_radius.top_left,
+                    corner_radius.bottom_right,
+                ]
+                .map(|radius| (radius + grow).clamp(0.0, max_radius));
+                let min = center - half_extents;
+                let size = half_extents * 2.0;
+                if radii.iter().all(|&radius| radius == radii[0]) {
+                    let _ = writeln!(
+                        out,
+                        r#"<rect x="{}" y="{}" width="{}" height="{}" rx="{}" fill="{paint}"/>"#,
+                        num(min.x),
+                        num(min.y),
+                        num(size.x),
+                        num(size.y),
+                        num(radii[0]),
+                    );
+                } else {
+                    let _ = writeln!(
+                        out,
+                        r#"<path d="{}" fill="{paint}"/>"#,
+                        rounded_rect_path(min, min + size, radii)
+                    );
+                }
+            }
+            Primitive::Line { p1, p2 } => {
+                if grow > 0.0 {
+                    let _ = writeln!(
+                        out,
+                        r#"<line x1="{}" y1="{}" x2="{}" y2="{}" stroke="{paint}" stroke-width="{}" stroke-linecap="round"/>"#,
+                        num(p1.x),
+                        num(p1.y),
+                        num(p2.x),
+                        num(p2.y),
+                        num(grow * 2.0),
+                    );
+                }
+            }
+            Primitive::Triangle { p1, p2, p3 } => {
+                let path = format!("M{}L{}L{}Z", point(p1), point(p2), point(p3));
+                self.path_region(out, &path, true, false, grow, paint);
+            }
+            Primitive::PolyQuad { points } => {
+                let path = format!(
+                    "M{}L{}L{}L{}Z",
+                    point(points[0]),
+                    point(points[1]),
+                    point(points[2]),
+                    point(points[3])
+                );
+                self.path_region(out, &path, true, true, grow, paint);
+            }
+            Primitive::CircleSector {
+                center,
+                radius_inner,
+                radius_outer,
+                angle_start,
+                angle_end,
+            } => {
+                let delta = (angle_end - angle_start).rem_euclid(TAU);
+                if delta <= 1e-6 {
+                    // Empty sectors measure the distance to their center
+                    circle(out, center, grow, paint);
+                    return;
+                }
+                if delta >= TAU - 1e-6 {
+                    let (inner, outer) = (radius_inner - grow, radius_outer + grow);
+                    if inner <= 0.0 {
+                        circle(out, center, outer, paint);
+                    } else if outer > inner {
+                        let _ = writeln!(
+                            out,
+                            r#"<path d="{}{}" fill="{paint}" fill-rule="evenodd"/>"#,
+                            circle_path(center, outer),
+                            circle_path(center, inner)
+                        );
+                    }
+                    return;
+                }
+
+                let at = |radius: f32, angle: f32| point(center + radius * Vec2::from_angle(angle));
+                let end = angle_start + delta;
+                let large_arc = u8::from(delta > PI);
+                let mut path = format!(
+                    "M{}A{r} {r} 0 {large_arc} 1 {}",
+                    at(radius_outer, angle_start),
+                    at(radius_outer, end),
+                    r = num(radius_outer),
+                );
+                let has_area = radius_outer > radius_inner;
+                if has_area && radius_inner > 0.0 {
+                    let _ = write!(
+                        path,
+                        "L{}A{r} {r} 0 {large_arc} 0 {}Z",
+                        at(radius_inner, end),
+                        at(radius_inner, angle_start),
+                        r = num(radius_inner),
+                    );
+                } else if has_area {
+                    let _ = write!(path, "L{}Z", point(center));
+                }
+                self.path_region(out, &path, has_area, false, grow, paint);
+            }
+        }
+    }
+
+    /// Writes the area within `grow` of a path. Paths without an area are only drawn when grown.
+    fn path_region(
+        &mut self,
+        out: &mut String,
+        path: &str,
+        has_area: bool,
+        even_odd: bool,
+        grow: f32,
+        paint: &str,
+    ) {
+        let fill_rule = if even_odd {
+            r#" fill-rule="evenodd""#
+        } else {
+            ""
+        };
+        if !has_area {
+            if grow > 0.0 {
+                let _ = writeln!(
+                    out,
+                    r#"<path d="{path}" fill="none" stroke="{paint}" stroke-width="{}" stroke-linecap="round" stroke-linejoin="round"/>"#,
+                    num(grow * 2.0)
+                );
+            }
+        } else if grow > 0.0 {
+            // Round joins make the stroke cover exactly the points within half its width of the edge
+            let _ = writeln!(
+                out,
+                r#"<path d="{path}" fill="{paint}"{fill_rule} stroke="{paint}" stroke-width="{}" stroke-linejoin="round"/>"#,
+                num(grow * 2.0)
+            );
+        } else if grow == 0.0 {
+            let _ = writeln!(out, r#"<path d="{path}" fill="{paint}"{fill_rule}/>"#);
+        } else {
+            // Shrinking removes the band along the edge, which is masked out with a stroke
+            let id = self.id("shrink");
+            let units = self.frame_units("maskUnits");
+            let _ = writeln!(
+                self.defs,
+                r#"<mask id="{id}" {units}><path d="{path}" fill="white"{fill_rule} stroke="black" stroke-width="{}"/></mask>"#,
+                num(-grow * 2.0)
+            );
+            let _ = writeln!(
+                out,
+                r#"<path d="{path}" fill="{paint}"{fill_rule} mask="url(#{id})"/>"#
+            );
+        }
+    }
+}
+
+fn circle(out: &mut String, center: Vec2, radius: f32, paint: &str) {
+    if radius > 0.0 {
+        let _ = writeln!(
+            out,
+            r#"<circle cx="{}" cy="{}" r="{}" fill="{paint}"/>"#,
+            num(center.x),
+            num(center.y),
+            num(radius)
+        );
+    }
+}
+
+fn circle_path(center: Vec2, radius: f32) -> String {
+    let r = num(radius);
+    format!(
+        "M{}A{r} {r} 0 1 1 {}A{r} {r} 0 1 1 {}Z",
+        point(center + vec2(radius, 0.0)),
+        point(center - vec2(radius, 0.0)),
+        point(center + vec2(radius, 0.0)),
+    )
+}
+
+/// Path of a rect with the radii of its top left, top right, bottom right and bottom left corners
+fn rounded_rect_path(min: Vec2, max: Vec2, radii: [f32; 4]) -> String {
+    let [top_left, top_right, bottom_right, bottom_left] = radii;
+    let corner = |r: f32, to: Vec2| {
+        if r > 0.0 {
+            format!("A{r} {r} 0 0 1 {}", point(to), r = num(r))
+        } else {
+            String::new()
+        }
+    };
+    format!(
+        "M{}L{}{}L{}{}L{}{}L{}{}Z",
+        point(vec2(min.x + top_left, min.y)),
+        point(vec2(max.x - top_right, min.y)),
+        corner(top_right, vec2(max.x, min.y + top_right)),
+        point(vec2(max.x, max.y - bottom_right)),
+        corner(bottom_right, vec2(max.x - bottom_right, max.y)),
+        point(vec2(min.x + bottom_left, max.y)),
+        corner(bottom_left, vec2(min.x, max.y - bottom_left)),
+        point(vec2(min.x, min.y + top_left)),
+        corner(top_left, vec2(min.x + top_left, min.y)),
+    )
+}
+
+/// Rounds coordinates to keep documents small and diffable
+fn num(value: f32) -> f32 {
+    (value * 1000.0).round() / 1000.0
+}
+
+fn point(p: Vec2) -> String {
+    format!("{} {}", num(p.x), num(p.y))
+}
+
+fn hex_color(color: Vec3) -> String {
+    let [r, g, b] = color
+        .clamp(Vec3::ZERO, Vec3::ONE)
+        .to_array()
+        .map(|c| (c * 255.0).round() as u8);
+    format!("#{r:02x}{g:02x}{b:02x}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Painter, TextureId, shape::GroupStyle, svg::SvgImage};
+
+    const RESOLUTION: (u32, u32) = (200, 200);
+
+    fn export_frame(paint: impl FnOnce(&mut Painter)) -> String {
+        let mut painter = Painter::new();
+        painter.start(RESOLUTION);
+        paint(&mut painter);
+        let mut svg = String::new();
+        painter.finish(|shapes, _| svg = export(shapes, RESOLUTION));
+        svg
+    }
+
+    /// Primitives and colors of the frame, leaving out the black background
+    fn painted(paint: impl FnOnce(&mut Painter)) -> Vec<(Primitive, Vec4)> {
+        let mut painter = Painter::new();
+        painter.start(RESOLUTION);
+        paint(&mut painter);
+        let mut shapes = Vec::new();
+        painter.finish(|finished, _| {
+            shapes = finished
+                .iter()
+                .map(|shape| (shape.primitive, shape.color))
+                .filter(|(_, color)| color.xyz() != Vec3::ZERO)
+                .collect()
+        });
+        shapes
+    }
+
+    #[test]
+    fn remaps_per_corner_radii() {
+        let svg = export_frame(|painter| {
+            painter.add_filled_rect((10.0, 10.0), (50.0, 30.0), [1.0, 2.0, 3.0, 4.0], Vec4::ONE);
+        });
+        // Screen corners take the radii the shader picks for their quadrant: top left from `bottom_left`, top right
+        // from `top_right`, bottom right from `top_left` and bottom left from `bottom_right`
+        assert!(svg.contains(
+            "<path d=\"M14 10L48 10A2 2 0 0 1 50 12L50 29A1 1 0 0 1 49 30L13 30A3 3 0 0 1 10 27L10 14A4 4 0 0 1 14 10Z\""
+        ), "{svg}");
+
+        let svg = export_frame(|painter| {
+            painter.add_filled_rect((10.0, 10.0), (50.0, 30.0), 4.0, Vec4::ONE);
+        });
+        assert!(
+            svg.contains(r#"<rect x="10" y="10" width="40" height="20" rx="4""#),
+            "{svg}"
+        );
+    }
+
+    #[test]
+    fn masks_outlined_groups() {
+        let svg = export_frame(|painter| {
+            painter.begin_group_with_style(GroupStyle::new(Vec4::ONE).with_line_width(4.0));
+            painter.add_filled_circle((50.0, 50.0), 20.0, Vec4::ONE);
+            painter.add_filled_triangle((60.0, 40.0), (100.0, 40.0), (80.0, 80.0), Vec4::ONE);
+            painter.end_group();
+        });
+        // The outline is the union grown by half the line width, masked by the union shrunk by it
+        assert!(
+            svg.contains(r##"<g color="#ffffff" mask="url(#outline"##),
+            "{svg}"
+        );
+        assert!(
+            svg.contains(r#"<circle cx="50" cy="50" r="22" fill="currentColor"/>"#),
+            "{svg}"
+        );
+        assert!(
+            svg.contains(r#"<circle cx="50" cy="50" r="18" fill="black"/>"#),
+            "{svg}"
+        );
+        assert!(svg.contains(
+            r#"<path d="M60 40L100 40L80 80Z" fill="currentColor" stroke="currentColor" stroke-width="4" stroke-linejoin="round"/>"#
+        ), "{svg}");
+        // Polygons are shrunk by masking out a stroke along their edge
+        assert!(svg.contains(
+            r#"<path d="M60 40L100 40L80 80Z" fill="white" stroke="black" stroke-width="4"/></mask>"#
+        ), "{svg}");
+        assert!(
+            svg.contains(r#"<path d="M60 40L100 40L80 80Z" fill="black" mask="url(#shrink"#),
+            "{svg}"
+        );
+    }
+
+    #[test]
+    fn writes_wrapping_circle_sectors() {
+        // From 5.5 over zero to 0.8, less than half a turn
+        let svg = export_frame(|painter| {
+            painter.add_filled_circle_sector((100.0, 100.0), 20.0, 50.0, 5.5, 0.8, Vec4::ONE);
+        });
+        assert!(svg.contains("M135.433 64.723A50 50 0 0 1 "), "{svg}");
+        assert!(svg.contains("A20 20 0 0 0 114.173 85.889Z"), "{svg}");
+
+        // From 0.5 to 0.2, almost a full turn
+        let svg = export_frame(|painter| {
+            painter.add_filled_circle_sector((100.0, 100.0), 20.0, 50.0, 0.5, 0.2, Vec4::ONE);
+        });
+        assert!(svg.contains("A50 50 0 1 1 "), "{svg}");
+        assert!(svg.contains("A20 20 0 1 0 "), "{svg}");
+
+        // A full turn is a ring, cut out with the even-odd rule
+        let svg = export_frame(|painter| {
+            painter.add_filled_circle_sector((100.0, 100.0), 20.0, 50.0, 0.0, -1e-7, Vec4::ONE);
+        });
+        assert!(svg.contains(
+            r#"<path d="M150 100A50 50 0 1 1 50 100A50 50 0 1 1 150 100ZM120 100A20 20 0 1 1 80 100A20 20 0 1 1 120 100Z" fill="currentColor" fill-rule="evenodd"/>"#
+        ), "{svg}");
+    }
+
+    #[test]
+    fn writes_shadows_and_glows() {
+        let svg = export_frame(|painter| {
+            painter
+                .add_filled_circle((50.0, 50.0), 10.0, Vec4::ONE)
+                .with_shadow(0.5, 6.0);
+        });
+        assert!(
+            svg.contains(r#"<feGaussianBlur in="SourceAlpha" stdDeviation="2"/>"#),
+            "{svg}"
+        );
+        assert!(
+            svg.contains(r##"<feFlood flood-color="#000000" flood-opacity="0.5"/>"##),
+            "{svg}"
+        );
+        assert!(
+            svg.contains(r##"<use href="#group1" filter="url(#glow2)"/>"##),
+            "{svg}"
+        );
+        assert!(
+            svg.contains(r##"<use href="#group1" color="#ffffff"/>"##),
+            "{svg}"
+        );
+
+        let svg = export_frame(|painter| {
+            painter
+                .add_filled_circle((50.0, 50.0), 10.0, Vec4::ONE)
+                .with_glow(Vec3::new(0.0, 0.3, 0.4), 9.0);
+        });
+        // Glows are drawn in their normalized color, at the strength given by its length
+        assert!(
+            svg.contains(r#"<feGaussianBlur in="SourceAlpha" stdDeviation="3"/>"#),
+            "{svg}"
+        );
+        assert!(
+            svg.contains(r##"<feFlood flood-color="#0099cc" flood-opacity="0.5"/>"##),
+            "{svg}"
+        );
+
+        // Invisible groups are still written for their glow
+        let svg = export_frame(|painter| {
+            painter
+                .add_filled_circle((50.0, 50.0), 10.0, Vec4::ZERO)
+                .with_glow(Vec3::ONE, 4.0);
+        });
+        assert!(
+            svg.contains(r##"<use href="#group1" filter="url(#glow2)"/>"##),
+            "{svg}"
+        );
+        assert!(!svg.contains(r##"<use href="#group1" color"##), "{svg}");
+    }
+
+    #[test]
+    fn skips_sdf_textured_groups() {
+        let svg = export_frame(|painter| {
+            painter
+                .add_filled_rect((10.0, 10.0), (50.0, 30.0), 0.0, Vec4::ONE)
+                .with_texture_id(TextureId::default())
+                .with_texture_is_mtsdf();
+            painter
+                .add_filled_circle((80.0, 80.0), 10.0, Vec4::new(1.0, 0.0, 0.0, 1.0))
+                .with_texture_id(TextureId::default());
+        });
+        assert!(!svg.contains(r#"width="40""#), "{svg}");
+        // Other textured groups are filled with their color
+        assert!(svg.contains(r##"<g color="#ff0000">"##), "{svg}");
+        assert!(
+            svg.contains(r#"<circle cx="80" cy="80" r="10" fill="currentColor"/>"#),
+            "{svg}"
+        );
+    }
+
+    #[test]
+    fn round_trips_through_import() {
+        let paint = |painter: &mut Painter| {
+            painter.add_filled_circle((50.0, 60.0), 20.0, Vec4::new(1.0, 0.0, 0.0, 1.0));
+            painter.add_filled_rect(
+                (100.0, 20.0),
+                (180.0, 60.0),
+                8.0,
+                Vec4::new(0.0, 1.0, 0.0, 1.0),
+            );
+            painter.add_filled_rect(
+                (20.0, 120.0),
+                (60.0, 180.0),
+                0.0,
+                Vec4::new(0.0, 0.0, 1.0, 0.6),
+            );
+            painter.add_filled_triangle(
+                (100.0, 100.0),
+                (180.0, 100.0),
+                (140.0, 180.0),
+                Vec4::new(1.0, 1.0, 0.0, 1.0),
+            );
+        };
+        let image = SvgImage::parse(&export_frame(paint)).unwrap();
+        assert_eq!(image.size, Vec2::new(200.0, 200.0));
+        // Translucent groups are written with a group opacity, which is exact for groups of a single shape
+        assert_eq!(
+            image.unsupported(),
+            ["group opacity (applied to each shape)"]
+        );
+
+        let imported = painted(|painter| image.paint(painter, Vec2::ZERO, 1.0));
+        let original = painted(paint);
+        assert_eq!(imported.len(), original.len());
+        for ((imported, imported_color), (original, original_color)) in
+            imported.iter().zip(&original)
+        {
+            assert!(
+                (*imported_color - *original_color).abs().max_element() < 0.01,
+                "{imported_color} != {original_color}"
+            );
+            let (a, b) = (imported.bounds(), original.bounds());
+            assert!(
+                a.min.abs_diff_eq(b.min, 1e-3) && a.max.abs_diff_eq(b.max, 1e-3),
+                "{imported:?} != {original:?}"
+            );
+            if !matches!(original, Primitive::Triangle { .. }) {
+                assert_eq!(imported, original);
+            }
+        }
+    }
+}
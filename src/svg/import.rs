@@ -0,0 +1,923 @@
+//! Reading SVG documents into shape groups
+
+use glam::{Affine2, Vec2, Vec4, vec2};
+use quick_xml::{
+    Reader,
+    events::{BytesStart, Event},
+};
+
+use super::{
+    Item, SvgImage, is_similarity,
+    path::{self, CenterArc, Segment, SubPath},
+    tessellate::triangulate,
+    transform_primitive,
+};
+use crate::{
+    error::{Error, SvgError},
+    shape::{GroupStyle, Primitive},
+};
+
+/// Units of absolute lengths, in pixels
+const UNITS: [(&str, f32); 6] = [
+    ("px", 1.0),
+    ("pt", 4.0 / 3.0),
+    ("pc", 16.0),
+    ("mm", 96.0 / 25.4),
+    ("cm", 96.0 / 2.54),
+    ("in", 96.0),
+];
+
+impl SvgImage {
+    /// Parses an SVG document. Only malformed XML and documents without an `<svg>` root are errors, everything that
+    /// can't be imported is reported in [`SvgImage::unsupported`] instead.
+    pub fn parse(svg: &str) -> Result<Self, Error> {
+        let mut importer = Importer::default();
+        importer.read(svg)?;
+        Ok(importer.image)
+    }
+}
+
+#[derive(Default)]
+struct Importer {
+    image: SvgImage,
+    root_seen: bool,
+}
+
+/// Properties inherited from parent elements
+#[derive(Clone, Debug)]
+struct Style {
+    fill: Paint,
+    stroke: Paint,
+    /// Value of `currentColor`
+    color: Vec4,
+    fill_opacity: f32,
+    stroke_opacity: f32,
+    stroke_width: f32,
+    /// Opacity of the element multiplied with its ancestors'
+    opacity: f32,
+    visible: bool,
+    /// Maps the element's coordinates to image coordinates
+    transform: Affine2,
+}
+
+impl Default for Style {
+    fn default() -> Self {
+        Self {
+            fill: Paint::Color(Vec4::new(0.0, 0.0, 0.0, 1.0)),
+            stroke: Paint::None,
+            color: Vec4::new(0.0, 0.0, 0.0, 1.0),
+            fill_opacity: 1.0,
+            stroke_opacity: 1.0,
+            stroke_width: 1.0,
+            opacity: 1.0,
+            visible: true,
+            transform: Affine2::IDENTITY,
+        }
+    }
+}
+
+impl Style {
+    fn color(&self, paint: Paint, opacity: f32) -> Option<Vec4> {
+        let mut color = match paint {
+            Paint::None => return None,
+            Paint::Color(color) => color,
+            Paint::CurrentColor => self.color,
+        };
+        color.w *= opacity * self.opacity;
+        (self.visible && color.w > 0.0).then_some(color)
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Paint {
+    None,
+    Color(Vec4),
+    CurrentColor,
+}
+
+/// Attributes of an element, with declarations from its `style` attribute after and so overriding the presentation
+/// attributes
+struct Attributes(Vec<(String, String)>);
+
+impl Attributes {
+    fn read(element: &BytesStart) -> Result<Self, Error> {
+        let mut attributes = Vec::new();
+        let mut declarations = Vec::new();
+        for attribute in element.attributes() {
+            let attribute =
+                attribute.map_err(|e| Error::InvalidSvg(SvgError::Attribute(e.to_string())))?;
+            let key = String::from_utf8_lossy(attribute.key.as_ref()).into_owned();
+            let value = attribute
+                .unescape_value()
+                .map_err(|e| Error::InvalidSvg(SvgError::Attribute(e.to_string())))?
+                .into_owned();
+            if key == "style" {
+                declarations.extend(value.split(';').filter_map(|declaration| {
+                    let (key, value) = declaration.split_once(':')?;
+                    let value = value.trim().trim_end_matches("!important").trim_end();
+                    Some((key.trim().to_owned(), value.to_owned()))
+                }));
+            } else {
+                attributes.push((key, value));
+            }
+        }
+        attributes.extend(declarations);
+        Ok(Self(attributes))
+    }
+
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0
+            .iter()
+            .rev()
+            .find(|(k, _)| k == key)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+impl Importer {
+    fn read(&mut self, svg: &str) -> Result<(), Error> {
+        let mut reader = Reader::from_str(svg);
+        // Styles of the open elements, and whether each one was imported as a group
+        let mut open: Vec<(Style, bool)> = Vec::new();
+        // Depth inside of an element whose content is skipped
+        let mut skipped = 0;
+        loop {
+            let event = reader.read_event().map_err(|e| {
+                Error::InvalidSvg(SvgError::Syntax {
+                    position: reader.error_position(),
+                    message: e.to_string(),
+                })
+            })?;
+            match event {
+                Event::Start(_) if skipped > 0 => skipped += 1,
+                Event::End(_) if skipped > 0 => skipped -= 1,
+                Event::Start(element) => {
+                    let parent = open.last().map(|(style, _)| style.clone());
+                    match self.element(&element, parent.unwrap_or_default(), true)? {
+                        Some(open_element) => open.push(open_element),
+                        None => skipped = 1,
+                    }
+                }
+                Event::Empty(element) if skipped == 0 => {
+                    let parent = open.last().map(|(style, _)| style.clone());
+                    self.element(&element, parent.unwrap_or_default(), false)?;
+                }
+                Event::End(_) => {
+                    if let Some((_, true)) = open.pop() {
+                        self.image.items.push(Item::EndGroup);
+                    }
+                }
+                Event::Eof => break,
+                _ => {}
+            }
+        }
+        if !self.root_seen {
+            return Err(Error::InvalidSvg(SvgError::MissingRoot));
+        }
+        Ok(())
+    }
+
+    /// Imports an element. Returns the element's style and whether it was imported as a group, or `None` if the
+    /// element's content should be skipped.
+    fn element(
+        &mut self,
+        element: &BytesStart,
+        parent: Style,
+        has_content: bool,
+    ) -> Result<Option<(Style, bool)>, Error> {
+        let qualified_name = element.name();
+        if qualified_name
+            .prefix()
+            .is_some_and(|prefix| prefix.as_ref() != b"svg")
+        {
+            // Elements from other namespaces, eg. editor metadata
+            return Ok(None);
+        }
+        let local_name = element.local_name();
+        let name = String::from_utf8_lossy(local_name.as_ref());
+        let attributes = Attributes::read(element)?;
+
+        if !self.root_seen {
+            if name != "svg" {
+                return Err(Error::InvalidSvg(SvgError::UnexpectedRoot(
+                    name.into_owned(),
+                )));
+            }
+            self.root_seen = true;
+            let mut style = self.style(parent, &attributes);
+            style.transform = self.viewport(&attributes) * style.transform;
+            return Ok(Some((style, false)));
+        }
+
+        if attributes.get("display") == Some("none") {
+            return Ok(None);
+        }
+        let style = self.style(parent, &attributes);
+        match &*name {
+            "g" | "a" | "svg" => {
+                if name == "svg" {
+                    self.report("nested <svg> viewports (drawn as groups)");
+                }
+                if has_content && attributes.get("opacity").is_some() {
+                    self.report("group opacity (applied to each shape)");
+                }
+                if has_content {
+                    self.image.items.push(Item::BeginGroup);
+                }
+                Ok(Some((style, has_content)))
+            }
+            "rect" | "circle" | "ellipse" | "line" | "polyline" | "polygon" | "path" => {
+                self.shape(&name, &attributes, &style);
+                Ok(Some((style, false)))
+            }
+            // Definitions are only drawn where they are referenced, which is reported there
+            "title" | "desc" | "metadata" | "defs" | "symbol" | "clipPath" | "mask" | "marker"
+            | "pattern" | "linearGradient" | "radialGradient" | "filter" => Ok(None),
+            "style" => {
+                self.report("<style> sheets");
+                Ok(None)
+            }
+            _ => {
+                self.report(format!("<{name}> elements"));
+                Ok(None)
+            }
+        }
+    }
+
+    fn style(&mut self, parent: Style, attributes: &Attributes) -> Style {
+        let mut style = parent;
+        let property = |key| attributes.get(key).filter(|&value| value != "inherit");
+
+        if let Some(color) = property("color") {
+            match parse_color(color) {
+                Some(color) => style.color = color,
+                None => self.report(format!("color `{color}`")),
+            }
+        }
+        if let Some(fill) = property("fill") {
+            style.fill = self.paint(fill);
+        }
+        if let Some(stroke) = property("stroke") {
+            style.stroke = self.paint(stroke);
+        }
+        if let Some(opacity) = property("fill-opacity").and_then(parse_opacity) {
+            style.fill_opacity = opacity;
+        }
+        if let Some(opacity) = property("stroke-opacity").and_then(parse_opacity) {
+            style.stroke_opacity = opacity;
+        }
+        if let Some(opacity) = property("opacity").and_then(parse_opacity) {
+            style.opacity *= opacity;
+        }
+        if let Some(width) = property("stroke-width").and_then(|width| self.length(width)) {
+            style.stroke_width = width;
+        }
+        if let Some(visibility) = property("visibility") {
+            style.visible = visibility == "visible";
+        }
+        if let Some(transform) = attributes.get("transform") {
+            style.transform *= self.transform(transform);
+        }
+
+        if property("stroke-dasharray").is_some_and(|value| value != "none") {
+            self.report("dashed strokes (drawn solid)");
+        }
+        for (key, feature) in [
+            ("clip-path", "clip paths"),
+            ("mask", "masks"),
+            ("filter", "filters"),
+            ("marker-start", "markers"),
+            ("marker-mid", "markers"),
+            ("marker-end", "markers"),
+        ] {
+            if property(key).is_some_and(|value| value != "none") {
+                self.report(feature);
+            }
+        }
+        style
+    }
+
+    fn paint(&mut self, value: &str) -> Paint {
+        let value = value.trim();
+        if value == "none" {
+            return Paint::None;
+        }
+        if value == "currentColor" {
+            return Paint::CurrentColor;
+        }
+        if let Some(reference) = value.strip_prefix("url(") {
+            self.report("gradient and pattern paints");
+            // Use the fallback color after the reference, if there is one
+            return match reference.split_once(')') {
+                Some((_, fallback)) if !fallback.trim().is_empty() => self.paint(fallback),
+                _ => Paint::None,
+            };
+        }
+        match parse_color(value) {
+            Some(color) => Paint::Color(color),
+            None => {
+                self.report(format!("color `{value}`"));
+                Paint::None
+            }
+        }
+    }
+
+    fn length(&mut self, value: &str) -> Option<f32> {
+        let value = value.trim();
+        if value.ends_with('%') || value.ends_with("em") || value.ends_with("ex") {
+            self.report("relative lengths");
+            return None;
+        }
+        let (number, scale) = UNITS
+            .iter()
+            .find_map(|&(unit, scale)| value.strip_suffix(unit).map(|number| (number, scale)))
+            .unwrap_or((value, 1.0));
+        number
+            .trim()
+            .parse::<f32>()
+            .ok()
+            .map(|number| number * scale)
+    }
+
+    fn number(&mut self, attributes: &Attributes, key: &str) -> Option<f32> {
+        attributes.get(key).and_then(|value| self.length(value))
+    }
+
+    /// Maps the root element's view box onto its size, and sets the image size
+    fn viewport(&mut self, attributes: &Attributes) -> Affine2 {
+        let width = self.number(attributes, "width");
+        let height = self.number(attributes, "height");
+        let view_box = attributes
+            .get("viewBox")
+            .map(path::parse_numbers)
+            .filter(|v| v.len() == 4 && v[2] > 0.0 && v[3] > 0.0);
+        let Some(view_box) = view_box else {
+            self.image.size = vec2(width.unwrap_or(0.0), height.unwrap_or(0.0));
+            return Affine2::IDENTITY;
+        };
+
+        let (origin, view_size) = (
+            vec2(view_box[0], view_box[1]),
+            vec2(view_box[2], view_box[3]),
+        );
+        // A missing width or height follows from the other one and the view box's aspect ratio
+        let size = match (width, height) {
+            (Some(width), Some(height)) => vec2(width, height),
+            (Some(width), None) => vec2(width, width * view_size.y / view_size.x),
+            (None, Some(height)) => vec2(height * view_size.x / view_size.y, height),
+            (None, None) => view_size,
+        };
+        self.image.size = size;
+
+        let to_origin = Affine2::from_translation(-origin);
+        let aspect_ratio = attributes
+            .get("preserveAspectRatio")
+            .unwrap_or("xMidYMid")
+            .trim();
+        if aspect_ratio == "none" {
+            return Affine2::from_scale(size / view_size) * to_origin;
+        }
+        if aspect_ratio != "xMidYMid" && aspect_ratio != "xMidYMid meet" {
+            self.report(
+                "preserveAspectRatio alignments other than `xMidYMid meet` (drawn centered)",
+            );
+        }
+        let scale = (size / view_size).min_element();
+        Affine2::from_translation((size - view_size * scale) / 2.0)
+            * Affine2::from_scale(Vec2::splat(scale))
+            * to_origin
+    }
+
+    fn transform(&mut self, value: &str) -> Affine2 {
+        let mut transform = Affine2::IDENTITY;
+        let mut rest = value;
+        while let Some((name, tail)) = rest.split_once('(') {
+            let Some((arguments, tail)) = tail.split_once(')') else {
+                break;
+            };
+            let name = name.trim_matches(|c: char| c.is_whitespace() || c == ',');
+            let t = match (name, path::parse_numbers(arguments).as_slice()) {
+                ("matrix", &[a, b, c, d, e, f]) => Affine2::from_cols_array(&[a, b, c, d, e, f]),
+                ("translate", &[x]) => Affine2::from_translation(vec2(x, 0.0)),
+                ("translate", &[x, y]) => Affine2::from_translation(vec2(x, y)),
+                ("scale", &[s]) => Affine2::from_scale(Vec2::splat(s)),
+                ("scale", &[x, y]) => Affine2::from_scale(vec2(x, y)),
+                ("rotate", &[angle]) => Affine2::from_angle(angle.to_radians()),
+                ("rotate", &[angle, x, y]) => {
+                    Affine2::from_translation(vec2(x, y))
+                        * Affine2::from_angle(angle.to_radians())
+                        * Affine2::from_translation(vec2(-x, -y))
+                }
+                ("skewX", &[angle]) => {
+                    Affine2::from_cols_array(&[1.0, 0.0, angle.to_radians().tan(), 1.0, 0.0, 0.0])
+                }
+                ("skewY", &[angle]) => {
+                    Affine2::from_cols_array(&[1.0, angle.to_radians().tan(), 0.0, 1.0, 0.0, 0.0])
+                }
+                _ => {
+                    // Invalid transforms are ignored as a whole
+                    self.report(format!("transform `{}`", value.trim()));
+                    return Affine2::IDENTITY;
+                }
+            };
+            transform *= t;
+            rest = tail;
+        }
+        transform
+    }
+
+    fn shape(&mut self, name: &str, attributes: &Attributes, style: &Style) {
+        let mut number = |key| self.number(attributes, key);
+        let (primitive, subpaths) = match name {
+            "rect" => {
+                let position = vec2(number("x").unwrap_or(0.0), number("y").unwrap_or(0.0));
+                let size = vec2(
+                    number("width").unwrap_or(0.0),
+                    number("height").unwrap_or(0.0),
+                );
+                if size.x <= 0.0 || size.y <= 0.0 {
+                    return;
+                }
+                // A missing corner radius is the same as the other one
+                let (rx, ry) = (number("rx"), number("ry"));
+                let radii = vec2(rx.or(ry).unwrap_or(0.0), ry.or(rx).unwrap_or(0.0))
+                    .max(Vec2::ZERO)
+                    .min(size / 2.0);
+                let primitive = (radii.x == radii.y).then(|| Primitive::Rect {
+                    center: position + size / 2.0,
+                    half_extents: size / 2.0,
+                    corner_radius: radii.x.into(),
+                });
+                (primitive, vec![rect_path(position, size, radii)])
+            }
+            "circle" | "ellipse" => {
+                let center = vec2(number("cx").unwrap_or(0.0), number("cy").unwrap_or(0.0));
+                let radii = if name == "circle" {
+                    Vec2::splat(number("r").unwrap_or(0.0))
+                } else {
+                    let (rx, ry) = (number("rx"), number("ry"));
+                    vec2(rx.or(ry).unwrap_or(0.0), ry.or(rx).unwrap_or(0.0))
+                };
+                if radii.x <= 0.0 || radii.y <= 0.0 {
+                    return;
+                }
+                let primitive = (radii.x == radii.y).then_some(Primitive::Circle {
+                    center,
+                    radius: radii.x,
+                });
+                (primitive, vec![ellipse_path(center, radii)])
+            }
+            "line" => {
+                let p1 = vec2(number("x1").unwrap_or(0.0), number("y1").unwrap_or(0.0));
+                let p2 = vec2(number("x2").unwrap_or(0.0), number("y2").unwrap_or(0.0));
+                let mut subpath = SubPath::new(p1);
+                subpath.segments.push(Segment::Line(p2));
+                (None, vec![subpath])
+            }
+            "polyline" | "polygon" => {
+                let numbers = path::parse_numbers(attributes.get("points").unwrap_or_default());
+                let mut points = numbers.chunks_exact(2).map(|p| vec2(p[0], p[1]));
+                let Some(start) = points.next() else {
+                    return;
+                };
+                let mut subpath = SubPath::new(start);
+                subpath.segments.extend(points.map(Segment::Line));
+                if name == "polygon" {
+                    subpath.close();
+                }
+                (None, vec![subpath])
+            }
+            _ => {
+                let (subpaths, error) = path::parse(attributes.get("d").unwrap_or_default());
+                if let Some(error) = error {
+                    self.report(format!(
+                        "malformed path data, drawn up to the error: {error}"
+                    ));
+                }
+                (None, subpaths)
+            }
+        };
+        self.add_shape(style, primitive, &subpaths);
+    }
+
+    /// Adds the fill and the stroke of an element. Its primitive, if it has one, is used for both, and its subpaths
+    /// otherwise.
+    fn add_shape(&mut self, style: &Style, primitive: Option<Primitive>, subpaths: &[SubPath]) {
+        let primitive = primitive.and_then(|p| transform_primitive(&p, &style.transform));
+        if let Some(color) = style.color(style.fill, style.fill_opacity) {
+            let primitives = match primitive {
+                Some(primitive) => vec![primitive],
+                None => self.fill(subpaths, &style.transform),
+            };
+            self.push_shape(GroupStyle::new(color), primitives);
+        }
+        if let Some(color) = style.color(style.stroke, style.stroke_opacity)
+            && style.stroke_width > 0.0
+        {
+            if !is_similarity(&style.transform.matrix2) {
+                self.report("non-uniformly scaled strokes (drawn with an average width)");
+            }
+            let width = style.stroke_width * style.transform.matrix2.determinant().abs().sqrt();
+            let primitives = match primitive {
+                Some(primitive) => vec![primitive],
+                None => stroke(subpaths, &style.transform),
+            };
+            self.push_shape(GroupStyle::new(color).with_line_width(width), primitives);
+        }
+    }
+
+    fn push_shape(&mut self, style: GroupStyle, primitives: Vec<Primitive>) {
+        if !primitives.is_empty() {
+            self.image.items.push(Item::Shape { style, primitives });
+        }
+    }
+
+    /// Triangulates the area of each subpath
+    fn fill(&mut self, subpaths: &[SubPath], transform: &Affine2) -> Vec<Primitive> {
+        let mut primitives = Vec::new();
+        let mut bounds: Vec<(Vec2, Vec2)> = Vec::new();
+        for subpath in subpaths {
+            let points = subpath.flatten(transform);
+            let (triangles, simple) = triangulate(&points);
+            if !simple {
+                self.report("self-intersecting fills (approximated)");
+            }
+            if triangles.is_empty() {
+                continue;
+            }
+            primitives.extend(
+                triangles
+                    .into_iter()
+                    .map(|[p1, p2, p3]| Primitive::Triangle { p1, p2, p3 }),
+            );
+
+            let min = points.iter().fold(Vec2::INFINITY, |min, &p| min.min(p));
+            let max = points.iter().fold(Vec2::NEG_INFINITY, |max, &p| max.max(p));
+            if bounds.iter().any(|&(other_min, other_max)| {
+                min.cmple(other_max).all() && max.cmpge(other_min).all()
+            }) {
+                self.report("overlapping subpaths (filled as their union, without holes)");
+            }
+            bounds.push((min, max));
+        }
+        primitives
+    }
+
+    fn report(&mut self, feature: impl Into<String>) {
+        let feature = feature.into();
+        if !self.image.unsupported.contains(&feature) {
+            self.image.unsupported.push(feature);
+        }
+    }
+}
+
+/// Outlines each subpath with lines, and with zero width circle sectors for circular arcs. The lines take the width
+/// of the stroke from their group's outline.
+fn stroke(subpaths: &[SubPath], transform: &Affine2) -> Vec<Primitive> {
+    let circles_stay_circular = is_similarity(&transform.matrix2);
+    let mut primitives = Vec::new();
+    for subpath in subpaths {
+        let mut from = subpath.start;
+        for segment in &subpath.segments {
+            let arc = match *segment {
+                Segment::Arc {
+                    radii,
+                    rotation,
+                    large_arc,
+                    sweep,
+                    to,
+                } if circles_stay_circular && radii.x.abs() == radii.y.abs() => {
+                    CenterArc::from_endpoints(from, radii, rotation, large_arc, sweep, to)
+                }
+                _ => None,
+            };
+            if let Some(arc) = arc {
+                // The arc is circular, so its rotation only offsets its angles
+                let start = arc.start_angle + arc.rotation;
+                let end = start + arc.sweep_angle;
+                let sector = Primitive::CircleSector {
+                    center: arc.center,
+                    radius_inner: arc.radii.x,
+                    radius_outer: arc.radii.x,
+                    angle_start: start.min(end),
+                    angle_end: start.max(end),
+                };
+                primitives.extend(transform_primitive(&sector, transform));
+            } else {
+                let mut points = vec![transform.transform_point2(from)];
+                path::flatten_segment(from, segment, transform, &mut points);
+                primitives.extend(points.windows(2).filter(|line| line[0] != line[1]).map(
+                    |line| Primitive::Line {
+                        p1: line[0],
+                        p2: line[1],
+                    },
+                ));
+            }
+            from = segment.end();
+        }
+    }
+    primitives
+}
+
+fn rect_path(position: Vec2, size: Vec2, radii: Vec2) -> SubPath {
+    let (min, max) = (position, position + size);
+    let corners = [
+        (vec2(max.x - radii.x, min.y), vec2(max.x, min.y + radii.y)),
+        (vec2(max.x, max.y - radii.y), vec2(max.x - radii.x, max.y)),
+        (vec2(min.x + radii.x, max.y), vec2(min.x, max.y - radii.y)),
+        (vec2(min.x, min.y + radii.y), vec2(min.x + radii.x, min.y)),
+    ];
+    let mut subpath = SubPath::new(corners[3].1);
+    for (corner_start, corner_end) in corners {
+        subpath.segments.push(Segment::Line(corner_start));
+        if radii.x > 0.0 && radii.y > 0.0 {
+            subpath.segments.push(Segment::Arc {
+                radii,
+                rotation: 0.0,
+                large_arc: false,
+                sweep: true,
+                to: corner_end,
+            });
+        }
+    }
+    subpath.close();
+    subpath
+}
+
+fn ellipse_path(center: Vec2, radii: Vec2) -> SubPath {
+    let mut subpath = SubPath::new(center + vec2(radii.x, 0.0));
+    for to in [center - vec2(radii.x, 0.0), center + vec2(radii.x, 0.0)] {
+        subpath.segments.push(Segment::Arc {
+            radii,
+            rotation: 0.0,
+            large_arc: false,
+            sweep: true,
+            to,
+        });
+    }
+    subpath.closed = true;
+    subpath
+}
+
+/// Parses an opacity, either as a number or as a percentage
+fn parse_opacity(value: &str) -> Option<f32> {
+    let opacity = match value.trim().strip_suffix('%') {
+        Some(percentage) => percentage.parse::<f32>().ok()? / 100.0,
+        None => value.trim().parse().ok()?,
+    };
+    Some(opacity.clamp(0.0, 1.0))
+}
+
+/// Parses a CSS color in hex notation, `rgb()` notation, or one of the basic named colors
+fn parse_color(value: &str) -> Option<Vec4> {
+    let value = value.trim();
+    if let Some(hex) = value.strip_prefix('#') {
+        let digits: Vec<u32> = hex.chars().map(|c| c.to_digit(16)).collect::<Option<_>>()?;
+        let channels: Vec<f32> = match digits.len() {
+            3 | 4 => digits.iter().map(|&d| (d * 17) as f32 / 255.0).collect(),
+            6 | 8 => digits
+                .chunks(2)
+                .map(|d| (d[0] * 16 + d[1]) as f32 / 255.0)
+                .collect(),
+            _ => return None,
+        };
+        return Some(Vec4::new(
+            channels[0],
+            channels[1],
+            channels[2],
+            channels.get(3).copied().unwrap_or(1.0),
+        ));
+    }
+
+    if let Some(arguments) = value
+        .strip_prefix("rgba(")
+        .or_else(|| value.strip_prefix("rgb("))
+        .and_then(|arguments| arguments.strip_suffix(')'))
+    {
+        let arguments: Vec<&str> = arguments
+            .split([',', ' ', '/'])
+            .filter(|argument| !argument.is_empty())
+            .collect();
+        let channel = |argument: &str| match argument.strip_suffix('%') {
+            Some(percentage) => percentage.parse::<f32>().ok().map(|p| p / 100.0),
+            None => argument.parse::<f32>().ok().map(|c| c / 255.0),
+        };
+        let (r, g, b) = match arguments[..] {
+            [r, g, b] | [r, g, b, _] => (channel(r)?, channel(g)?, channel(b)?),
+            _ => return None,
+        };
+        let alpha = match arguments.get(3) {
+            Some(alpha) => parse_opacity(alpha)?,
+            None => 1.0,
+        };
+        return Some(Vec4::new(r, g, b, alpha).clamp(Vec4::ZERO, Vec4::ONE));
+    }
+
+    let rgb: u32 = match value.to_ascii_lowercase().as_str() {
+        "transparent" => return Some(Vec4::ZERO),
+        "black" => 0x000000,
+        "white" => 0xffffff,
+        "red" => 0xff0000,
+        "lime" => 0x00ff00,
+        "blue" => 0x0000ff,
+        "yellow" => 0xffff00,
+        "cyan" | "aqua" => 0x00ffff,
+        "magenta" | "fuchsia" => 0xff00ff,
+        "gray" | "grey" => 0x808080,
+        "silver" => 0xc0c0c0,
+        "maroon" => 0x800000,
+        "olive" => 0x808000,
+        "green" => 0x008000,
+        "purple" => 0x800080,
+        "teal" => 0x008080,
+        "navy" => 0x000080,
+        "orange" => 0xffa500,
+        _ => return None,
+    };
+    let [_, r, g, b] = rgb.to_be_bytes();
+    Some(Vec4::new(
+        r as f32 / 255.0,
+        g as f32 / 255.0,
+        b as f32 / 255.0,
+        1.0,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rgba(r: u8, g: u8, b: u8, a: u8) -> Vec4 {
+        Vec4::new(r as f32, g as f32, b as f32, a as f32) / 255.0
+    }
+
+    fn attributes(pairs: &[(&str, &str)]) -> Attributes {
+        Attributes(
+            pairs
+                .iter()
+                .map(|&(key, value)| (key.to_owned(), value.to_owned()))
+                .collect(),
+        )
+    }
+
+    fn assert_maps(transform: Affine2, from: (f32, f32), to: (f32, f32)) {
+        let mapped = transform.transform_point2(from.into());
+        assert!(
+            mapped.abs_diff_eq(to.into(), 1e-4),
+            "{from:?} is mapped to {mapped}, expected {to:?}"
+        );
+    }
+
+    #[test]
+    fn parses_hex_colors() {
+        assert_eq!(parse_color("#f00"), Some(rgba(255, 0, 0, 255)));
+        assert_eq!(parse_color(" #F0F8 "), Some(rgba(255, 0, 255, 136)));
+        assert_eq!(parse_color("#336699"), Some(rgba(0x33, 0x66, 0x99, 255)));
+        assert_eq!(parse_color("#ff000080"), Some(rgba(255, 0, 0, 128)));
+        assert_eq!(parse_color("#ff00"), Some(rgba(255, 255, 0, 0)));
+        assert_eq!(parse_color("#ff"), None);
+        assert_eq!(parse_color("#ggg"), None);
+    }
+
+    #[test]
+    fn parses_rgb_colors() {
+        assert_eq!(parse_color("rgb(255, 0, 51)"), Some(rgba(255, 0, 51, 255)));
+        assert_eq!(
+            parse_color("rgb(100%, 50%, 0%)"),
+            Some(Vec4::new(1.0, 0.5, 0.0, 1.0))
+        );
+        assert_eq!(
+            parse_color("rgba(0,0,255,0.5)"),
+            Some(Vec4::new(0.0, 0.0, 1.0, 0.5))
+        );
+        assert_eq!(
+            parse_color("rgb(0 0 255 / 25%)"),
+            Some(Vec4::new(0.0, 0.0, 1.0, 0.25))
+        );
+        // Out of range channels are clamped
+        assert_eq!(
+            parse_color("rgb(300, -5, 0)"),
+            Some(Vec4::new(1.0, 0.0, 0.0, 1.0))
+        );
+        assert_eq!(parse_color("rgb(1, 2)"), None);
+        assert_eq!(parse_color("rgb(1, 2, x)"), None);
+        assert_eq!(parse_color("rgb(1, 2, 3"), None);
+    }
+
+    #[test]
+    fn parses_named_colors() {
+        assert_eq!(parse_color("red"), Some(rgba(255, 0, 0, 255)));
+        assert_eq!(parse_color("Orange"), Some(rgba(255, 0xa5, 0, 255)));
+        assert_eq!(parse_color("grey"), parse_color("gray"));
+        assert_eq!(parse_color("transparent"), Some(Vec4::ZERO));
+        assert_eq!(parse_color("rebeccapurple"), None);
+        assert_eq!(parse_color(""), None);
+    }
+
+    #[test]
+    fn parses_transform_lists() {
+        let mut importer = Importer::default();
+
+        let t = importer.transform("translate(10 20) scale(2)");
+        assert_maps(t, (1.0, 1.0), (12.0, 22.0));
+        let t = importer.transform("translate(10,20),scale(2,3)");
+        assert_maps(t, (1.0, 1.0), (12.0, 23.0));
+        let t = importer.transform("translate(5)");
+        assert_maps(t, (1.0, 1.0), (6.0, 1.0));
+        let t = importer.transform("matrix(1 2 3 4 5 6)");
+        assert_maps(t, (1.0, 1.0), (9.0, 12.0));
+        let t = importer.transform("rotate(90)");
+        assert_maps(t, (1.0, 0.0), (0.0, 1.0));
+        let t = importer.transform("rotate(90 10 10)");
+        assert_maps(t, (20.0, 10.0), (10.0, 20.0));
+        let t = importer.transform("skewX(45)");
+        assert_maps(t, (1.0, 1.0), (2.0, 1.0));
+        let t = importer.transform("skewY(45)");
+        assert_maps(t, (1.0, 1.0), (1.0, 2.0));
+        assert!(importer.image.unsupported.is_empty());
+
+        // Invalid transforms are ignored as a whole, and reported
+        let t = importer.transform("translate(10) scale(1 2 3)");
+        assert_eq!(t, Affine2::IDENTITY);
+        assert_eq!(
+            importer.image.unsupported,
+            ["transform `translate(10) scale(1 2 3)`"]
+        );
+    }
+
+    #[test]
+    fn maps_the_view_box_to_the_viewport() {
+        let mut importer = Importer::default();
+
+        // Without a view box, the user space is the viewport
+        let t = importer.viewport(&attributes(&[("width", "1in"), ("height", "50")]));
+        assert_eq!(t, Affine2::IDENTITY);
+        assert_eq!(importer.image.size, vec2(96.0, 50.0));
+
+        // Uniformly scaled and centered by default
+        let t = importer.viewport(&attributes(&[
+            ("width", "200"),
+            ("height", "100"),
+            ("viewBox", "0 0 100 100"),
+        ]));
+        assert_eq!(importer.image.size, vec2(200.0, 100.0));
+        assert_maps(t, (0.0, 0.0), (50.0, 0.0));
+        assert_maps(t, (100.0, 100.0), (150.0, 100.0));
+
+        // Stretched to the viewport
+        let t = importer.viewport(&attributes(&[
+            ("width", "200"),
+            ("height", "100"),
+            ("viewBox", "-10 -10 100 100"),
+            ("preserveAspectRatio", "none"),
+        ]));
+        assert_maps(t, (-10.0, -10.0), (0.0, 0.0));
+        assert_maps(t, (90.0, 90.0), (200.0, 100.0));
+        assert!(importer.image.unsupported.is_empty());
+
+        // A missing height follows from the view box's aspect ratio
+        let t = importer.viewport(&attributes(&[
+            ("width", "50"),
+            ("viewBox", "10 10 100 200"),
+        ]));
+        assert_eq!(importer.image.size, vec2(50.0, 100.0));
+        assert_maps(t, (10.0, 10.0), (0.0, 0.0));
+        assert_maps(t, (110.0, 210.0), (50.0, 100.0));
+
+        // Without a size, the view box is the size
+        importer.viewport(&attributes(&[("viewBox", "0,0,30,40")]));
+        assert_eq!(importer.image.size, vec2(30.0, 40.0));
+
+        // Other alignments are drawn centered, and reported
+        let t = importer.viewport(&attributes(&[
+            ("width", "100"),
+            ("height", "200"),
+            ("viewBox", "0 0 100 100"),
+            ("preserveAspectRatio", "xMinYMin slice"),
+        ]));
+        assert_maps(t, (0.0, 0.0), (0.0, 50.0));
+        assert_eq!(importer.image.unsupported.len(), 1);
+    }
+
+    #[test]
+    fn rejects_malformed_documents() {
+        let reason = |svg: &str| match SvgImage::parse(svg) {
+            Err(Error::InvalidSvg(reason)) => reason,
+            result => panic!("{svg:?} parsed as {:?}", result.map(|_| ())),
+        };
+        assert_eq!(reason(""), SvgError::MissingRoot);
+        assert_eq!(reason("<?xml version=\"1.0\"?>"), SvgError::MissingRoot);
+        assert_eq!(
+            reason("<html><svg/></html>"),
+            SvgError::UnexpectedRoot("html".to_string())
+        );
+        assert!(matches!(
+            reason("<svg><rect width=\"1\" width=\"2\"/></svg>"),
+            SvgError::Attribute(_)
+        ));
+        assert!(matches!(
+            reason("<svg><rect></svg>"),
+            SvgError::Syntax { position: 11, .. }
+        ));
+    }
+}
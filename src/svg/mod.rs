@@ -0,0 +1,178 @@
+//! Conversion between SVG images and shapes, with the `svg` feature.
+//!
+//! [`SvgImage`] imports an SVG image to paint it with a [`Painter`]. Rects, circles, and ellipses with equal radii
+//! become their primitives. Other shapes are flattened into polygons, which are filled with triangles and stroked with
+//! lines, except for circular arcs which are stroked as circle sectors. Every filled or stroked element is drawn as its
+//! own styled group, and `<g>` elements become painter groups.
+//!
+//! Strokes always have round caps and joins. Features that are skipped or approximated are listed by
+//! [`SvgImage::unsupported`].
+//!
+//! [`export`] goes the other way, writing a painted frame as an SVG document.
+
+mod export;
+mod import;
+mod path;
+mod tessellate;
+
+use glam::{Affine2, Mat2, Vec2, vec2};
+
+pub use export::export;
+
+use crate::{
+    Painter,
+    shape::{CornerRadius, GroupStyle, Primitive},
+};
+
+/// Shapes imported from an SVG image, which can be painted any number of times
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SvgImage {
+    /// Size of the image, in pixels at a scale of 1
+    pub size: Vec2,
+    items: Vec<Item>,
+    unsupported: Vec<String>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Item {
+    BeginGroup,
+    EndGroup,
+    /// A filled or stroked element, in image coordinates
+    Shape {
+        style: GroupStyle,
+        primitives: Vec<Primitive>,
+    },
+}
+
+impl SvgImage {
+    /// Descriptions of the image's features that were skipped or only approximated, eg. gradients or text
+    pub fn unsupported(&self) -> &[String] {
+        &self.unsupported
+    }
+
+    /// Adds the image's shapes to the painter, with the top left corner of the image at `position` and scaled by
+    /// `scale`
+    pub fn paint(&self, painter: &mut Painter, position: impl Into<Vec2>, scale: f32) {
+        let transform =
+            Affine2::from_scale_angle_translation(Vec2::splat(scale), 0.0, position.into());
+        for item in &self.items {
+            match item {
+                Item::BeginGroup => painter.begin_group(),
+                Item::EndGroup => painter.end_group(),
+                Item::Shape { style, primitives } => {
+                    painter.begin_group_with_style(GroupStyle {
+                        line_width: style.line_width * scale,
+                        ..*style
+                    });
+                    for primitive in primitives {
+                        if let Some(primitive) = transform_primitive(primitive, &transform) {
+                            painter.add_primitive(primitive, style.color);
+                        }
+                    }
+                    painter.end_group();
+                }
+            }
+        }
+    }
+}
+
+/// Whether the transform only translates, rotates, mirrors and scales uniformly, which keeps circles circular
+fn is_similarity(matrix: &Mat2) -> bool {
+    let (sx, sy) = (matrix.x_axis.length(), matrix.y_axis.length());
+    let epsilon = 1e-4 * sx.max(sy);
+    (sx - sy).abs() <= epsilon && matrix.x_axis.dot(matrix.y_axis).abs() <= epsilon * sx.max(sy)
+}
+
+/// Whether the transform keeps the x and y axes in place, apart from scaling them
+fn is_axis_aligned(matrix: &Mat2) -> bool {
+    let epsilon = 1e-6 * matrix.x_axis.x.abs().max(matrix.y_axis.y.abs());
+    matrix.x_axis.y.abs() <= epsilon && matrix.y_axis.x.abs() <= epsilon
+}
+
+/// Applies an affine transform to a primitive, or returns `None` if the transformed shape isn't a primitive
+fn transform_primitive(primitive: &Primitive, transform: &Affine2) -> Option<Primitive> {
+    let matrix = transform.matrix2;
+    let point = |p: Vec2| transform.transform_point2(p);
+    Some(match *primitive {
+        Primitive::Line { p1, p2 } => Primitive::Line {
+            p1: point(p1),
+            p2: point(p2),
+        },
+        Primitive::Triangle { p1, p2, p3 } => Primitive::Triangle {
+            p1: point(p1),
+            p2: point(p2),
+            p3: point(p3),
+        },
+        Primitive::PolyQuad { points } => Primitive::PolyQuad {
+            points: points.map(point),
+        },
+        Primitive::Circle { center, radius } if is_similarity(&matrix) => Primitive::Circle {
+            center: point(center),
+            radius: radius * matrix.x_axis.length(),
+        },
+        Primitive::CircleSector {
+            center,
+            radius_inner,
+            radius_outer,
+            angle_start,
+            angle_end,
+        } if is_similarity(&matrix) => {
+            let scale = matrix.x_axis.length();
+            let rotation = matrix.x_axis.y.atan2(matrix.x_axis.x);
+            // Mirroring reverses the direction of the arc
+            let (angle_start, angle_end) = if matrix.determinant() < 0.0 {
+                (rotation - angle_end, rotation - angle_start)
+            } else {
+                (rotation + angle_start, rotation + angle_end)
+            };
+            Primitive::CircleSector {
+                center: point(center),
+                radius_inner: radius_inner * scale,
+                radius_outer: radius_outer * scale,
+                angle_start,
+                angle_end,
+            }
+        }
+        Primitive::Rect {
+            center,
+            half_extents,
+            corner_radius,
+        } if is_axis_aligned(&matrix)
+            && (corner_radius == CornerRadius::from(0.0) || is_similarity(&matrix)) =>
+        {
+            let scale = vec2(matrix.x_axis.x, matrix.y_axis.y);
+            let mut r = corner_radius;
+            if scale.x < 0.0 {
+                std::mem::swap(&mut r.top_left, &mut r.top_right);
+                std::mem::swap(&mut r.bottom_left, &mut r.bottom_right);
+            }
+            if scale.y < 0.0 {
+                std::mem::swap(&mut r.top_left, &mut r.bottom_left);
+                std::mem::swap(&mut r.top_right, &mut r.bottom_right);
+            }
+            let radius_scale = scale.x.abs();
+            Primitive::Rect {
+                center: point(center),
+                half_extents: half_extents * scale.abs(),
+                corner_radius: CornerRadius {
+                    top_left: r.top_left * radius_scale,
+                    top_right: r.top_right * radius_scale,
+                    bottom_right: r.bottom_right * radius_scale,
+                    bottom_left: r.bottom_left * radius_scale,
+                },
+            }
+        }
+        Primitive::Rect {
+            center,
+            half_extents,
+            corner_radius,
+        } if corner_radius == CornerRadius::from(0.0) => {
+            let (x, y) = (half_extents.x, half_extents.y);
+            Primitive::PolyQuad {
+                points: [vec2(-x, -y), vec2(x, -y), vec2(x, y), vec2(-x, y)]
+                    .map(|corner| point(center + corner)),
+            }
+        }
+        _ => return None,
+    })
+}
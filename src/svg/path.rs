@@ -0,0 +1,644 @@
+//! SVG path data, and flattening of its curves into line segments
+
+use glam::{Affine2, Vec2, vec2};
+
+/// Maximum distance between a flattened curve and the real curve, in pixels at a scale of 1
+const FLATTEN_TOLERANCE: f32 = 0.05;
+const MAX_CURVE_SEGMENTS: u32 = 256;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(super) enum Segment {
+    Line(Vec2),
+    Quadratic(Vec2, Vec2),
+    Cubic(Vec2, Vec2, Vec2),
+    Arc {
+        radii: Vec2,
+        /// Rotation of the ellipse's x axis, in radians
+        rotation: f32,
+        large_arc: bool,
+        sweep: bool,
+        to: Vec2,
+    },
+}
+
+impl Segment {
+    pub(super) fn end(&self) -> Vec2 {
+        match *self {
+            Segment::Line(to)
+            | Segment::Quadratic(_, to)
+            | Segment::Cubic(_, _, to)
+            | Segment::Arc { to, .. } => to,
+        }
+    }
+}
+
+/// A continuous run of segments, starting at `start`. Closed subpaths end with a segment back to the start.
+#[derive(Clone, Debug, PartialEq)]
+pub(super) struct SubPath {
+    pub start: Vec2,
+    pub segments: Vec<Segment>,
+    pub closed: bool,
+}
+
+impl SubPath {
+    pub(super) fn new(start: Vec2) -> Self {
+        Self {
+            start,
+            segments: Vec::new(),
+            closed: false,
+        }
+    }
+
+    /// Closes the subpath, adding a line back to the start if it doesn't end there
+    pub(super) fn close(&mut self) {
+        let end = self.segments.last().map_or(self.start, Segment::end);
+        if end != self.start {
+            self.segments.push(Segment::Line(self.start));
+        }
+        self.closed = true;
+    }
+
+    /// Points along the subpath with all curves flattened, in the coordinate system `transform` maps to
+    pub(super) fn flatten(&self, transform: &Affine2) -> Vec<Vec2> {
+        let mut points = vec![transform.transform_point2(self.start)];
+        let mut from = self.start;
+        for segment in &self.segments {
+            flatten_segment(from, segment, transform, &mut points);
+            from = segment.end();
+        }
+        points
+    }
+}
+
+/// An elliptical arc in center parameterization
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(super) struct CenterArc {
+    pub center: Vec2,
+    pub radii: Vec2,
+    pub rotation: f32,
+    pub start_angle: f32,
+    /// Signed angle the arc spans, positive in the direction of increasing angles
+    pub sweep_angle: f32,
+}
+
+impl CenterArc {
+    /// Converts an SVG endpoint arc, following the SVG implementation notes. Returns `None` for arcs that are drawn
+    /// as straight lines because a radius is zero, or that are omitted because they start where they end.
+    pub(super) fn from_endpoints(
+        from: Vec2,
+        radii: Vec2,
+        rotation: f32,
+        large_arc: bool,
+        sweep: bool,
+        to: Vec2,
+    ) -> Option<Self> {
+        let mut radii = radii.abs();
+        if from == to || radii.x == 0.0 || radii.y == 0.0 {
+            return None;
+        }
+
+        let (sin, cos) = rotation.sin_cos();
+        let half_delta = (from - to) / 2.0;
+        let p = vec2(
+            cos * half_delta.x + sin * half_delta.y,
+            -sin * half_delta.x + cos * half_delta.y,
+        );
+
+        // Scale up radii that are too small to reach the end point
+        let lambda = (p / radii).length_squared();
+        if lambda > 1.0 {
+            radii *= lambda.sqrt();
+        }
+
+        let (rx2, ry2) = (radii.x * radii.x, radii.y * radii.y);
+        let numerator = rx2 * ry2 - rx2 * p.y * p.y - ry2 * p.x * p.x;
+        let denominator = rx2 * p.y * p.y + ry2 * p.x * p.x;
+        let mut factor = (numerator / denominator).max(0.0).sqrt();
+        if large_arc == sweep {
+            factor = -factor;
+        }
+        let center_rotated = factor * vec2(radii.x * p.y / radii.y, -radii.y * p.x / radii.x);
+        let center = vec2(
+            cos * center_rotated.x - sin * center_rotated.y,
+            sin * center_rotated.x + cos * center_rotated.y,
+        ) + (from + to) / 2.0;
+
+        let start = (p - center_rotated) / radii;
+        let end = (-p - center_rotated) / radii;
+        let start_angle = start.y.atan2(start.x);
+        let mut sweep_angle = start.angle_to(end);
+        if sweep && sweep_angle < 0.0 {
+            sweep_angle += std::f32::consts::TAU;
+        } else if !sweep && sweep_angle > 0.0 {
+            sweep_angle -= std::f32::consts::TAU;
+        }
+
+        Some(Self {
+            center,
+            radii,
+            rotation,
+            start_angle,
+            sweep_angle,
+        })
+    }
+
+    pub(super) fn point_at(&self, angle: f32) -> Vec2 {
+        let (sin, cos) = self.rotation.sin_cos();
+        let p = self.radii * Vec2::from_angle(angle);
+        self.center + vec2(cos * p.x - sin * p.y, sin * p.x + cos * p.y)
+    }
+}
+
+/// Adds the points of a flattened segment to `points`, excluding its start point
+pub(super) fn flatten_segment(
+    from: Vec2,
+    segment: &Segment,
+    transform: &Affine2,
+    points: &mut Vec<Vec2>,
+) {
+    let point = |p: Vec2| transform.transform_point2(p);
+    match *segment {
+        Segment::Line(to) => points.push(point(to)),
+        Segment::Quadratic(control, to) => {
+            // Béziers are invariant under affine transforms, so they are flattened in the target coordinate system
+            let (p0, p1, p2) = (point(from), point(control), point(to));
+            let n = curve_segments((p0 - 2.0 * p1 + p2).length() / 8.0);
+            for i in 1..=n {
+                let t = i as f32 / n as f32;
+                let mt = 1.0 - t;
+                points.push(mt * mt * p0 + 2.0 * mt * t * p1 + t * t * p2);
+            }
+        }
+        Segment::Cubic(control1, control2, to) => {
+            let (p0, p1, p2, p3) = (point(from), point(control1), point(control2), point(to));
+            let deviation = (p0 - 2.0 * p1 + p2)
+                .length()
+                .max((p1 - 2.0 * p2 + p3).length());
+            let n = curve_segments(deviation * 0.75);
+            for i in 1..=n {
+                let t = i as f32 / n as f32;
+                let mt = 1.0 - t;
+                points.push(
+                    mt * mt * mt * p0
+                        + 3.0 * mt * mt * t * p1
+                        + 3.0 * mt * t * t * p2
+                        + t * t * t * p3,
+                );
+            }
+        }
+        Segment::Arc {
+            radii,
+            rotation,
+            large_arc,
+            sweep,
+            to,
+        } => {
+            let Some(arc) = CenterArc::from_endpoints(from, radii, rotation, large_arc, sweep, to)
+            else {
+                if from != to {
+                    points.push(point(to));
+                }
+                return;
+            };
+            let radius = arc.radii.max_element() * transform_max_scale(transform);
+            let step = 2.0 * (1.0 - FLATTEN_TOLERANCE / radius).clamp(-1.0, 1.0).acos();
+            let n = ((arc.sweep_angle.abs() / step.max(1e-3)).ceil() as u32)
+                .clamp(1, MAX_CURVE_SEGMENTS);
+            for i in 1..=n {
+                let angle = arc.start_angle + arc.sweep_angle * i as f32 / n as f32;
+                points.push(point(arc.point_at(angle)));
+            }
+        }
+    }
+}
+
+/// Number of line segments needed to approximate a curve whose flattening error for a single segment is `error`
+fn curve_segments(error: f32) -> u32 {
+    ((error / FLATTEN_TOLERANCE).sqrt().ceil() as u32).clamp(1, MAX_CURVE_SEGMENTS)
+}
+
+fn transform_max_scale(transform: &Affine2) -> f32 {
+    transform
+        .matrix2
+        .x_axis
+        .length()
+        .max(transform.matrix2.y_axis.length())
+}
+
+/// Parses path data into subpaths. On malformed data, returns the subpaths parsed up to the error along with the
+/// error, which is how SVG renderers treat it.
+pub(super) fn parse(data: &str) -> (Vec<SubPath>, Option<String>) {
+    let mut parser = PathParser {
+        numbers: NumberScanner::new(data),
+        subpaths: Vec::new(),
+        current: None,
+        position: Vec2::ZERO,
+        last_control: None,
+    };
+    let error = parser.parse().err();
+    let mut subpaths = parser.subpaths;
+    subpaths.extend(parser.current);
+    (subpaths, error)
+}
+
+struct PathParser<'a> {
+    numbers: NumberScanner<'a>,
+    subpaths: Vec<SubPath>,
+    current: Option<SubPath>,
+    position: Vec2,
+    /// Control point of the previous curve command, and whether it was cubic, for the smooth curve commands
+    last_control: Option<(Vec2, bool)>,
+}
+
+impl PathParser<'_> {
+    fn parse(&mut self) -> Result<(), String> {
+        let mut command = None;
+        loop {
+            self.numbers.skip_separators();
+            let Some(next) = self.numbers.peek() else {
+                return Ok(());
+            };
+            if next.is_ascii_alphabetic() {
+                self.numbers.advance();
+                command = Some(next);
+                if next.eq_ignore_ascii_case(&b'z') {
+                    self.close();
+                    continue;
+                }
+            } else if command.is_none() {
+                return Err(format!(
+                    "path data must start with a command, found '{}'",
+                    next as char
+                ));
+            }
+            let Some(c) = command else {
+                return Ok(());
+            };
+            self.command(c)?;
+            // Coordinates following a move are implicit lines
+            command = match c {
+                b'M' => Some(b'L'),
+                b'm' => Some(b'l'),
+                b'Z' | b'z' => None,
+                c => Some(c),
+            };
+        }
+    }
+
+    fn command(&mut self, command: u8) -> Result<(), String> {
+        let relative = command.is_ascii_lowercase();
+        let origin = if relative { self.position } else { Vec2::ZERO };
+        let mut control = None;
+        match command.to_ascii_uppercase() {
+            b'M' => {
+                let to = origin + self.point()?;
+                self.subpaths.extend(self.current.take());
+                self.current = Some(SubPath::new(to));
+                self.position = to;
+            }
+            b'L' => {
+                let to = origin + self.point()?;
+                self.push(Segment::Line(to));
+            }
+            b'H' => {
+                let x = origin.x + self.number()?;
+                self.push(Segment::Line(vec2(x, self.position.y)));
+            }
+            b'V' => {
+                let y = origin.y + self.number()?;
+                self.push(Segment::Line(vec2(self.position.x, y)));
+            }
+            b'C' => {
+                let c1 = origin + self.point()?;
+                let c2 = origin + self.point()?;
+                let to = origin + self.point()?;
+                self.push(Segment::Cubic(c1, c2, to));
+                control = Some((c2, true));
+            }
+            b'S' => {
+                let c1 = self.reflected_control(true);
+                let c2 = origin + self.point()?;
+                let to = origin + self.point()?;
+                self.push(Segment::Cubic(c1, c2, to));
+                control = Some((c2, true));
+            }
+            b'Q' => {
+                let c = origin + self.point()?;
+                let to = origin + self.point()?;
+                self.push(Segment::Quadratic(c, to));
+                control = Some((c, false));
+            }
+            b'T' => {
+                let c = self.reflected_control(false);
+                let to = origin + self.point()?;
+                self.push(Segment::Quadratic(c, to));
+                control = Some((c, false));
+            }
+            b'A' => {
+                let radii = self.point()?;
+                let rotation = self.number()?.to_radians();
+                let large_arc = self.flag()?;
+                let sweep = self.flag()?;
+                let to = origin + self.point()?;
+                self.push(Segment::Arc {
+                    radii,
+                    rotation,
+                    large_arc,
+                    sweep,
+                    to,
+                });
+            }
+            _ => return Err(format!("unknown path command '{}'", command as char)),
+        }
+        self.last_control = control;
+        Ok(())
+    }
+
+    fn push(&mut self, segment: Segment) {
+        let position = self.position;
+        self.current
+            .get_or_insert_with(|| SubPath::new(position))
+            .segments
+            .push(segment);
+        self.position = segment.end();
+    }
+
+    fn close(&mut self) {
+        if let Some(mut subpath) = self.current.take() {
+            subpath.close();
+            self.position = subpath.start;
+            self.subpaths.push(subpath);
+        }
+        self.last_control = None;
+    }
+
+    /// First control point of a smooth curve: the previous control point mirrored around the current position
+    fn reflected_control(&self, cubic: bool) -> Vec2 {
+        match self.last_control {
+            Some((control, was_cubic)) if was_cubic == cubic => 2.0 * self.position - control,
+            _ => self.position,
+        }
+    }
+
+    fn point(&mut self) -> Result<Vec2, String> {
+        Ok(vec2(self.number()?, self.number()?))
+    }
+
+    fn number(&mut self) -> Result<f32, String> {
+        self.numbers
+            .next_number()
+            .ok_or_else(|| format!("expected a number at byte {}", self.numbers.position))
+    }
+
+    fn flag(&mut self) -> Result<bool, String> {
+        self.numbers.skip_separators();
+        match self.numbers.peek() {
+            Some(b'0') => {
+                self.numbers.advance();
+                Ok(false)
+            }
+            Some(b'1') => {
+                self.numbers.advance();
+                Ok(true)
+            }
+            _ => Err(format!(
+                "expected an arc flag at byte {}",
+                self.numbers.position
+            )),
+        }
+    }
+}
+
+/// Reads numbers separated by whitespace and commas, including the compact forms allowed in path data like
+/// `10-5.5.5`
+pub(super) struct NumberScanner<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> NumberScanner<'a> {
+    pub(super) fn new(text: &'a str) -> Self {
+        Self {
+            bytes: text.as_bytes(),
+            position: 0,
+        }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.bytes.get(self.position).copied()
+    }
+
+    fn advance(&mut self) {
+        self.position += 1;
+    }
+
+    fn skip_separators(&mut self) {
+        while self
+            .peek()
+            .is_some_and(|b| b.is_ascii_whitespace() || b == b',')
+        {
+            self.advance();
+        }
+    }
+
+    pub(super) fn next_number(&mut self) -> Option<f32> {
+        self.skip_separators();
+        let start = self.position;
+        if matches!(self.peek(), Some(b'+' | b'-')) {
+            self.advance();
+        }
+        let mut seen_dot = false;
+        let mut seen_digit = false;
+        while let Some(b) = self.peek() {
+            match b {
+                b'0'..=b'9' => seen_digit = true,
+                b'.' if !seen_dot => seen_dot = true,
+                _ => break,
+            }
+            self.advance();
+        }
+        if seen_digit && matches!(self.peek(), Some(b'e' | b'E')) {
+            let mantissa_end = self.position;
+            self.advance();
+            if matches!(self.peek(), Some(b'+' | b'-')) {
+                self.advance();
+            }
+            if self.peek().is_some_and(|b| b.is_ascii_digit()) {
+                while self.peek().is_some_and(|b| b.is_ascii_digit()) {
+                    self.advance();
+                }
+            } else {
+                self.position = mantissa_end;
+            }
+        }
+        if !seen_digit {
+            self.position = start;
+            return None;
+        }
+        std::str::from_utf8(&self.bytes[start..self.position])
+            .ok()?
+            .parse()
+            .ok()
+    }
+}
+
+/// Parses a list of numbers, eg. the points of a polygon
+pub(super) fn parse_numbers(text: &str) -> Vec<f32> {
+    let mut scanner = NumberScanner::new(text);
+    std::iter::from_fn(|| scanner.next_number()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Parses path data that must be valid
+    fn parse_ok(data: &str) -> Vec<SubPath> {
+        let (subpaths, error) = parse(data);
+        assert_eq!(error, None, "{data:?}");
+        subpaths
+    }
+
+    fn lines(points: &[(f32, f32)]) -> Vec<Segment> {
+        points
+            .iter()
+            .map(|&(x, y)| Segment::Line(vec2(x, y)))
+            .collect()
+    }
+
+    #[test]
+    fn parses_absolute_commands() {
+        let subpaths = parse_ok("M10 20 L30 40 H50 V60 Z");
+        assert_eq!(
+            subpaths,
+            [SubPath {
+                start: vec2(10.0, 20.0),
+                segments: lines(&[(30.0, 40.0), (50.0, 40.0), (50.0, 60.0), (10.0, 20.0)]),
+                closed: true,
+            }]
+        );
+    }
+
+    #[test]
+    fn parses_relative_commands() {
+        let subpaths = parse_ok("m10 20 l5 5 h5 v-10 z m5 5 l1 1");
+        assert_eq!(subpaths.len(), 2);
+        assert_eq!(
+            subpaths[0].segments,
+            lines(&[(15.0, 25.0), (20.0, 25.0), (20.0, 15.0), (10.0, 20.0)])
+        );
+        // Relative to the start of the closed subpath
+        assert_eq!(subpaths[1].start, vec2(15.0, 25.0));
+        assert_eq!(subpaths[1].segments, lines(&[(16.0, 26.0)]));
+        assert!(!subpaths[1].closed);
+
+        let curves = parse_ok("m10 10 c0 10 10 10 10 0 q5 -5 10 0 a5 5 0 0 0 10 0");
+        assert_eq!(
+            curves[0].segments,
+            [
+                Segment::Cubic(vec2(10.0, 20.0), vec2(20.0, 20.0), vec2(20.0, 10.0)),
+                Segment::Quadratic(vec2(25.0, 5.0), vec2(30.0, 10.0)),
+                Segment::Arc {
+                    radii: vec2(5.0, 5.0),
+                    rotation: 0.0,
+                    large_arc: false,
+                    sweep: false,
+                    to: vec2(40.0, 10.0),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn repeats_commands_implicitly() {
+        // Coordinates after a move are lines, other commands repeat themselves
+        assert_eq!(
+            parse_ok("M0 0 10 0 10 10")[0].segments,
+            lines(&[(10.0, 0.0), (10.0, 10.0)])
+        );
+        assert_eq!(
+            parse_ok("m1 1 2 2 h1 2")[0].segments,
+            lines(&[(3.0, 3.0), (4.0, 3.0), (6.0, 3.0)])
+        );
+        assert_eq!(
+            parse_ok("M0 0 Q1 1 2 0 3 -1 4 0")[0].segments,
+            [
+                Segment::Quadratic(vec2(1.0, 1.0), vec2(2.0, 0.0)),
+                Segment::Quadratic(vec2(3.0, -1.0), vec2(4.0, 0.0)),
+            ]
+        );
+    }
+
+    #[test]
+    fn reflects_smooth_curve_controls() {
+        assert_eq!(
+            parse_ok("M0 0 C10 0 20 10 20 20 S30 40 40 40")[0].segments[1],
+            Segment::Cubic(vec2(20.0, 30.0), vec2(30.0, 40.0), vec2(40.0, 40.0))
+        );
+        assert_eq!(
+            parse_ok("M0 0 Q10 0 10 10 T10 30")[0].segments[1],
+            Segment::Quadratic(vec2(10.0, 20.0), vec2(10.0, 30.0))
+        );
+        // Without a previous curve of the same kind, the control point is the current position
+        assert_eq!(
+            parse_ok("M0 0 Q10 0 10 10 S20 20 30 30")[0].segments[1],
+            Segment::Cubic(vec2(10.0, 10.0), vec2(20.0, 20.0), vec2(30.0, 30.0))
+        );
+    }
+
+    #[test]
+    fn parses_compact_numbers_and_flags() {
+        assert_eq!(
+            parse_ok("M10-5.5.5.5L1e1,2E-1")[0],
+            SubPath {
+                start: vec2(10.0, -5.5),
+                segments: lines(&[(0.5, 0.5), (10.0, 0.2)]),
+                closed: false,
+            }
+        );
+
+        // Arc flags don't need separators
+        let arc = |large_arc, sweep, to| Segment::Arc {
+            radii: vec2(10.0, 5.0),
+            rotation: 30f32.to_radians(),
+            large_arc,
+            sweep,
+            to,
+        };
+        assert_eq!(
+            parse_ok("M0 0a10 5 30 1110 0A10,5,30,0,1,20,20")[0].segments,
+            [
+                arc(true, true, vec2(10.0, 0.0)),
+                arc(false, true, vec2(20.0, 20.0))
+            ]
+        );
+    }
+
+    #[test]
+    fn keeps_subpaths_before_errors() {
+        let (subpaths, error) = parse("10 10");
+        assert!(subpaths.is_empty());
+        assert!(error.is_some());
+
+        let (subpaths, error) = parse("M0 0 L5 5 X1 1");
+        assert_eq!(subpaths[0].segments, lines(&[(5.0, 5.0)]));
+        assert!(error.unwrap().contains("unknown path command 'X'"));
+
+        let (subpaths, error) = parse("M0 0 L5 5 L10");
+        assert_eq!(subpaths[0].segments, lines(&[(5.0, 5.0)]));
+        assert!(error.unwrap().contains("expected a number"));
+
+        let (subpaths, error) = parse("M0 0 A1 1 0 2 0 5 5");
+        assert_eq!(subpaths.len(), 1);
+        assert!(error.unwrap().contains("expected an arc flag"));
+    }
+
+    #[test]
+    fn parses_number_lists() {
+        assert_eq!(
+            parse_numbers(" 1,2 -3.5e2 .5-1 "),
+            [1.0, 2.0, -350.0, 0.5, -1.0]
+        );
+        assert_eq!(parse_numbers("1 x 2"), [1.0]);
+    }
+}
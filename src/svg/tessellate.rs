@@ -0,0 +1,190 @@
+//! Triangulation of filled polygons
+
+use glam::Vec2;
+
+/// Splits a simple polygon into triangles by ear clipping.
+///
+/// Returns `false` along with the triangles if the polygon intersects itself. In that case the part that couldn't be
+/// clipped is filled as a triangle fan, which covers roughly the right area but not the exact shape.
+pub(super) fn triangulate(points: &[Vec2]) -> (Vec<[Vec2; 3]>, bool) {
+    let mut polygon: Vec<Vec2> = Vec::with_capacity(points.len());
+    for &p in points {
+        if polygon.last() != Some(&p) {
+            polygon.push(p);
+        }
+    }
+    while polygon.len() > 1 && polygon.first() == polygon.last() {
+        polygon.pop();
+    }
+    if polygon.len() < 3 {
+        return (Vec::new(), true);
+    }
+
+    // Ears are convex corners, so their orientation has to match the polygon's
+    let area = signed_area(&polygon);
+    if area == 0.0 {
+        return (Vec::new(), true);
+    }
+    let orientation = area.signum();
+    let mut triangles = Vec::with_capacity(polygon.len() - 2);
+    let mut remaining: Vec<usize> = (0..polygon.len()).collect();
+    let mut i = 0;
+    let mut attempts = 0;
+    while remaining.len() > 3 {
+        if attempts > remaining.len() {
+            // No ear left, the polygon intersects itself
+            let first = polygon[remaining[0]];
+            triangles.extend(
+                remaining[1..]
+                    .windows(2)
+                    .map(|w| [first, polygon[w[0]], polygon[w[1]]]),
+            );
+            return (triangles, false);
+        }
+
+        let n = remaining.len();
+        let (a, b, c) = (
+            polygon[remaining[(i + n - 1) % n]],
+            polygon[remaining[i % n]],
+            polygon[remaining[(i + 1) % n]],
+        );
+        let turn = (b - a).perp_dot(c - b);
+        if turn == 0.0 {
+            // Collinear corners add no area
+            remaining.remove(i % n);
+            attempts = 0;
+            continue;
+        }
+        let is_ear = turn.signum() == orientation
+            && !remaining.iter().any(|&j| {
+                let p = polygon[j];
+                p != a && p != b && p != c && in_triangle(p, a, b, c)
+            });
+        if is_ear {
+            triangles.push([a, b, c]);
+            remaining.remove(i % n);
+            attempts = 0;
+        } else {
+            i += 1;
+            attempts += 1;
+        }
+        i %= remaining.len();
+    }
+    let last = [
+        polygon[remaining[0]],
+        polygon[remaining[1]],
+        polygon[remaining[2]],
+    ];
+    if (last[1] - last[0]).perp_dot(last[2] - last[0]) != 0.0 {
+        triangles.push(last);
+    }
+    (triangles, true)
+}
+
+fn signed_area(polygon: &[Vec2]) -> f32 {
+    let mut area = 0.0;
+    let mut j = polygon.len() - 1;
+    for i in 0..polygon.len() {
+        area += polygon[j].perp_dot(polygon[i]);
+        j = i;
+    }
+    area / 2.0
+}
+
+/// Whether `p` lies inside or on the edge of the triangle
+fn in_triangle(p: Vec2, a: Vec2, b: Vec2, c: Vec2) -> bool {
+    let d1 = (b - a).perp_dot(p - a);
+    let d2 = (c - b).perp_dot(p - b);
+    let d3 = (a - c).perp_dot(p - c);
+    let has_negative = d1 < 0.0 || d2 < 0.0 || d3 < 0.0;
+    let has_positive = d1 > 0.0 || d2 > 0.0 || d3 > 0.0;
+    !(has_negative && has_positive)
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::vec2;
+
+    use super::*;
+
+    fn triangles_area(triangles: &[[Vec2; 3]]) -> f32 {
+        triangles.iter().map(|t| signed_area(t)).sum()
+    }
+
+    /// Triangulates the polygon, and checks that the triangles have the polygon's orientation and cover its area
+    fn assert_covers(polygon: &[Vec2]) -> Vec<[Vec2; 3]> {
+        let (triangles, simple) = triangulate(polygon);
+        assert!(simple);
+        let area = signed_area(polygon);
+        for triangle in &triangles {
+            // Ears are never flipped or degenerate
+            assert_eq!(signed_area(triangle).signum(), area.signum());
+        }
+        assert!(
+            (triangles_area(&triangles) - area).abs() < 1e-3,
+            "triangles cover {}, polygon {area}",
+            triangles_area(&triangles)
+        );
+        triangles
+    }
+
+    #[test]
+    fn triangulates_concave_polygons() {
+        // An arrow pointing right, concave at the back
+        let arrow = [
+            vec2(0.0, 0.0),
+            vec2(10.0, 5.0),
+            vec2(0.0, 10.0),
+            vec2(3.0, 5.0),
+        ];
+        assert_eq!(assert_covers(&arrow).len(), 2);
+
+        // A comb with three teeth
+        let comb = [
+            vec2(0.0, 0.0),
+            vec2(50.0, 0.0),
+            vec2(50.0, 30.0),
+            vec2(40.0, 30.0),
+            vec2(40.0, 10.0),
+            vec2(30.0, 10.0),
+            vec2(30.0, 30.0),
+            vec2(20.0, 30.0),
+            vec2(20.0, 10.0),
+            vec2(10.0, 10.0),
+            vec2(10.0, 30.0),
+            vec2(0.0, 30.0),
+        ];
+        assert_covers(&comb);
+
+        // Both orientations work
+        let reversed: Vec<_> = comb.iter().rev().copied().collect();
+        assert_covers(&reversed);
+    }
+
+    #[test]
+    fn skips_duplicate_and_collinear_points() {
+        let square = [
+            vec2(0.0, 0.0),
+            vec2(5.0, 0.0),
+            vec2(10.0, 0.0),
+            vec2(10.0, 0.0),
+            vec2(10.0, 10.0),
+            vec2(0.0, 10.0),
+            vec2(0.0, 0.0),
+        ];
+        assert_covers(&square);
+    }
+
+    #[test]
+    fn degenerate_polygons_have_no_triangles() {
+        assert_eq!(triangulate(&[]), (Vec::new(), true));
+        assert_eq!(
+            triangulate(&[vec2(0.0, 0.0), vec2(1.0, 1.0), vec2(0.0, 0.0)]),
+            (Vec::new(), true)
+        );
+        assert_eq!(
+            triangulate(&[vec2(0.0, 0.0), vec2(1.0, 1.0), vec2(2.0, 2.0)]),
+            (Vec::new(), true)
+        );
+    }
+}
@@ -0,0 +1,261 @@
+use std::collections::{HashMap, HashSet};
+
+use glam::UVec2;
+use ttf_parser::{Face, GlyphId};
+
+use super::{AtlasInfo, FontAtlas, FontMetrics, GlyphImageKind, msdf};
+use crate::{
+    backend::{
+        atlas::ShelfAllocator, sampler::SamplerOptions, texture::TextureOptions, wgpu::WgpuRenderer,
+    },
+    error::Error,
+};
+
+/// Options for glyphs generated by a [`GlyphCache`]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GlyphCacheOptions {
+    pub kind: GlyphImageKind,
+    /// Size of one em in the generated glyph images, in pixels
+    pub em_size: f32,
+    /// Width of the distance field range, in pixels
+    pub distance_range: f32,
+    /// Size of the atlas texture the glyphs are packed into
+    pub atlas_size: (u32, u32),
+}
+
+impl Default for GlyphCacheOptions {
+    fn default() -> Self {
+        Self {
+            kind: GlyphImageKind::Mtsdf,
+            em_size: 32.0,
+            distance_range: 4.0,
+            atlas_size: (1024, 1024),
+        }
+    }
+}
+
+/// Generates glyph images from a TrueType/OpenType font on demand, and packs them into an atlas texture.
+///
+/// Call [`GlyphCache::prepare`] with the text to draw, then pass [`GlyphCache::font`] to
+/// [`crate::Painter::add_text`]. Glyphs stay cached until the cache is cleared with [`GlyphCache::clear`], eg. once the
+/// atlas is full.
+pub struct GlyphCache {
+    font_data: Vec<u8>,
+    face_index: u32,
+    options: GlyphCacheOptions,
+    font: FontAtlas,
+    glyph_ids: HashMap<char, GlyphId>,
+    /// Characters whose glyph image didn't fit into the atlas, so they aren't generated again
+    unfitted: HashSet<char>,
+    allocator: ShelfAllocator,
+}
+
+impl GlyphCache {
+    /// Empty space around each glyph image, so linear filtering doesn't bleed between glyphs
+    const PADDING: u32 = 1;
+
+    /// Creates an empty cache for the face at `face_index` in the font file, and registers its atlas texture
+    pub fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        renderer: &mut WgpuRenderer,
+        font_data: Vec<u8>,
+        face_index: u32,
+        options: GlyphCacheOptions,
+    ) -> Result<Self, Error> {
+        let face =
+            Face::parse(&font_data, face_index).map_err(|e| Error::InvalidFont(e.to_string()))?;
+        let units_per_em = face.units_per_em() as f32;
+        let metrics = FontMetrics {
+            line_height: (face.ascender() - face.descender() + face.line_gap()) as f32
+                / units_per_em,
+            ascender: face.ascender() as f32 / units_per_em,
+            descender: face.descender() as f32 / units_per_em,
+        };
+
+        let format = options.kind.texture_format();
+        let (width, height) = options.atlas_size;
+        let data = vec![0; width as usize * height as usize * format.bytes_per_pixel() as usize];
+        let texture_id = renderer.create_texture(
+            device,
+            queue,
+            options.atlas_size,
+            &data,
+            TextureOptions {
+                format,
+                sampler: SamplerOptions::LINEAR,
+                generate_mipmaps: false,
+            },
+        )?;
+
+        let atlas = AtlasInfo {
+            kind: options.kind,
+            distance_range: options.distance_range,
+            em_size: options.em_size,
+            size: UVec2::new(width, height),
+            y_origin_bottom: false,
+        };
+        Ok(Self {
+            font_data,
+            face_index,
+            options,
+            font: FontAtlas::new(texture_id, atlas, metrics),
+            glyph_ids: HashMap::new(),
+            unfitted: HashSet::new(),
+            allocator: ShelfAllocator::new(UVec2::new(width, height), Self::PADDING),
+        })
+    }
+
+    /// Font atlas with all glyphs generated so far
+    pub fn font(&self) -> &FontAtlas {
+        &self.font
+    }
+
+    /// Generates and uploads the glyphs of all characters in `text` that aren't cached yet.
+    ///
+    /// Characters missing from the font use the font's fallback glyph. Returns [`Error::AtlasFull`] if the atlas has
+    /// no space left for some of the glyphs, after preparing all glyphs that fit. The glyphs that didn't fit are drawn
+    /// with the replacement character if it is cached, or skipped otherwise, and aren't generated again.
+    pub fn prepare(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        renderer: &mut WgpuRenderer,
+        text: &str,
+    ) -> Result<(), Error> {
+        if text.chars().all(|c| self.is_cached(c)) {
+            return Ok(());
+        }
+        let face = Face::parse(&self.font_data, self.face_index)
+            .map_err(|e| Error::InvalidFont(e.to_string()))?;
+        let units_per_em = face.units_per_em() as f32;
+
+        let mut result = Ok(());
+        for c in text.chars() {
+            if self.is_cached(c) {
+                continue;
+            }
+            let glyph = face.glyph_index(c).unwrap_or(GlyphId(0));
+            let advance = face.glyph_hor_advance(glyph).unwrap_or(0) as f32 / units_per_em;
+
+            match msdf::generate(
+                &face,
+                glyph,
+                self.options.kind,
+                self.options.em_size,
+                self.options.distance_range,
+            ) {
+                Some(bitmap) => {
+                    let size = UVec2::new(bitmap.width, bitmap.height);
+                    let origin = match self.allocator.allocate(size) {
+                        Ok(origin) => origin,
+                        Err(e) => {
+                            self.unfitted.insert(c);
+                            result = Err(e);
+                            continue;
+                        }
+                    };
+                    if let Err(e) = renderer.update_texture(
+                        device,
+                        queue,
+                        self.font.texture_id,
+                        (origin.x, origin.y),
+                        (size.x, size.y),
+                        &bitmap.data,
+                    ) {
+                        // The glyph isn't cached, so it is generated again by the next call
+                        self.allocator.deallocate(origin, size);
+                        return Err(e);
+                    }
+                    let atlas_bounds = [
+                        origin.x as f32,
+                        (origin.y + size.y) as f32,
+                        (origin.x + size.x) as f32,
+                        origin.y as f32,
+                    ];
+                    self.font.insert_glyph(
+                        c,
+                        advance,
+                        Some(bitmap.plane_bounds),
+                        Some(atlas_bounds),
+                    );
+                }
+                None => self.font.insert_glyph(c, advance, None, None),
+            }
+
+            insert_kerning(&mut self.font, &self.glyph_ids, &face, c, glyph);
+            self.glyph_ids.insert(c, glyph);
+        }
+        result
+    }
+
+    /// Removes all cached glyphs and clears the atlas texture. Glyphs are generated again by the next call to
+    /// [`GlyphCache::prepare`], including those that didn't fit before.
+    ///
+    /// Text laid out with the old glyphs has to be prepared and laid out again before it is drawn.
+    pub fn clear(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        renderer: &mut WgpuRenderer,
+    ) -> Result<(), Error> {
+        self.font.clear_glyphs();
+        self.glyph_ids.clear();
+        self.unfitted.clear();
+        let size = self.font.atlas.size;
+        self.allocator = ShelfAllocator::new(size, Self::PADDING);
+
+        // Glyph padding is never written, so leftovers of the old glyphs would bleed into the new ones
+        let bytes_per_pixel = self.options.kind.texture_format().bytes_per_pixel() as usize;
+        let data = vec![0; size.x as usize * size.y as usize * bytes_per_pixel];
+        renderer.update_texture(
+            device,
+            queue,
+            self.font.texture_id,
+            (0, 0),
+            (size.x, size.y),
+            &data,
+        )
+    }
+
+    fn is_cached(&self, c: char) -> bool {
+        c == '\n' || c == '\r' || self.glyph_ids.contains_key(&c) || self.unfitted.contains(&c)
+    }
+}
+
+/// Adds the kerning between a new glyph and all cached glyphs, from the font's `kern` table
+fn insert_kerning(
+    font: &mut FontAtlas,
+    glyph_ids: &HashMap<char, GlyphId>,
+    face: &Face,
+    c: char,
+    glyph: GlyphId,
+) {
+    let Some(kern) = face.tables().kern else {
+        return;
+    };
+    let units_per_em = face.units_per_em() as f32;
+    let kerning = |left, right| {
+        kern.subtables
+            .into_iter()
+            .filter(|s| s.horizontal && !s.variable && !s.has_cross_stream)
+            .filter_map(|s| s.glyphs_kerning(left, right))
+            .map(|k| k as f32)
+            .sum::<f32>()
+            / units_per_em
+    };
+    for (&other, &other_glyph) in glyph_ids {
+        for (pair, k) in [
+            ((c, other), kerning(glyph, other_glyph)),
+            ((other, c), kerning(other_glyph, glyph)),
+        ] {
+            if k != 0.0 {
+                font.insert_kerning(pair.0, pair.1, k);
+            }
+        }
+    }
+    let k = kerning(glyph, glyph);
+    if k != 0.0 {
+        font.insert_kerning(c, c, k);
+    }
+}
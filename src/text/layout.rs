@@ -0,0 +1,404 @@
+use glam::{Vec2, Vec3, Vec4};
+
+use super::FontAtlas;
+
+/// Horizontal alignment of lines within a text layout
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TextAlign {
+    #[default]
+    Left,
+    Center,
+    Right,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TextStyle {
+    /// Font size in pixels per em
+    pub size: f32,
+    pub color: Vec4,
+    pub align: TextAlign,
+    /// Multiplier for the font's line height
+    pub line_spacing: f32,
+    /// Lines longer than this are wrapped at whitespace, or between characters if a single word doesn't fit.
+    /// Lines are aligned within this width if it is set, or within the widest line otherwise.
+    pub max_width: Option<f32>,
+    /// Offset applied to the glyph distances in pixels, see [`crate::Shape::with_distance_offset`].
+    /// Negative values make the text bolder, positive values thinner.
+    pub distance_offset: f32,
+    /// Draws only the outline of the glyphs, with this width in pixels, if larger than zero
+    pub line_width: f32,
+    /// Glow or shadow, see [`crate::Shape::with_glow`] and [`crate::Shape::with_shadow`]
+    pub glow: Vec4,
+}
+
+impl Default for TextStyle {
+    fn default() -> Self {
+        Self {
+            size: 16.0,
+            color: Vec4::ONE,
+            align: TextAlign::Left,
+            line_spacing: 1.0,
+            max_width: None,
+            distance_offset: 0.0,
+            line_width: 0.0,
+            glow: Vec4::ZERO,
+        }
+    }
+}
+
+impl TextStyle {
+    pub fn new(size: f32, color: impl Into<Vec4>) -> Self {
+        Self {
+            size,
+            color: color.into(),
+            ..Default::default()
+        }
+    }
+
+    pub fn with_align(mut self, align: TextAlign) -> Self {
+        self.align = align;
+        self
+    }
+
+    pub fn with_line_spacing(mut self, line_spacing: f32) -> Self {
+        self.line_spacing = line_spacing;
+        self
+    }
+
+    pub fn with_max_width(mut self, max_width: f32) -> Self {
+        self.max_width = Some(max_width);
+        self
+    }
+
+    pub fn with_distance_offset(mut self, offset: f32) -> Self {
+        self.distance_offset = offset;
+        self
+    }
+
+    pub fn with_line_width(mut self, line_width: f32) -> Self {
+        self.line_width = line_width;
+        self
+    }
+
+    pub fn with_glow(mut self, color: Vec3, size: f32) -> Self {
+        self.glow = Vec4::new(color.x, color.y, color.z, size);
+        self
+    }
+
+    pub fn with_shadow(mut self, opacity: f32, size: f32) -> Self {
+        self.glow = Vec4::new(opacity, opacity, opacity, -size);
+        self
+    }
+}
+
+/// A glyph image placed by [`FontAtlas::layout`]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PositionedGlyph {
+    pub character: char,
+    /// Top-left corner of the glyph image, relative to the top-left corner of the layout
+    pub min: Vec2,
+    pub max: Vec2,
+    pub uv_min: Vec2,
+    pub uv_max: Vec2,
+}
+
+/// Result of laying out a string
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TextLayout {
+    /// Glyphs with an image, in text order. Whitespace and characters missing from the font are not included.
+    pub glyphs: Vec<PositionedGlyph>,
+    /// Width of the widest line and height of all lines
+    pub size: Vec2,
+    pub line_count: usize,
+}
+
+/// Characters of one laid out line
+#[derive(Default)]
+struct Line {
+    chars: Vec<char>,
+    /// Width without trailing whitespace, which hangs past the wrap width
+    width: f32,
+}
+
+pub(crate) fn layout(font: &FontAtlas, text: &str, style: &TextStyle) -> TextLayout {
+    let scale = style.size;
+    let max_width = style.max_width.map(|w| w / scale);
+
+    let mut lines = Vec::new();
+    for paragraph in text.split('\n') {
+        wrap_paragraph(
+            font,
+            paragraph.trim_end_matches('\r'),
+            max_width,
+            &mut lines,
+        );
+    }
+
+    let line_height = font.metrics.line_height * style.line_spacing;
+    let widest = lines.iter().map(|l| l.width).fold(0.0, f32::max);
+    let align_width = max_width.unwrap_or(widest);
+
+    let mut glyphs = Vec::new();
+    for (line_index, line) in lines.iter().enumerate() {
+        let mut pen = Vec2::new(
+            match style.align {
+                TextAlign::Left => 0.0,
+                TextAlign::Center => (align_width - line.width) * 0.5,
+                TextAlign::Right => align_width - line.width,
+            },
+            font.metrics.ascender + line_index as f32 * line_height,
+        );
+        let mut prev = None;
+        for &c in &line.chars {
+            let Some(glyph) = lookup(font, c) else {
+                continue;
+            };
+            if let Some(prev) = prev {
+                pen.x += font.kerning(prev, c);
+            }
+            if let Some((min, max)) = glyph.plane {
+                glyphs.push(PositionedGlyph {
+                    character: c,
+                    min: (pen + min) * scale,
+                    max: (pen + max) * scale,
+                    uv_min: glyph.uv.0,
+                    uv_max: glyph.uv.1,
+                });
+            }
+            pen.x += glyph.advance;
+            prev = Some(c);
+        }
+    }
+
+    TextLayout {
+        glyphs,
+        size: Vec2::new(widest, lines.len() as f32 * line_height) * scale,
+        line_count: lines.len(),
+    }
+}
+
+/// The glyph for a character, falling back to the replacement character
+fn lookup(font: &FontAtlas, c: char) -> Option<&super::Glyph> {
+    font.glyph(c)
+        .or_else(|| font.glyph(char::REPLACEMENT_CHARACTER))
+}
+
+/// Advance of `c` following `prev`, including kerning
+fn advance(font: &FontAtlas, prev: Option<char>, c: char) -> f32 {
+    let kerning = prev.map_or(0.0, |prev| font.kerning(prev, c));
+    kerning + lookup(font, c).map_or(0.0, |g| g.advance)
+}
+
+/// Breaks a paragraph into lines no wider than `max_width` ems
+fn wrap_paragraph(
+    font: &FontAtlas,
+    paragraph: &str,
+    max_width: Option<f32>,
+    lines: &mut Vec<Line>,
+) {
+    let mut line = Line::default();
+    // Width of the line including trailing whitespace
+    let mut pen = 0.0;
+
+    for word in split_words(paragraph) {
+        let is_space = word.starts_with(char::is_whitespace);
+        let mut prev = line.chars.last().copied();
+        let word_width: f32 = word
+            .chars()
+            .map(|c| {
+                let a = advance(font, prev, c);
+                prev = Some(c);
+                a
+            })
+            .sum();
+
+        let fits = max_width.is_none_or(|max| pen + word_width <= max);
+        if !fits && !is_space && !line.chars.is_empty() {
+            lines.push(std::mem::take(&mut line));
+            pen = 0.0;
+        }
+
+        for c in word.chars() {
+            let prev = line.chars.last().copied();
+            let a = advance(font, prev, c);
+            // Words wider than a whole line are broken between characters
+            if let Some(max) = max_width
+                && !is_space
+                && !line.chars.is_empty()
+                && pen + a > max
+                && word_width > max
+            {
+                lines.push(std::mem::take(&mut line));
+                pen = 0.0;
+            }
+            pen += if line.chars.is_empty() {
+                advance(font, None, c)
+            } else {
+                a
+            };
+            line.chars.push(c);
+            if !is_space {
+                line.width = pen;
+            }
+        }
+    }
+    lines.push(line);
+}
+
+/// Splits text into alternating runs of whitespace and non-whitespace
+fn split_words(text: &str) -> impl Iterator<Item = &str> {
+    let mut rest = text;
+    std::iter::from_fn(move || {
+        let first = rest.chars().next()?;
+        let is_space = first.is_whitespace();
+        let end = rest
+            .find(|c: char| c.is_whitespace() != is_space)
+            .unwrap_or(rest.len());
+        let (word, tail) = rest.split_at(end);
+        rest = tail;
+        Some(word)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::UVec2;
+
+    use super::*;
+    use crate::{
+        shape::TextureId,
+        text::{AtlasInfo, FontMetrics, GlyphImageKind},
+    };
+
+    /// Monospaced font where 'a' is half an em wide and a space a quarter em
+    fn font() -> FontAtlas {
+        let mut font = FontAtlas::new(
+            TextureId::default(),
+            AtlasInfo {
+                kind: GlyphImageKind::Mtsdf,
+                distance_range: 4.0,
+                em_size: 32.0,
+                size: UVec2::new(64, 64),
+                y_origin_bottom: true,
+            },
+            FontMetrics {
+                line_height: 1.25,
+                ascender: 0.75,
+                descender: -0.25,
+            },
+        );
+        font.insert_glyph(
+            'a',
+            0.5,
+            Some([0.0, 0.0, 0.5, 0.5]),
+            Some([0.0, 0.0, 16.0, 16.0]),
+        );
+        font.insert_glyph(' ', 0.25, None, None);
+        font
+    }
+
+    /// Size 10, so an 'a' is 5 pixels wide and lines are 12.5 pixels apart
+    fn style() -> TextStyle {
+        TextStyle::new(10.0, Vec4::ONE)
+    }
+
+    fn lines(layout: &TextLayout) -> Vec<Vec<f32>> {
+        let mut lines: Vec<Vec<f32>> = Vec::new();
+        for glyph in &layout.glyphs {
+            let line = ((glyph.min.y - 2.5) / 12.5) as usize;
+            lines.resize(lines.len().max(line + 1), Vec::new());
+            lines[line].push(glyph.min.x);
+        }
+        lines
+    }
+
+    #[test]
+    fn lays_out_a_single_line() {
+        let layout = font().layout("aa a", &style());
+        assert_eq!(layout.line_count, 1);
+        assert_eq!(layout.size, Vec2::new(17.5, 12.5));
+        assert_eq!(lines(&layout), vec![vec![0.0, 5.0, 12.5]]);
+        assert_eq!(layout.glyphs[0].min, Vec2::new(0.0, 2.5));
+        assert_eq!(layout.glyphs[0].max, Vec2::new(5.0, 7.5));
+    }
+
+    #[test]
+    fn breaks_at_newlines() {
+        let layout = font().layout("a\r\naa\n\na", &style());
+        assert_eq!(layout.line_count, 4);
+        assert_eq!(layout.size, Vec2::new(10.0, 50.0));
+        assert_eq!(
+            lines(&layout),
+            vec![vec![0.0], vec![0.0, 5.0], vec![], vec![0.0]]
+        );
+    }
+
+    #[test]
+    fn wraps_at_whitespace() {
+        let layout = font().layout("aa aa  aa", &style().with_max_width(14.0));
+        assert_eq!(layout.line_count, 3);
+        // Trailing whitespace hangs past the wrap width and doesn't count towards the line width
+        assert_eq!(layout.size, Vec2::new(10.0, 37.5));
+        assert_eq!(lines(&layout), vec![vec![0.0, 5.0]; 3]);
+    }
+
+    #[test]
+    fn breaks_long_words_between_characters() {
+        let layout = font().layout("aaaaa", &style().with_max_width(12.0));
+        assert_eq!(layout.line_count, 3);
+        assert_eq!(
+            lines(&layout),
+            vec![vec![0.0, 5.0], vec![0.0, 5.0], vec![0.0]]
+        );
+    }
+
+    #[test]
+    fn aligns_lines() {
+        let font = font();
+        let layout = |align, max_width: Option<f32>| {
+            let mut style = style().with_align(align);
+            style.max_width = max_width;
+            lines(&font.layout("aa\na ", &style))
+        };
+
+        // Within the widest line
+        assert_eq!(
+            layout(TextAlign::Left, None),
+            vec![vec![0.0, 5.0], vec![0.0]]
+        );
+        assert_eq!(
+            layout(TextAlign::Center, None),
+            vec![vec![0.0, 5.0], vec![2.5]]
+        );
+        assert_eq!(
+            layout(TextAlign::Right, None),
+            vec![vec![0.0, 5.0], vec![5.0]]
+        );
+
+        // Within the wrap width
+        assert_eq!(
+            layout(TextAlign::Center, Some(20.0)),
+            vec![vec![5.0, 10.0], vec![7.5]]
+        );
+        assert_eq!(
+            layout(TextAlign::Right, Some(20.0)),
+            vec![vec![10.0, 15.0], vec![15.0]]
+        );
+    }
+
+    #[test]
+    fn falls_back_to_the_replacement_character() {
+        let mut font = font();
+        assert!(font.layout("b", &style()).glyphs.is_empty());
+
+        font.insert_glyph(
+            char::REPLACEMENT_CHARACTER,
+            0.5,
+            Some([0.0, 0.0, 0.5, 0.5]),
+            Some([16.0, 0.0, 32.0, 16.0]),
+        );
+        let layout = font.layout("ab", &style());
+        assert_eq!(lines(&layout), vec![vec![0.0, 5.0]]);
+        assert_eq!(layout.glyphs[1].character, 'b');
+    }
+}
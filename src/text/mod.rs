@@ -0,0 +1,572 @@
+//! Text rendering on top of signed distance field font atlases.
+//!
+//! A [`FontAtlas`] holds the glyph metrics of a font baked into a texture, eg. by
+//! [msdf-atlas-gen](https://github.com/Chlumsky/msdf-atlas-gen). The texture is registered with the renderer like any
+//! other texture, and [`crate::Painter::add_text`] draws one textured rect per glyph. Loading msdf-atlas-gen's JSON
+//! metrics requires the `serde` feature, its CSV glyph tables can always be loaded.
+//!
+//! With the `ttf` feature, a [`GlyphCache`] generates the glyph images from a TrueType/OpenType font at runtime instead.
+
+#[cfg(feature = "ttf")]
+mod glyph_cache;
+pub mod layout;
+#[cfg(feature = "ttf")]
+mod msdf;
+
+use std::collections::HashMap;
+
+use glam::{UVec2, Vec2};
+
+use crate::{
+    backend::texture::TextureDataFormat,
+    error::{Error, FontAtlasError},
+    shape::{ShapeFlags, TextureId},
+};
+
+#[cfg(feature = "ttf")]
+pub use glyph_cache::{GlyphCache, GlyphCacheOptions};
+pub use layout::{PositionedGlyph, TextAlign, TextLayout, TextStyle};
+
+/// What the glyph images in an atlas contain
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum GlyphImageKind {
+    /// Plain coverage masks, drawn like regular textures
+    Mask,
+    /// Single-channel signed distance fields
+    Sdf,
+    /// Multi-channel signed distance fields, optionally with the true distance in the alpha channel
+    #[default]
+    Mtsdf,
+}
+
+impl GlyphImageKind {
+    /// Shape flags that make the shader interpret the glyph images correctly
+    pub fn shape_flags(&self) -> ShapeFlags {
+        match self {
+            GlyphImageKind::Mask => ShapeFlags::empty(),
+            GlyphImageKind::Sdf => ShapeFlags::TEXTURE_SDF,
+            GlyphImageKind::Mtsdf => ShapeFlags::TEXTURE_MTSDF,
+        }
+    }
+
+    /// Texture format that holds glyph images of this kind
+    pub fn texture_format(&self) -> TextureDataFormat {
+        match self {
+            GlyphImageKind::Mask | GlyphImageKind::Mtsdf => TextureDataFormat::Rgba8,
+            GlyphImageKind::Sdf => TextureDataFormat::R8,
+        }
+    }
+}
+
+/// Layout of the atlas texture, corresponding to the `atlas` section of msdf-atlas-gen's metrics
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AtlasInfo {
+    pub kind: GlyphImageKind,
+    /// Width of the distance field range, in atlas pixels
+    pub distance_range: f32,
+    /// Size of one em in the atlas, in pixels
+    pub em_size: f32,
+    pub size: UVec2,
+    /// Whether atlas pixel coordinates start at the bottom of the texture, as with msdf-atlas-gen's default
+    pub y_origin_bottom: bool,
+}
+
+/// Vertical font metrics, in ems
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FontMetrics {
+    /// Distance between consecutive baselines
+    pub line_height: f32,
+    /// Distance from the baseline to the top of the tallest glyphs, positive upwards
+    pub ascender: f32,
+    /// Distance from the baseline to the bottom of the lowest glyphs, negative below the baseline
+    pub descender: f32,
+}
+
+/// A single glyph of a [`FontAtlas`]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Glyph {
+    /// Horizontal advance, in ems
+    pub advance: f32,
+    /// Glyph image placement relative to the pen position on the baseline, in ems and y-down.
+    /// `None` for glyphs without an image, like spaces.
+    pub plane: Option<(Vec2, Vec2)>,
+    /// Top-left and bottom-right corner of the glyph image in the atlas texture, in UV coordinates
+    pub uv: (Vec2, Vec2),
+}
+
+/// Glyph metrics and kerning of a font baked into an atlas texture
+#[derive(Clone, Debug)]
+pub struct FontAtlas {
+    pub texture_id: TextureId,
+    pub atlas: AtlasInfo,
+    pub metrics: FontMetrics,
+    glyphs: HashMap<char, Glyph>,
+    kerning: HashMap<(char, char), f32>,
+}
+
+impl FontAtlas {
+    /// Creates an empty font atlas. Glyphs are added with [`FontAtlas::insert_glyph`].
+    pub fn new(texture_id: TextureId, atlas: AtlasInfo, metrics: FontMetrics) -> Self {
+        Self {
+            texture_id,
+            atlas,
+            metrics,
+            glyphs: HashMap::new(),
+            kerning: HashMap::new(),
+        }
+    }
+
+    /// Loads the metrics written by `msdf-atlas-gen -json`, for the atlas image registered as `texture_id`.
+    ///
+    /// Glyphs must be identified by unicode code points, not glyph indices.
+    #[cfg(feature = "serde")]
+    pub fn from_msdf_json(json: &str, texture_id: TextureId) -> Result<Self, Error> {
+        let root: msdf_json::Root = serde_json::from_str(json).map_err(|e| {
+            Error::InvalidFontAtlas(FontAtlasError::Json {
+                line: e.line(),
+                column: e.column(),
+                message: e.to_string(),
+            })
+        })?;
+
+        let kind = match root.atlas.kind.as_str() {
+            "hardmask" | "softmask" => GlyphImageKind::Mask,
+            "sdf" | "psdf" => GlyphImageKind::Sdf,
+            "msdf" | "mtsdf" => GlyphImageKind::Mtsdf,
+            kind => {
+                return Err(invalid(FontAtlasError::UnsupportedAtlasType(
+                    kind.to_owned(),
+                )));
+            }
+        };
+        let y_origin_bottom = match root.atlas.y_origin.as_deref() {
+            None | Some("bottom") => true,
+            Some("top") => false,
+            Some(origin) => {
+                return Err(invalid(FontAtlasError::UnsupportedYOrigin(
+                    origin.to_owned(),
+                )));
+            }
+        };
+        let atlas = AtlasInfo {
+            kind,
+            distance_range: root.atlas.distance_range,
+            em_size: root.atlas.size,
+            size: UVec2::new(root.atlas.width, root.atlas.height),
+            y_origin_bottom,
+        };
+        check_atlas_size(&atlas)?;
+        let metrics = FontMetrics {
+            line_height: root.metrics.line_height,
+            ascender: root.metrics.ascender,
+            descender: root.metrics.descender,
+        };
+
+        let code_point = |unicode: u32| {
+            char::from_u32(unicode).ok_or(invalid(FontAtlasError::InvalidCodePoint {
+                code_point: unicode,
+                line: None,
+            }))
+        };
+        let mut font = Self::new(texture_id, atlas, metrics);
+        for glyph in root.glyphs {
+            font.insert_glyph(
+                code_point(glyph.unicode)?,
+                glyph.advance,
+                glyph.plane_bounds.map(Into::into),
+                glyph.atlas_bounds.map(Into::into),
+            );
+        }
+        for pair in root.kerning {
+            font.insert_kerning(
+                code_point(pair.unicode1)?,
+                code_point(pair.unicode2)?,
+                pair.advance,
+            );
+        }
+        Ok(font)
+    }
+
+    /// Loads the glyph table written by `msdf-atlas-gen -csv`. The CSV format doesn't include the atlas layout,
+    /// font metrics or kerning, so those have to be provided separately.
+    ///
+    /// Each row holds the code point, advance, plane bounds and atlas bounds, with the bounds as `left, bottom, right, top`.
+    pub fn from_msdf_csv(
+        csv: &str,
+        texture_id: TextureId,
+        atlas: AtlasInfo,
+        metrics: FontMetrics,
+    ) -> Result<Self, Error> {
+        check_atlas_size(&atlas)?;
+        let mut font = Self::new(texture_id, atlas, metrics);
+        for (line_index, line) in csv.lines().enumerate() {
+            let line_number = line_index + 1;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let values: Vec<f32> = line
+                .split(',')
+                .map(|v| v.trim().parse::<f32>())
+                .collect::<Result<_, _>>()
+                .map_err(|_| invalid(FontAtlasError::InvalidNumber { line: line_number }))?;
+            let &[unicode, advance, pl, pb, pr, pt, al, ab, ar, at] = values.as_slice() else {
+                return Err(invalid(FontAtlasError::WrongColumnCount {
+                    line: line_number,
+                    columns: values.len(),
+                }));
+            };
+            let unicode = char::from_u32(unicode as u32).ok_or(invalid(
+                FontAtlasError::InvalidCodePoint {
+                    code_point: unicode as u32,
+                    line: Some(line_number),
+                },
+            ))?;
+            // Glyphs without an image have all-zero bounds
+            let bounds = |b: [f32; 4]| b.iter().any(|v| *v != 0.0).then_some(b);
+            font.insert_glyph(
+                unicode,
+                advance,
+                bounds([pl, pb, pr, pt]),
+                bounds([al, ab, ar, at]),
+            );
+        }
+        Ok(font)
+    }
+
+    /// Adds a glyph. Bounds are given as `[left, bottom, right, top]`, the plane bounds in ems relative to the pen
+    /// position with y pointing up, and the atlas bounds in atlas pixels.
+    pub fn insert_glyph(
+        &mut self,
+        character: char,
+        advance: f32,
+        plane_bounds: Option<[f32; 4]>,
+        atlas_bounds: Option<[f32; 4]>,
+    ) {
+        let plane = plane_bounds
+            .map(|[left, bottom, right, top]| (Vec2::new(left, -top), Vec2::new(right, -bottom)));
+        let uv = match atlas_bounds {
+            Some([left, bottom, right, top]) => {
+                let size = self.atlas.size.as_vec2();
+                let (top, bottom) = if self.atlas.y_origin_bottom {
+                    (size.y - top, size.y - bottom)
+                } else {
+                    (top, bottom)
+                };
+                // msdf-atlas-gen bounds can be given in either vertical order
+                (
+                    Vec2::new(left, top.min(bottom)) / size,
+                    Vec2::new(right, top.max(bottom)) / size,
+                )
+            }
+            None => (Vec2::ZERO, Vec2::ZERO),
+        };
+        self.glyphs.insert(
+            character,
+            Glyph {
+                advance,
+                plane: plane.filter(|_| atlas_bounds.is_some()),
+                uv,
+            },
+        );
+    }
+
+    /// Sets the kerning adjustment between two characters, in ems
+    pub fn insert_kerning(&mut self, left: char, right: char, advance: f32) {
+        self.kerning.insert((left, right), advance);
+    }
+
+    pub fn glyph(&self, character: char) -> Option<&Glyph> {
+        self.glyphs.get(&character)
+    }
+
+    /// Kerning adjustment to the advance between two characters, in ems
+    pub fn kerning(&self, left: char, right: char) -> f32 {
+        self.kerning.get(&(left, right)).copied().unwrap_or(0.0)
+    }
+
+    /// Removes all glyphs and kerning pairs, keeping the atlas layout and metrics
+    #[cfg(feature = "ttf")]
+    pub(crate) fn clear_glyphs(&mut self) {
+        self.glyphs.clear();
+        self.kerning.clear();
+    }
+
+    /// Lays out the text without drawing it, eg. to measure it
+    pub fn layout(&self, text: &str, style: &TextStyle) -> TextLayout {
+        layout::layout(self, text, style)
+    }
+}
+
+fn invalid(reason: FontAtlasError) -> Error {
+    Error::InvalidFontAtlas(reason)
+}
+
+/// Atlas bounds are divided by the atlas size, so an empty atlas would turn all texture coordinates into NaN
+fn check_atlas_size(atlas: &AtlasInfo) -> Result<(), Error> {
+    if atlas.size.x == 0 || atlas.size.y == 0 {
+        return Err(invalid(FontAtlasError::EmptyAtlas));
+    }
+    Ok(())
+}
+
+/// The parts of msdf-atlas-gen's JSON metrics used by [`FontAtlas::from_msdf_json`]
+#[cfg(feature = "serde")]
+mod msdf_json {
+    use serde::Deserialize;
+
+    #[derive(Deserialize)]
+    pub struct Root {
+        pub atlas: Atlas,
+        pub metrics: Metrics,
+        pub glyphs: Vec<Glyph>,
+        #[serde(default)]
+        pub kerning: Vec<KerningPair>,
+    }
+
+    #[derive(Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct Atlas {
+        #[serde(rename = "type")]
+        pub kind: String,
+        pub distance_range: f32,
+        pub size: f32,
+        pub width: u32,
+        pub height: u32,
+        pub y_origin: Option<String>,
+    }
+
+    #[derive(Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct Metrics {
+        pub line_height: f32,
+        pub ascender: f32,
+        pub descender: f32,
+    }
+
+    #[derive(Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct Glyph {
+        pub unicode: u32,
+        pub advance: f32,
+        pub plane_bounds: Option<Bounds>,
+        pub atlas_bounds: Option<Bounds>,
+    }
+
+    #[derive(Deserialize)]
+    pub struct Bounds {
+        pub left: f32,
+        pub bottom: f32,
+        pub right: f32,
+        pub top: f32,
+    }
+
+    impl From<Bounds> for [f32; 4] {
+        fn from(b: Bounds) -> Self {
+            [b.left, b.bottom, b.right, b.top]
+        }
+    }
+
+    #[derive(Deserialize)]
+    pub struct KerningPair {
+        pub unicode1: u32,
+        pub unicode2: u32,
+        pub advance: f32,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(feature = "serde")]
+    const METRICS_JSON: &str = r#"{
+        "atlas": {
+            "type": "mtsdf",
+            "distanceRange": 4,
+            "size": 32,
+            "width": 64,
+            "height": 64,
+            "yOrigin": "bottom"
+        },
+        "metrics": { "emSize": 1, "lineHeight": 1.25, "ascender": 0.9, "descender": -0.25 },
+        "glyphs": [
+            { "unicode": 32, "advance": 0.25 },
+            {
+                "unicode": 65,
+                "advance": 0.625,
+                "planeBounds": { "left": 0, "bottom": -0.125, "right": 0.5, "top": 0.75 },
+                "atlasBounds": { "left": 16, "bottom": 32, "right": 32, "top": 56 }
+            }
+        ],
+        "kerning": [{ "unicode1": 65, "unicode2": 65, "advance": -0.0625 }]
+    }"#;
+
+    fn atlas_info() -> AtlasInfo {
+        AtlasInfo {
+            kind: GlyphImageKind::Mtsdf,
+            distance_range: 4.0,
+            em_size: 32.0,
+            size: UVec2::new(64, 64),
+            y_origin_bottom: true,
+        }
+    }
+
+    fn font_metrics() -> FontMetrics {
+        FontMetrics {
+            line_height: 1.25,
+            ascender: 0.9,
+            descender: -0.25,
+        }
+    }
+
+    /// Checks the space and `A` glyphs both test atlases describe
+    fn assert_glyphs(font: &FontAtlas) {
+        let space = font.glyph(' ').unwrap();
+        assert_eq!(space.advance, 0.25);
+        assert_eq!(space.plane, None);
+
+        // Plane bounds are flipped to y-down, atlas bounds to a top-left origin
+        let a = font.glyph('A').unwrap();
+        assert_eq!(a.advance, 0.625);
+        assert_eq!(
+            a.plane,
+            Some((Vec2::new(0.0, -0.75), Vec2::new(0.5, 0.125)))
+        );
+        assert_eq!(a.uv, (Vec2::new(0.25, 0.125), Vec2::new(0.5, 0.5)));
+        assert_eq!(font.glyph('B'), None);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn loads_msdf_json() {
+        let font = FontAtlas::from_msdf_json(METRICS_JSON, TextureId::default()).unwrap();
+        assert_eq!(font.atlas, atlas_info());
+        assert_eq!(font.metrics, font_metrics());
+        assert_glyphs(&font);
+        assert_eq!(font.kerning('A', 'A'), -0.0625);
+        assert_eq!(font.kerning('A', ' '), 0.0);
+
+        let without_kerning = METRICS_JSON.replace(
+            r#",
+        "kerning": [{ "unicode1": 65, "unicode2": 65, "advance": -0.0625 }]"#,
+            "",
+        );
+        let font = FontAtlas::from_msdf_json(&without_kerning, TextureId::default()).unwrap();
+        assert_eq!(font.kerning('A', 'A'), 0.0);
+    }
+
+    #[test]
+    fn loads_msdf_csv() {
+        let csv = "32,0.25,0,0,0,0,0,0,0,0\n\n65,0.625,0,-0.125,0.5,0.75,16,32,32,56\n";
+        let font =
+            FontAtlas::from_msdf_csv(csv, TextureId::default(), atlas_info(), font_metrics())
+                .unwrap();
+        assert_glyphs(&font);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn rejects_malformed_json() {
+        let load = |json: &str| FontAtlas::from_msdf_json(json, TextureId::default());
+        let is_invalid =
+            |result: Result<FontAtlas, Error>| matches!(result, Err(Error::InvalidFontAtlas(_)));
+
+        assert!(is_invalid(load("{\"atlas\": ")));
+        assert!(is_invalid(load("{}")));
+        assert!(is_invalid(load(&METRICS_JSON.replace("mtsdf", "bitmap"))));
+        assert!(is_invalid(load(
+            &METRICS_JSON.replace("\"yOrigin\": \"bottom\"", "\"yOrigin\": \"left\"")
+        )));
+        assert!(is_invalid(load(
+            &METRICS_JSON.replace("\"advance\": 0.25", "\"advance\": \"wide\"")
+        )));
+        assert!(is_invalid(load(
+            &METRICS_JSON.replace("\"unicode\": 65", "\"unicode\": 55296")
+        )));
+        assert!(is_invalid(load(
+            &METRICS_JSON.replace("\"kerning\": [", "\"kerning\": 1, \"pairs\": [")
+        )));
+        assert!(is_invalid(load(
+            &METRICS_JSON.replace("\"width\": 64", "\"width\": 0")
+        )));
+        assert!(is_invalid(load(
+            &METRICS_JSON.replace("\"height\": 64", "\"height\": 0")
+        )));
+        assert!(is_invalid(load(&"[".repeat(100_000))));
+
+        assert!(matches!(
+            load("{\"atlas\": "),
+            Err(Error::InvalidFontAtlas(FontAtlasError::Json {
+                line: 1,
+                ..
+            }))
+        ));
+        assert_eq!(
+            load(&METRICS_JSON.replace("mtsdf", "bitmap")).err(),
+            Some(Error::InvalidFontAtlas(
+                FontAtlasError::UnsupportedAtlasType("bitmap".to_string())
+            ))
+        );
+        assert_eq!(
+            load(&METRICS_JSON.replace("\"unicode\": 65", "\"unicode\": 55296")).err(),
+            Some(Error::InvalidFontAtlas(FontAtlasError::InvalidCodePoint {
+                code_point: 55296,
+                line: None
+            }))
+        );
+    }
+
+    #[cfg(feature = "ttf")]
+    #[test]
+    fn clears_glyphs() {
+        let mut font = FontAtlas::new(TextureId::default(), atlas_info(), font_metrics());
+        font.insert_glyph(
+            'A',
+            0.625,
+            Some([0.0, 0.0, 0.5, 0.75]),
+            Some([16.0, 32.0, 32.0, 56.0]),
+        );
+        font.insert_kerning('A', 'A', -0.0625);
+        font.clear_glyphs();
+        assert_eq!(font.glyph('A'), None);
+        assert_eq!(font.kerning('A', 'A'), 0.0);
+        assert_eq!(font.atlas, atlas_info());
+        assert_eq!(font.metrics, font_metrics());
+    }
+
+    #[test]
+    fn rejects_malformed_csv() {
+        let load = |csv: &str| {
+            FontAtlas::from_msdf_csv(csv, TextureId::default(), atlas_info(), font_metrics())
+        };
+        assert_eq!(
+            load("65,0.5,0,0,0,0,0,0,0\n").err(),
+            Some(Error::InvalidFontAtlas(FontAtlasError::WrongColumnCount {
+                line: 1,
+                columns: 9
+            }))
+        );
+        assert_eq!(
+            load("32,0.25,0,0,0,0,0,0,0,0\n65,0.5,a,0,0,0,0,0,0,0\n").err(),
+            Some(Error::InvalidFontAtlas(FontAtlasError::InvalidNumber {
+                line: 2
+            }))
+        );
+        assert_eq!(
+            load("55296,0.5,0,0,0,0,0,0,0,0").err(),
+            Some(Error::InvalidFontAtlas(FontAtlasError::InvalidCodePoint {
+                code_point: 55296,
+                line: Some(1)
+            }))
+        );
+
+        let empty = AtlasInfo {
+            size: UVec2::new(64, 0),
+            ..atlas_info()
+        };
+        assert_eq!(
+            FontAtlas::from_msdf_csv("", TextureId::default(), empty, font_metrics()).err(),
+            Some(Error::InvalidFontAtlas(FontAtlasError::EmptyAtlas))
+        );
+    }
+}
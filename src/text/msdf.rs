@@ -0,0 +1,573 @@
+//! CPU generation of signed distance fields from glyph outlines.
+//!
+//! Follows the approach of [msdfgen](https://github.com/Chlumsky/msdfgen): outline edges are colored so that every
+//! corner is formed by edges that share only one color channel, and each channel stores the signed pseudo-distance
+//! to the closest edge of that color. The median of the channels then reconstructs sharp corners. Curves are
+//! flattened into short line segments first, which keeps the distance computations simple.
+
+use glam::Vec2;
+use ttf_parser::{Face, GlyphId, OutlineBuilder};
+
+use super::GlyphImageKind;
+
+const RED: u8 = 1;
+const GREEN: u8 = 2;
+const BLUE: u8 = 4;
+const WHITE: u8 = RED | GREEN | BLUE;
+const CYAN: u8 = GREEN | BLUE;
+const MAGENTA: u8 = RED | BLUE;
+const YELLOW: u8 = RED | GREEN;
+
+/// Edges meeting at an angle sharper than this (in radians) form a corner
+const CORNER_ANGLE_THRESHOLD: f32 = 3.0;
+/// Maximum distance between a curve and its flattened segments, in pixels
+const FLATTEN_TOLERANCE: f32 = 0.02;
+
+/// A rendered glyph image
+pub(crate) struct GlyphBitmap {
+    pub width: u32,
+    pub height: u32,
+    /// Pixels in the format used for the glyph image kind, top row first
+    pub data: Vec<u8>,
+    /// Placement of the image relative to the pen position, as `[left, bottom, right, top]` in ems with y up
+    pub plane_bounds: [f32; 4],
+}
+
+/// Renders the glyph with one em spanning `em_size` pixels, and distances from `-range / 2` to `range / 2` pixels
+/// mapped to the full value range. Returns `None` for glyphs without an outline.
+pub(crate) fn generate(
+    face: &Face,
+    glyph: GlyphId,
+    kind: GlyphImageKind,
+    em_size: f32,
+    range: f32,
+) -> Option<GlyphBitmap> {
+    let bbox = face.glyph_bounding_box(glyph)?;
+    let units_per_em = face.units_per_em() as f32;
+    let scale = em_size / units_per_em;
+    let padding = (range * 0.5).ceil() + 1.0;
+    let width = ((bbox.x_max - bbox.x_min) as f32 * scale + 2.0 * padding).ceil() as u32;
+    let height = ((bbox.y_max - bbox.y_min) as f32 * scale + 2.0 * padding).ceil() as u32;
+
+    let mut builder = ContourBuilder {
+        contours: Vec::new(),
+        current: Vec::new(),
+        start: Vec2::ZERO,
+        last: Vec2::ZERO,
+        // Font units to pixels, flipping y so the first row is the top of the glyph
+        origin: Vec2::new(bbox.x_min as f32, bbox.y_max as f32),
+        scale: Vec2::new(scale, -scale),
+        padding,
+    };
+    face.outline_glyph(glyph, &mut builder)?;
+    builder.finish_contour();
+
+    let segments = color_and_flatten(&builder.contours);
+    if segments.is_empty() {
+        return None;
+    }
+
+    let bytes_per_pixel = kind.texture_format().bytes_per_pixel() as usize;
+    let mut data = vec![0; width as usize * height as usize * bytes_per_pixel];
+    let encode = |distance: f32| ((distance / range + 0.5).clamp(0.0, 1.0) * 255.0).round() as u8;
+    for y in 0..height {
+        for x in 0..width {
+            let p = Vec2::new(x as f32 + 0.5, y as f32 + 0.5);
+            let [r, g, b, a] = signed_distances(&segments, p);
+            let pixel = &mut data[(y * width + x) as usize * bytes_per_pixel..][..bytes_per_pixel];
+            match kind {
+                GlyphImageKind::Mtsdf => {
+                    pixel.copy_from_slice(&[encode(r), encode(g), encode(b), encode(a)]);
+                }
+                GlyphImageKind::Sdf => pixel[0] = encode(a),
+                GlyphImageKind::Mask => {
+                    let coverage = ((a + 0.5).clamp(0.0, 1.0) * 255.0).round() as u8;
+                    pixel.copy_from_slice(&[255, 255, 255, coverage]);
+                }
+            }
+        }
+    }
+
+    let left = bbox.x_min as f32 / units_per_em - padding / em_size;
+    let top = bbox.y_max as f32 / units_per_em + padding / em_size;
+    Some(GlyphBitmap {
+        width,
+        height,
+        data,
+        plane_bounds: [
+            left,
+            top - height as f32 / em_size,
+            left + width as f32 / em_size,
+            top,
+        ],
+    })
+}
+
+#[derive(Clone, Copy)]
+enum Edge {
+    Line(Vec2, Vec2),
+    Quad(Vec2, Vec2, Vec2),
+    Cubic(Vec2, Vec2, Vec2, Vec2),
+}
+
+impl Edge {
+    fn start_direction(&self) -> Vec2 {
+        let dir = match *self {
+            Edge::Line(a, b) => b - a,
+            Edge::Quad(a, c, b) => first_nonzero(&[c - a, b - a]),
+            Edge::Cubic(a, c1, c2, b) => first_nonzero(&[c1 - a, c2 - a, b - a]),
+        };
+        dir.normalize_or_zero()
+    }
+
+    fn end_direction(&self) -> Vec2 {
+        let dir = match *self {
+            Edge::Line(a, b) => b - a,
+            Edge::Quad(a, c, b) => first_nonzero(&[b - c, b - a]),
+            Edge::Cubic(a, c1, c2, b) => first_nonzero(&[b - c2, b - c1, b - a]),
+        };
+        dir.normalize_or_zero()
+    }
+
+    /// Appends the end points of line segments approximating the edge, excluding its start point
+    fn flatten(&self, out: &mut Vec<Vec2>) {
+        match *self {
+            Edge::Line(_, b) => out.push(b),
+            Edge::Quad(a, c, b) => {
+                let dd = (a - 2.0 * c + b).length();
+                let n = segment_count(dd / (4.0 * FLATTEN_TOLERANCE));
+                for i in 1..=n {
+                    let t = i as f32 / n as f32;
+                    let mt = 1.0 - t;
+                    out.push(mt * mt * a + 2.0 * mt * t * c + t * t * b);
+                }
+            }
+            Edge::Cubic(a, c1, c2, b) => {
+                let dd = (a - 2.0 * c1 + c2)
+                    .length()
+                    .max((c1 - 2.0 * c2 + b).length());
+                let n = segment_count(3.0 * dd / (4.0 * FLATTEN_TOLERANCE));
+                for i in 1..=n {
+                    let t = i as f32 / n as f32;
+                    let mt = 1.0 - t;
+                    out.push(
+                        mt * mt * mt * a
+                            + 3.0 * mt * mt * t * c1
+                            + 3.0 * mt * t * t * c2
+                            + t * t * t * b,
+                    );
+                }
+            }
+        }
+    }
+}
+
+fn first_nonzero(candidates: &[Vec2]) -> Vec2 {
+    candidates
+        .iter()
+        .copied()
+        .find(|v| *v != Vec2::ZERO)
+        .unwrap_or(Vec2::ZERO)
+}
+
+/// Number of segments for which the flattening error is `1 / n^2` of `error_scale`
+fn segment_count(error_scale: f32) -> u32 {
+    (error_scale.sqrt().ceil() as u32).clamp(1, 64)
+}
+
+struct ContourBuilder {
+    contours: Vec<Vec<Edge>>,
+    current: Vec<Edge>,
+    start: Vec2,
+    last: Vec2,
+    origin: Vec2,
+    scale: Vec2,
+    padding: f32,
+}
+
+impl ContourBuilder {
+    fn map(&self, x: f32, y: f32) -> Vec2 {
+        (Vec2::new(x, y) - self.origin) * self.scale + self.padding
+    }
+
+    fn push(&mut self, edge: Edge, end: Vec2) {
+        // Skip degenerate edges, which have no direction
+        let degenerate = match edge {
+            Edge::Line(a, b) => a == b,
+            Edge::Quad(a, c, b) => a == c && c == b,
+            Edge::Cubic(a, c1, c2, b) => a == c1 && c1 == c2 && c2 == b,
+        };
+        if !degenerate {
+            self.current.push(edge);
+        }
+        self.last = end;
+    }
+
+    fn finish_contour(&mut self) {
+        if self.last != self.start {
+            self.push(Edge::Line(self.last, self.start), self.start);
+        }
+        if !self.current.is_empty() {
+            self.contours.push(std::mem::take(&mut self.current));
+        }
+    }
+}
+
+impl OutlineBuilder for ContourBuilder {
+    fn move_to(&mut self, x: f32, y: f32) {
+        self.finish_contour();
+        self.start = self.map(x, y);
+        self.last = self.start;
+    }
+
+    fn line_to(&mut self, x: f32, y: f32) {
+        let p = self.map(x, y);
+        self.push(Edge::Line(self.last, p), p);
+    }
+
+    fn quad_to(&mut self, x1: f32, y1: f32, x: f32, y: f32) {
+        let (c, p) = (self.map(x1, y1), self.map(x, y));
+        self.push(Edge::Quad(self.last, c, p), p);
+    }
+
+    fn curve_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, x: f32, y: f32) {
+        let (c1, c2, p) = (self.map(x1, y1), self.map(x2, y2), self.map(x, y));
+        self.push(Edge::Cubic(self.last, c1, c2, p), p);
+    }
+
+    fn close(&mut self) {
+        self.finish_contour();
+    }
+}
+
+struct Segment {
+    a: Vec2,
+    b: Vec2,
+    color: u8,
+}
+
+/// Assigns edge colors like msdfgen's simple edge coloring, and flattens the contours into colored segments
+fn color_and_flatten(contours: &[Vec<Edge>]) -> Vec<Segment> {
+    let cross_threshold = CORNER_ANGLE_THRESHOLD.sin();
+    let mut segments = Vec::new();
+
+    for contour in contours {
+        let n = contour.len();
+        let is_corner: Vec<bool> = (0..n)
+            .map(|i| {
+                let a = contour[(i + n - 1) % n].end_direction();
+                let b = contour[i].start_direction();
+                a.dot(b) <= 0.0 || a.perp_dot(b).abs() > cross_threshold
+            })
+            .collect();
+
+        // Flatten, remembering which segments start at a corner
+        let mut points = Vec::new();
+        let mut corners = Vec::new();
+        let start = match contour[0] {
+            Edge::Line(a, _) | Edge::Quad(a, ..) | Edge::Cubic(a, ..) => a,
+        };
+        for (edge, &corner) in contour.iter().zip(&is_corner) {
+            corners.push(corner);
+            edge.flatten(&mut points);
+            corners.resize(points.len(), false);
+        }
+        let count = points.len();
+        let segment_start = |i: usize| if i == 0 { start } else { points[i - 1] };
+
+        let corner_indices: Vec<usize> = (0..count).filter(|&i| corners[i]).collect();
+        let colors: Vec<u8> = match corner_indices.as_slice() {
+            // Smooth contour
+            [] => vec![WHITE; count],
+            // Teardrop, split into three parts so the corner is still formed by two colors
+            [corner] if count >= 3 => (0..count)
+                .map(|i| {
+                    let k = (i + count - corner) % count;
+                    [MAGENTA, WHITE, YELLOW][k * 3 / count]
+                })
+                .collect(),
+            [_] => vec![WHITE; count],
+            [first, ..] => {
+                let spline_count = corner_indices.len();
+                let mut colors = vec![0; count];
+                let mut spline = 0;
+                for k in 0..count {
+                    let i = (first + k) % count;
+                    if k > 0 && corners[i] {
+                        spline += 1;
+                    }
+                    let mut color = [CYAN, MAGENTA, YELLOW][spline % 3];
+                    // The last spline also neighbors the first one
+                    if spline == spline_count - 1 && color == CYAN {
+                        color = MAGENTA;
+                    }
+                    colors[i] = color;
+                }
+                colors
+            }
+        };
+
+        for i in 0..count {
+            let (a, b) = (segment_start(i), points[i]);
+            if a != b {
+                segments.push(Segment {
+                    a,
+                    b,
+                    color: colors[i],
+                });
+            }
+        }
+    }
+
+    // Normalize the orientation so the inside of the shape is on the positive side of the segments
+    let area: f32 = segments.iter().map(|s| s.a.perp_dot(s.b)).sum();
+    if area < 0.0 {
+        for segment in &mut segments {
+            std::mem::swap(&mut segment.a, &mut segment.b);
+        }
+    }
+    segments
+}
+
+/// Closest segment of a color channel
+#[derive(Clone, Copy)]
+struct Closest {
+    distance: f32,
+    /// How parallel the segment is to the direction towards the point, to break ties at shared end points
+    orthogonality: f32,
+    index: usize,
+    t: f32,
+}
+
+/// Signed pseudo-distances for the red, green and blue channels, and the true signed distance. Positive inside.
+fn signed_distances(segments: &[Segment], p: Vec2) -> [f32; 4] {
+    let none = Closest {
+        distance: f32::INFINITY,
+        orthogonality: 1.0,
+        index: usize::MAX,
+        t: 0.0,
+    };
+    let mut closest = [none; 3];
+    let mut min_distance = f32::INFINITY;
+    let mut winding = 0;
+
+    for (index, segment) in segments.iter().enumerate() {
+        let (a, b) = (segment.a, segment.b);
+        let d = b - a;
+        let t = (p - a).dot(d) / d.length_squared();
+        let nearest = a + d * t.clamp(0.0, 1.0);
+        let distance = p.distance(nearest);
+        let orthogonality = if (0.0..=1.0).contains(&t) {
+            0.0
+        } else {
+            d.normalize().dot((p - nearest).normalize_or_zero()).abs()
+        };
+        min_distance = min_distance.min(distance);
+
+        for (channel, bit) in [RED, GREEN, BLUE].into_iter().enumerate() {
+            let c = &mut closest[channel];
+            if segment.color & bit != 0
+                && (distance < c.distance
+                    || (distance == c.distance && orthogonality < c.orthogonality))
+            {
+                *c = Closest {
+                    distance,
+                    orthogonality,
+                    index,
+                    t,
+                };
+            }
+        }
+
+        let side = d.perp_dot(p - a);
+        if a.y <= p.y && b.y > p.y && side > 0.0 {
+            winding += 1;
+        } else if b.y <= p.y && a.y > p.y && side < 0.0 {
+            winding -= 1;
+        }
+    }
+
+    let inside = winding != 0;
+    let true_distance = if inside { min_distance } else { -min_distance };
+
+    let mut channels = [true_distance; 3];
+    for (channel, c) in closest.iter().enumerate() {
+        if c.index == usize::MAX {
+            continue;
+        }
+        let segment = &segments[c.index];
+        let dir = (segment.b - segment.a).normalize();
+        let side = dir.perp_dot(p - segment.a);
+        let mut distance = c.distance.copysign(side);
+        // Past the ends of the segment, extend it along its direction so corners stay sharp
+        let end = if c.t < 0.0 {
+            Some(segment.a)
+        } else if c.t > 1.0 {
+            Some(segment.b)
+        } else {
+            None
+        };
+        if let Some(end) = end {
+            let pseudo = dir.perp_dot(p - end);
+            if pseudo.abs() <= distance.abs() {
+                distance = pseudo;
+            }
+        }
+        channels[channel] = distance;
+    }
+
+    // Fall back to the true distance where the channels disagree with the actual inside test, eg. where contours
+    // overlap
+    let [r, g, b] = channels;
+    let median = r.min(g).max(r.max(g).min(b));
+    if (median > 0.0) != inside {
+        channels = [true_distance; 3];
+    }
+    [channels[0], channels[1], channels[2], true_distance]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Builds colored segments from outline commands given in pixels
+    fn segments(outline: impl FnOnce(&mut ContourBuilder)) -> Vec<Segment> {
+        let mut builder = ContourBuilder {
+            contours: Vec::new(),
+            current: Vec::new(),
+            start: Vec2::ZERO,
+            last: Vec2::ZERO,
+            origin: Vec2::ZERO,
+            scale: Vec2::ONE,
+            padding: 0.0,
+        };
+        outline(&mut builder);
+        builder.finish_contour();
+        color_and_flatten(&builder.contours)
+    }
+
+    fn square(builder: &mut ContourBuilder) {
+        builder.move_to(0.0, 0.0);
+        builder.line_to(10.0, 0.0);
+        builder.line_to(10.0, 10.0);
+        builder.line_to(0.0, 10.0);
+        builder.close();
+    }
+
+    /// Circle of radius 10 around (20, 20), from four cubic arcs
+    fn circle(builder: &mut ContourBuilder) {
+        let k = 10.0 * 0.552_284_8;
+        builder.move_to(30.0, 20.0);
+        builder.curve_to(30.0, 20.0 + k, 20.0 + k, 30.0, 20.0, 30.0);
+        builder.curve_to(20.0 - k, 30.0, 10.0, 20.0 + k, 10.0, 20.0);
+        builder.curve_to(10.0, 20.0 - k, 20.0 - k, 10.0, 20.0, 10.0);
+        builder.curve_to(20.0 + k, 10.0, 30.0, 20.0 - k, 30.0, 20.0);
+        builder.close();
+    }
+
+    fn median([r, g, b, _]: [f32; 4]) -> f32 {
+        r.min(g).max(r.max(g).min(b))
+    }
+
+    #[test]
+    fn square_distances() {
+        let segments = segments(square);
+        for (p, expected) in [
+            (Vec2::new(5.0, 5.0), 5.0),
+            (Vec2::new(5.0, 2.0), 2.0),
+            (Vec2::new(9.0, 5.0), 1.0),
+            (Vec2::new(15.0, 5.0), -5.0),
+            (Vec2::new(5.0, -3.0), -3.0),
+            (Vec2::new(13.0, 14.0), -5.0),
+        ] {
+            let distances = signed_distances(&segments, p);
+            assert!(
+                (distances[3] - expected).abs() < 1e-4,
+                "true distance at {p} is {}, expected {expected}",
+                distances[3]
+            );
+            assert_eq!(median(distances) > 0.0, expected > 0.0, "sign at {p}");
+        }
+
+        // Outside a corner, the median is the distance to the farther of the extended edges, so the reconstructed
+        // corner stays sharp while the true distance is rounded
+        let distances = signed_distances(&segments, Vec2::new(12.0, 13.0));
+        assert_eq!(median(distances), -3.0);
+        assert!((distances[3] + 13f32.sqrt()).abs() < 1e-4);
+    }
+
+    #[test]
+    fn orientation_does_not_change_the_sign() {
+        let reversed = segments(|builder| {
+            builder.move_to(0.0, 0.0);
+            builder.line_to(0.0, 10.0);
+            builder.line_to(10.0, 10.0);
+            builder.line_to(10.0, 0.0);
+            builder.close();
+        });
+        let forward = segments(square);
+        for p in [
+            Vec2::new(5.0, 5.0),
+            Vec2::new(12.0, 5.0),
+            Vec2::new(-1.0, -2.0),
+        ] {
+            let (reversed, forward) = (
+                signed_distances(&reversed, p),
+                signed_distances(&forward, p),
+            );
+            assert_eq!(reversed[3], forward[3]);
+            assert_eq!(median(reversed), median(forward));
+        }
+    }
+
+    #[test]
+    fn circle_distances() {
+        let segments = segments(circle);
+        for (p, expected) in [
+            (Vec2::new(20.0, 20.0), 10.0),
+            (Vec2::new(25.0, 20.0), 5.0),
+            (Vec2::new(20.0, 35.0), -5.0),
+            (Vec2::new(20.0 + 15.0 * 0.6, 20.0 - 15.0 * 0.8), -5.0),
+            (Vec2::new(0.0, 0.0), 10.0 - 800f32.sqrt()),
+        ] {
+            let distances = signed_distances(&segments, p);
+            for distance in distances {
+                assert!(
+                    (distance - expected).abs() < 0.05,
+                    "distance at {p} is {distances:?}, expected {expected}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn smooth_contours_are_white() {
+        let segments = segments(circle);
+        assert!(segments.len() > 4);
+        assert!(segments.iter().all(|s| s.color == WHITE));
+    }
+
+    #[test]
+    fn sharp_corners_have_different_colors() {
+        let segments = segments(square);
+        assert_eq!(segments.len(), 4);
+        for (i, segment) in segments.iter().enumerate() {
+            let next = &segments[(i + 1) % segments.len()];
+            assert_ne!(segment.color, next.color);
+            // Exactly one channel is shared, so the median of the channels forms the corner
+            assert_eq!((segment.color & next.color).count_ones(), 1);
+        }
+    }
+
+    #[test]
+    fn teardrop_corner_has_different_colors() {
+        let segments = segments(|builder| {
+            builder.move_to(0.0, 0.0);
+            builder.curve_to(20.0, 0.0, 20.0, 20.0, 0.0, 0.0);
+            builder.close();
+        });
+        let (first, last) = (&segments[0], &segments[segments.len() - 1]);
+        assert_ne!(first.color, last.color);
+        assert_eq!((first.color & last.color).count_ones(), 1);
+    }
+}
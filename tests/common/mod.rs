@@ -0,0 +1,21 @@
+//! Helpers shared by the tests running on the GPU
+
+/// A device on a software adapter of one of the given backends, or `None` if there is none, in which case GPU tests
+/// are skipped
+pub async fn software_device(backends: wgpu::Backends) -> Option<(wgpu::Device, wgpu::Queue)> {
+    let mut descriptor = wgpu::InstanceDescriptor::from_env_or_default();
+    descriptor.backends &= backends;
+    let instance = wgpu::Instance::new(&descriptor);
+    let adapter = instance
+        .request_adapter(&wgpu::RequestAdapterOptions {
+            power_preference: wgpu::PowerPreference::LowPower,
+            compatible_surface: None,
+            force_fallback_adapter: true,
+        })
+        .await
+        .ok()?;
+    adapter
+        .request_device(&wgpu::DeviceDescriptor::default())
+        .await
+        .ok()
+}
@@ -0,0 +1,164 @@
+//! Checks that redrawing only the damaged parts of a frame into a persistent target gives the same image as rendering
+//! the whole frame. Rendering runs on a software adapter, the test is skipped without one. OpenGL adapters are left out,
+//! as GLSL can't sample a texture with the renderer's several samplers.
+
+use glam::Vec4;
+use mondrian::{
+    Painter, RetainedScene,
+    backend::wgpu::{PersistentTarget, WgpuRenderer},
+};
+
+mod common;
+
+use common::software_device;
+
+const RESOLUTION: (u32, u32) = (256, 192);
+const FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8Unorm;
+
+fn paint(painter: &mut Painter, moving_x: f32) {
+    painter.add_filled_rect(
+        (0.0, 0.0),
+        (256.0, 192.0),
+        0.0,
+        Vec4::new(0.1, 0.1, 0.15, 1.0),
+    );
+    painter.add_filled_circle((128.0, 96.0), 40.0, Vec4::new(0.2, 0.6, 1.0, 1.0));
+    painter
+        .add_filled_rect(
+            (moving_x, 20.0),
+            (moving_x + 40.0, 60.0),
+            6.0,
+            Vec4::new(1.0, 0.5, 0.1, 0.8),
+        )
+        .with_glow(Vec4::ONE.truncate(), 8.0);
+    painter.add_filled_line((10.0, 180.0), (240.0, 150.0), 2.0, Vec4::ONE);
+}
+
+#[test]
+fn damaged_render_matches_full_render() {
+    let Some((device, queue)) =
+        pollster::block_on(software_device(wgpu::Backends::all() - wgpu::Backends::GL))
+    else {
+        eprintln!("No software adapter available, skipping damaged rendering comparison");
+        return;
+    };
+
+    let mut scene = RetainedScene::new();
+    let mut renderer = WgpuRenderer::new(&device, FORMAT);
+    let target = PersistentTarget::new(&device, RESOLUTION, FORMAT);
+    let mut draw_scene = |scene: &mut RetainedScene, moving_x: f32| {
+        paint(scene.start(RESOLUTION), moving_x);
+        let mut encoder = device.create_command_encoder(&Default::default());
+        let mut damage = None;
+        scene.finish(|shapes, binner, frame_damage| {
+            renderer.prepare_damaged(&device, &queue, shapes, binner, frame_damage);
+            // Everything outside the damaged regions is kept from the previous frame
+            let load = if frame_damage.full {
+                wgpu::LoadOp::Clear(wgpu::Color::BLACK)
+            } else {
+                wgpu::LoadOp::Load
+            };
+            let mut pass = begin_pass(&mut encoder, target.view(), load);
+            renderer.render_damaged(&mut pass, binner, frame_damage);
+            damage = Some(frame_damage.clone());
+        });
+        queue.submit(Some(encoder.finish()));
+        damage.unwrap()
+    };
+
+    assert!(draw_scene(&mut scene, 20.0).full);
+    let damage = draw_scene(&mut scene, 180.0);
+    // Only the tiles around the old and new position of the moved rect are redrawn
+    assert!(!damage.full);
+    assert_eq!(damage.shapes, 2..3);
+    let dirty_tiles: u32 = damage
+        .dirty_rects
+        .iter()
+        .map(|rect| rect.width * rect.height)
+        .sum();
+    assert!(
+        dirty_tiles > 0 && dirty_tiles < 8 * 6 / 2,
+        "{dirty_tiles} tiles redrawn"
+    );
+    let damaged = read_texture(&device, &queue, target.texture());
+
+    let mut painter = Painter::new();
+    let mut renderer = WgpuRenderer::new(&device, FORMAT);
+    let reference = PersistentTarget::new(&device, RESOLUTION, FORMAT);
+    painter.start(RESOLUTION);
+    paint(&mut painter, 180.0);
+    let mut encoder = device.create_command_encoder(&Default::default());
+    painter.finish(|shapes, binner| {
+        renderer.prepare(&device, &queue, shapes, binner);
+        let mut pass = begin_pass(
+            &mut encoder,
+            reference.view(),
+            wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+        );
+        renderer.render(&mut pass);
+    });
+    queue.submit(Some(encoder.finish()));
+    let full = read_texture(&device, &queue, reference.texture());
+
+    for (i, (damaged, full)) in damaged.chunks(4).zip(full.chunks(4)).enumerate() {
+        let (x, y) = (i as u32 % RESOLUTION.0, i as u32 / RESOLUTION.0);
+        assert!(
+            damaged.iter().zip(full).all(|(a, b)| a.abs_diff(*b) <= 1),
+            "pixel ({x}, {y}) is {damaged:?} in the damaged render and {full:?} in the full render"
+        );
+    }
+}
+
+fn begin_pass<'a>(
+    encoder: &'a mut wgpu::CommandEncoder,
+    view: &'a wgpu::TextureView,
+    load: wgpu::LoadOp<wgpu::Color>,
+) -> wgpu::RenderPass<'a> {
+    encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+        label: None,
+        color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+            view,
+            resolve_target: None,
+            ops: wgpu::Operations {
+                load,
+                store: wgpu::StoreOp::Store,
+            },
+            depth_slice: None,
+        })],
+        depth_stencil_attachment: None,
+        occlusion_query_set: None,
+        timestamp_writes: None,
+    })
+}
+
+/// Reads back an RGBA8 texture, row by row without padding
+fn read_texture(device: &wgpu::Device, queue: &wgpu::Queue, texture: &wgpu::Texture) -> Vec<u8> {
+    let (width, height) = RESOLUTION;
+    // Rows of 256 pixels already meet the copy alignment of 256 bytes
+    let bytes_per_row = width * 4;
+    let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label: None,
+        size: (bytes_per_row * height) as u64,
+        usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+    });
+    let mut encoder = device.create_command_encoder(&Default::default());
+    encoder.copy_texture_to_buffer(
+        texture.as_image_copy(),
+        wgpu::TexelCopyBufferInfo {
+            buffer: &buffer,
+            layout: wgpu::TexelCopyBufferLayout {
+                offset: 0,
+                bytes_per_row: Some(bytes_per_row),
+                rows_per_image: None,
+            },
+        },
+        texture.size(),
+    );
+    queue.submit(Some(encoder.finish()));
+
+    let slice = buffer.slice(..);
+    slice.map_async(wgpu::MapMode::Read, |result| result.unwrap());
+    device.poll(wgpu::PollType::wait_indefinitely()).unwrap();
+    slice.get_mapped_range().to_vec()
+}
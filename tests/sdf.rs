@@ -0,0 +1,272 @@
+//! Checks that the CPU distance functions in `mondrian::sdf` match the shader's `sdf.wgsl`, by evaluating both at
+//! random points for random primitives. The shader side runs on a software adapter, the test is skipped without one.
+
+use glam::{Vec2, vec2};
+use mondrian::{CornerRadius, Primitive};
+use wgpu::util::DeviceExt;
+
+mod common;
+
+use common::software_device;
+
+const SAMPLES_PER_KIND: usize = 2048;
+/// Floats per sample: primitive kind, point, 8 primitive parameters and padding
+const SAMPLE_STRIDE: usize = 12;
+
+const TEST_SHADER: &str = r#"
+@group(0) @binding(0)
+var<storage, read> samples: array<f32>;
+@group(0) @binding(1)
+var<storage, read_write> distances: array<f32>;
+
+@compute @workgroup_size(64)
+fn main(@builtin(global_invocation_id) id: vec3<u32>) {
+    if id.x >= arrayLength(&distances) {
+        return;
+    }
+    let base = id.x * 12u;
+    let p = vec2<f32>(samples[base + 1u], samples[base + 2u]);
+    var q: array<f32, 8>;
+    for (var i = 0u; i < 8u; i++) {
+        q[i] = samples[base + 3u + i];
+    }
+
+    var d = 0.0;
+    switch u32(samples[base]) {
+        case 0u: {
+            d = sd_circle(p - vec2(q[0], q[1]), q[2]);
+        }
+        case 1u: {
+            d = sd_triangle(p, vec2(q[0], q[1]), vec2(q[2], q[3]), vec2(q[4], q[5]));
+        }
+        case 2u: {
+            d = sd_rounded_rect(p - vec2(q[0], q[1]), vec2(q[2], q[3]), vec4(q[4], q[5], q[6], q[7]));
+        }
+        case 3u: {
+            d = sd_line(p, vec2(q[0], q[1]), vec2(q[2], q[3]));
+        }
+        case 4u: {
+            d = sd_sector(p - vec2(q[0], q[1]), q[2], q[3], q[4], q[5]);
+        }
+        default: {
+            let v = array<vec2<f32>, 4>(vec2(q[0], q[1]), vec2(q[2], q[3]), vec2(q[4], q[5]), vec2(q[6], q[7]));
+            d = sd_quad(p, v);
+        }
+    }
+    distances[id.x] = d;
+}
+"#;
+
+#[test]
+fn cpu_distances_match_shader() {
+    let Some((device, queue)) = pollster::block_on(software_device(wgpu::Backends::all())) else {
+        eprintln!("No software adapter available, skipping shader comparison");
+        return;
+    };
+
+    let mut rng = fastrand::Rng::with_seed(0x5df);
+    let mut samples = Vec::new();
+    for kind in 0..6 {
+        for _ in 0..SAMPLES_PER_KIND {
+            let primitive = random_primitive(&mut rng, kind);
+            let point = random_point(&mut rng, 150.0);
+            samples.push((primitive, point));
+        }
+    }
+
+    let gpu_distances = evaluate_shader(&device, &queue, &samples);
+    for ((primitive, point), gpu) in samples.iter().zip(gpu_distances) {
+        let cpu = primitive.distance(*point);
+        // Transcendental functions in shaders are allowed to be slightly less precise than on the CPU
+        let tolerance = 1e-2 + 1e-4 * cpu.abs();
+        assert!(
+            (cpu - gpu).abs() <= tolerance,
+            "{primitive:?} at {point}: CPU distance {cpu}, shader distance {gpu}"
+        );
+    }
+}
+
+fn random_point(rng: &mut fastrand::Rng, extent: f32) -> Vec2 {
+    vec2(
+        (rng.f32() * 2.0 - 1.0) * extent,
+        (rng.f32() * 2.0 - 1.0) * extent,
+    )
+}
+
+fn random_primitive(rng: &mut fastrand::Rng, kind: u32) -> Primitive {
+    use std::f32::consts::TAU;
+
+    match kind {
+        0 => Primitive::Circle {
+            center: random_point(rng, 100.0),
+            radius: rng.f32() * 80.0,
+        },
+        1 => Primitive::Triangle {
+            p1: random_point(rng, 100.0),
+            p2: random_point(rng, 100.0),
+            p3: random_point(rng, 100.0),
+        },
+        2 => {
+            let half_extents = vec2(rng.f32() * 80.0 + 1.0, rng.f32() * 80.0 + 1.0);
+            let center = random_point(rng, 100.0);
+            let max_radius = half_extents.min_element();
+            let mut radius = || rng.f32() * max_radius;
+            Primitive::Rect {
+                center,
+                half_extents,
+                corner_radius: CornerRadius {
+                    top_left: radius(),
+                    top_right: radius(),
+                    bottom_right: radius(),
+                    bottom_left: radius(),
+                },
+            }
+        }
+        3 => Primitive::Line {
+            p1: random_point(rng, 100.0),
+            p2: random_point(rng, 100.0),
+        },
+        4 => {
+            let radius_inner = rng.f32() * 40.0;
+            Primitive::CircleSector {
+                center: random_point(rng, 100.0),
+                radius_inner,
+                radius_outer: radius_inner + rng.f32() * 60.0 + 1.0,
+                angle_start: (rng.f32() * 2.0 - 1.0) * TAU,
+                angle_end: (rng.f32() * 2.0 - 1.0) * TAU,
+            }
+        }
+        _ => Primitive::PolyQuad {
+            points: std::array::from_fn(|_| random_point(rng, 100.0)),
+        },
+    }
+}
+
+/// Flattens a primitive into the sample layout read by the test shader
+fn push_sample(data: &mut Vec<f32>, primitive: &Primitive, point: Vec2) {
+    let (kind, params): (u32, Vec<f32>) = match *primitive {
+        Primitive::Circle { center, radius } => (0, vec![center.x, center.y, radius]),
+        Primitive::Triangle { p1, p2, p3 } => (1, vec![p1.x, p1.y, p2.x, p2.y, p3.x, p3.y]),
+        Primitive::Rect {
+            center,
+            half_extents,
+            corner_radius,
+        } => (
+            2,
+            vec![
+                center.x,
+                center.y,
+                half_extents.x,
+                half_extents.y,
+                corner_radius.top_left,
+                corner_radius.top_right,
+                corner_radius.bottom_right,
+                corner_radius.bottom_left,
+            ],
+        ),
+        Primitive::Line { p1, p2 } => (3, vec![p1.x, p1.y, p2.x, p2.y]),
+        Primitive::CircleSector {
+            center,
+            radius_inner,
+            radius_outer,
+            angle_start,
+            angle_end,
+        } => (
+            4,
+            vec![
+                center.x,
+                center.y,
+                radius_inner,
+                radius_outer,
+                angle_start,
+                angle_end,
+            ],
+        ),
+        Primitive::PolyQuad { points } => (5, points.iter().flat_map(|p| [p.x, p.y]).collect()),
+    };
+
+    let start = data.len();
+    data.extend([kind as f32, point.x, point.y]);
+    data.extend(params);
+    data.resize(start + SAMPLE_STRIDE, 0.0);
+}
+
+fn evaluate_shader(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    samples: &[(Primitive, Vec2)],
+) -> Vec<f32> {
+    let mut data = Vec::with_capacity(samples.len() * SAMPLE_STRIDE);
+    for (primitive, point) in samples {
+        push_sample(&mut data, primitive, *point);
+    }
+
+    let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some("SDF test shader"),
+        source: wgpu::ShaderSource::Wgsl(
+            [include_str!("../src/backend/sdf.wgsl"), TEST_SHADER]
+                .join("\n")
+                .into(),
+        ),
+    });
+    let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+        label: Some("SDF test pipeline"),
+        layout: None,
+        module: &shader,
+        entry_point: Some("main"),
+        compilation_options: Default::default(),
+        cache: None,
+    });
+
+    let output_size = (samples.len() * size_of::<f32>()) as u64;
+    let sample_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("SDF test samples"),
+        contents: bytemuck::cast_slice(&data),
+        usage: wgpu::BufferUsages::STORAGE,
+    });
+    let distance_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("SDF test distances"),
+        size: output_size,
+        usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+        mapped_at_creation: false,
+    });
+    let readback_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("SDF test readback"),
+        size: output_size,
+        usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    });
+    let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("SDF test bind group"),
+        layout: &pipeline.get_bind_group_layout(0),
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: sample_buffer.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: distance_buffer.as_entire_binding(),
+            },
+        ],
+    });
+
+    let mut encoder = device.create_command_encoder(&Default::default());
+    {
+        let mut pass = encoder.begin_compute_pass(&Default::default());
+        pass.set_pipeline(&pipeline);
+        pass.set_bind_group(0, &bind_group, &[]);
+        pass.dispatch_workgroups((samples.len() as u32).div_ceil(64), 1, 1);
+    }
+    encoder.copy_buffer_to_buffer(&distance_buffer, 0, &readback_buffer, 0, output_size);
+    queue.submit([encoder.finish()]);
+
+    let slice = readback_buffer.slice(..);
+    slice.map_async(wgpu::MapMode::Read, |result| {
+        result.expect("Failed to map readback buffer")
+    });
+    device
+        .poll(wgpu::PollType::wait_indefinitely())
+        .expect("Failed to wait for the GPU");
+    bytemuck::cast_slice(&slice.get_mapped_range()).to_vec()
+}